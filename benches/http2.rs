@@ -0,0 +1,61 @@
+//! Compares the throughput of HTTP/1.1 and HTTP/2 with `ReqwestHttpClient`
+//! against the local test server.
+//!
+//! Run with `cargo bench --features reqwest-backend --bench http2`.
+
+use mightybadger::ReqwestHttpClient;
+use mightybadger_test_server::sync::TestServer;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+const THREADS: usize = 8;
+const NOTICES_PER_THREAD: usize = 200;
+
+fn run(name: &str, client: reqwest::blocking::Client, port: u16) {
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+        config.http_client = Some(Arc::new(ReqwestHttpClient::with_client(client)));
+    });
+    let start = Instant::now();
+    let threads = (0..THREADS)
+        .map(|_| {
+            thread::spawn(|| {
+                let error = "x".parse::<i32>().unwrap_err();
+                for _ in 0..NOTICES_PER_THREAD {
+                    mightybadger::notify_std_error(&error);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    let notices = THREADS * NOTICES_PER_THREAD;
+    println!(
+        "{}: {} notices in {:.2?} ({:.0} notices/s)",
+        name,
+        notices,
+        elapsed,
+        notices as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    let server = TestServer::new();
+    let port = server.addr().port();
+    let http1 = reqwest::blocking::Client::builder()
+        .http1_only()
+        .build()
+        .unwrap();
+    let http2 = reqwest::blocking::Client::builder()
+        .http2_prior_knowledge()
+        .build()
+        .unwrap();
+    run("HTTP/1.1", http1, port);
+    run("HTTP/2", http2, port);
+}
@@ -1,6 +1,7 @@
 use std::env;
 
 fn main() {
+    println!("cargo:rustc-check-cfg=cfg(tokio_unstable)");
     println!(
         "cargo:rustc-env=HONEYBADGER_CLIENT_ARCH={}",
         env::var("TARGET").unwrap()
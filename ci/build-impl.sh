@@ -9,6 +9,23 @@ if [[ ${MINVER:-false} = true ]]; then
 fi
 
 cargo build --examples --verbose
-cargo test --verbose
+cargo test --workspace --verbose
+cargo test --features test-server --verbose
+cargo test --features macros --verbose
+cargo test --features signal --verbose
+cargo test --features regex --verbose
+cargo test --features log --verbose
+cargo test --features log-filter --verbose
+cargo test --features tokio --verbose
+cargo test --features vault --verbose
+cargo test --features reqwest-backend --verbose
+cargo test --features anyhow,eyre --verbose
+cargo test --features validate --verbose
+if [[ ${MSRV:-false} = false ]]; then
+  # The cloud SDKs need a newer Rust than `rust-version`.
+  cargo test --features aws-secrets --verbose
+  cargo test --features azure-keyvault --verbose
+fi
+cargo build --no-default-features --features wasm --verbose
 
 cp Cargo.toml.bak Cargo.toml
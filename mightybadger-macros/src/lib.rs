@@ -0,0 +1,212 @@
+//! Procedural macros for mightybadger.
+//!
+//! Use them through the `macros` feature of `mightybadger`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    bracketed, parse_macro_input, Attribute, Data, DeriveInput, Ident, ItemFn, LitStr, ReturnType,
+    Token,
+};
+
+/// Reports errors returned from the function to Honeybadger.
+///
+/// When the function returns `Err(e)`, `e` is sent with
+/// `mightybadger::notify_std_error` before being returned as usual.
+/// The error type must implement `std::error::Error + 'static`.
+/// Works on both `fn` and `async fn`.
+///
+/// ## Example
+///
+/// ```ignore
+/// #[mightybadger::with_error_context(tags = ["config"])]
+/// fn read_config() -> std::io::Result<String> {
+///     std::fs::read_to_string("config.toml")
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn with_error_context(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as Args);
+    let item = parse_macro_input!(item as ItemFn);
+    expand(args, item).into()
+}
+
+/// Arguments of `#[with_error_context]`.
+#[derive(Default)]
+struct Args {
+    tags: Vec<LitStr>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut args = Args::default();
+        while !input.is_empty() {
+            let name: Ident = input.parse()?;
+            if name != "tags" {
+                return Err(syn::Error::new(name.span(), "expected `tags`"));
+            }
+            input.parse::<Token![=]>()?;
+            let content;
+            bracketed!(content in input);
+            let tags = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+            args.tags.extend(tags);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn expand(args: Args, item: ItemFn) -> TokenStream2 {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+    if let ReturnType::Default = sig.output {
+        return syn::Error::new_spanned(&sig, "the function must return a `Result`")
+            .to_compile_error();
+    }
+    // The return type is not spelled out anywhere: it may contain
+    // `impl Trait`, which is only allowed in the signature.
+    let result = if sig.asyncness.is_some() {
+        quote! { async move #block.await }
+    } else {
+        // Called through `FnOnce` so that the closure may return borrows of
+        // what it captures, e.g. `&mut self.field`, as the function could.
+        quote! {
+            {
+                fn __mightybadger_call_once<R, F: ::std::ops::FnOnce() -> R>(f: F) -> R {
+                    f()
+                }
+                __mightybadger_call_once(move || #block)
+            }
+        }
+    };
+    let tags = &args.tags;
+    let notify = if tags.is_empty() {
+        quote! { ::mightybadger::notify_std_error(__mightybadger_error) }
+    } else {
+        quote! { ::mightybadger::notify_std_error_with_tags(__mightybadger_error, &[#(#tags),*]) }
+    };
+    quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __mightybadger_result = #result;
+            if let ::std::result::Result::Err(ref __mightybadger_error) = __mightybadger_result {
+                #notify;
+            }
+            __mightybadger_result
+        }
+    }
+}
+
+/// Implements `mightybadger::HoneybadgerMetadata` for an error type.
+///
+/// The class name is the path of the type, e.g. `app::billing::ChargeError`.
+/// Tags are given with `#[honeybadger(tag = "...")]` on the type and,
+/// for enums, on each variant. Register the type with
+/// `mightybadger::metadata::register` (for `Fail`) or
+/// `mightybadger::metadata::register_std_error` (for `std::error::Error`)
+/// so that notices use them.
+///
+/// ## Example
+///
+/// ```ignore
+/// #[derive(Debug, mightybadger::HoneybadgerMetadata)]
+/// #[honeybadger(tag = "payment")]
+/// enum ChargeError {
+///     Declined,
+///     #[honeybadger(tag = "fraud")]
+///     Fraud { score: u32 },
+/// }
+/// ```
+#[proc_macro_derive(HoneybadgerMetadata, attributes(honeybadger))]
+pub fn derive_honeybadger_metadata(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    expand_metadata(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Collects the tags of `#[honeybadger(tag = "...")]` attributes.
+fn honeybadger_tags(attrs: &[Attribute]) -> syn::Result<Vec<LitStr>> {
+    let mut tags = Vec::new();
+    for attr in attrs {
+        if !attr.path().is_ident("honeybadger") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                tags.push(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `tag`"))
+            }
+        })?;
+    }
+    Ok(tags)
+}
+
+fn expand_metadata(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let class_name = name.to_string();
+    let tags = honeybadger_tags(&input.attrs)?;
+    let variant_tags = match input.data {
+        Data::Enum(ref data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let variant_name = &variant.ident;
+                let tags = honeybadger_tags(&variant.attrs)?;
+                if !tags.is_empty() {
+                    arms.push(quote! {
+                        #name::#variant_name { .. } => {
+                            tags.extend([#(#tags),*].iter().map(|tag| tag.to_string()));
+                        }
+                    });
+                }
+            }
+            if arms.is_empty() {
+                quote! {}
+            } else {
+                quote! {
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        #(#arms)*
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Data::Struct(_) => quote! {},
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input,
+                "HoneybadgerMetadata cannot be derived for unions",
+            ));
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::mightybadger::HoneybadgerMetadata for #name #ty_generics #where_clause {
+            fn class_name(&self) -> &str {
+                ::std::concat!(::std::module_path!(), "::", #class_name)
+            }
+
+            fn tags(&self) -> ::std::vec::Vec<::std::string::String> {
+                #[allow(unused_mut)]
+                let mut tags: ::std::vec::Vec<::std::string::String> =
+                    ::std::vec![#(::std::string::String::from(#tags)),*];
+                #variant_tags
+                tags
+            }
+        }
+    })
+}
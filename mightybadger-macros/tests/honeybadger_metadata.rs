@@ -0,0 +1,64 @@
+// `failure_derive` expands to impls nested in anonymous consts.
+#![allow(non_local_definitions)]
+
+use std::fmt;
+use std::sync::Arc;
+
+use failure::Fail;
+use mightybadger::testing::FakeReporter;
+use mightybadger::HoneybadgerMetadata;
+
+#[derive(Debug, HoneybadgerMetadata)]
+#[honeybadger(tag = "payment")]
+enum ChargeError {
+    Declined,
+    #[honeybadger(tag = "fraud", tag = "review")]
+    Fraud {
+        score: u32,
+    },
+}
+
+impl fmt::Display for ChargeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChargeError::Declined => f.write_str("card declined"),
+            ChargeError::Fraud { score } => write!(f, "fraud suspected ({})", score),
+        }
+    }
+}
+
+impl std::error::Error for ChargeError {}
+
+#[derive(Debug, Fail, HoneybadgerMetadata)]
+#[fail(display = "ledger unavailable")]
+struct LedgerError;
+
+#[test]
+fn test_honeybadger_metadata() {
+    assert_eq!(
+        ChargeError::Declined.class_name(),
+        "honeybadger_metadata::ChargeError"
+    );
+    assert_eq!(ChargeError::Declined.tags(), ["payment"]);
+    assert_eq!(
+        ChargeError::Fraud { score: 90 }.tags(),
+        ["payment", "fraud", "review"]
+    );
+    assert!(LedgerError.tags().is_empty());
+
+    let reporter = Arc::new(FakeReporter::new());
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(reporter.clone());
+    });
+    mightybadger::metadata::register_std_error::<ChargeError>();
+    mightybadger::metadata::register::<LedgerError>();
+
+    mightybadger::notify_std_error_with_tags(&ChargeError::Fraud { score: 90 }, &["fraud"]);
+    mightybadger::notify(&LedgerError);
+    let payloads = reporter.payloads();
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].error.class, "honeybadger_metadata::ChargeError");
+    assert_eq!(payloads[0].error.tags, ["fraud", "payment", "review"]);
+    assert_eq!(payloads[1].error.class, "honeybadger_metadata::LedgerError");
+}
@@ -0,0 +1,85 @@
+use std::io;
+use std::num::ParseIntError;
+use std::sync::Arc;
+
+use mightybadger::testing::FakeReporter;
+
+#[mightybadger::with_error_context]
+fn parse(s: &str) -> Result<i32, ParseIntError> {
+    let x = s.parse::<i32>()?;
+    Ok(x * 2)
+}
+
+#[mightybadger::with_error_context(tags = ["parser", "async"])]
+async fn parse_async(s: &str) -> Result<i32, ParseIntError> {
+    if s.is_empty() {
+        return Ok(0);
+    }
+    s.parse()
+}
+
+#[mightybadger::with_error_context]
+fn digits(s: &str) -> Result<impl Iterator<Item = u8> + '_, ParseIntError> {
+    s.parse::<u64>()?;
+    Ok(s.bytes().map(|b| b - b'0'))
+}
+
+struct Buffer {
+    bytes: Vec<u8>,
+}
+
+impl Buffer {
+    #[mightybadger::with_error_context]
+    fn get_mut(&mut self) -> Result<&mut Vec<u8>, io::Error> {
+        if self.bytes.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "empty buffer"));
+        }
+        Ok(&mut self.bytes)
+    }
+
+    #[mightybadger::with_error_context]
+    async fn get_mut_async(&mut self) -> Result<&mut Vec<u8>, io::Error> {
+        self.get_mut()
+    }
+}
+
+#[test]
+fn test_with_error_context() {
+    let reporter = Arc::new(FakeReporter::new());
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(reporter.clone());
+    });
+
+    assert_eq!(parse("21"), Ok(42));
+    assert!(reporter.payloads().is_empty());
+
+    assert!(parse("x").is_err());
+    let payloads = reporter.payloads();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].error.class, "std::num::ParseIntError");
+    assert!(payloads[0].error.tags.is_empty());
+    reporter.clear();
+
+    assert_eq!(futures::executor::block_on(parse_async("")), Ok(0));
+    assert!(futures::executor::block_on(parse_async("x")).is_err());
+    let payloads = reporter.payloads();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].error.tags, vec!["parser", "async"]);
+    reporter.clear();
+
+    assert_eq!(digits("42").unwrap().collect::<Vec<_>>(), [4, 2]);
+    assert!(digits("x").is_err());
+    let mut buffer = Buffer { bytes: vec![1] };
+    buffer.get_mut().unwrap().push(2);
+    futures::executor::block_on(buffer.get_mut_async())
+        .unwrap()
+        .push(3);
+    assert_eq!(buffer.bytes, [1, 2, 3]);
+    buffer.bytes.clear();
+    assert!(buffer.get_mut().is_err());
+    let payloads = reporter.payloads();
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].error.class, "std::num::ParseIntError");
+    assert_eq!(payloads[1].error.message, "empty buffer");
+}
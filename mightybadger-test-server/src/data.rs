@@ -1,19 +1,104 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 #[derive(Debug, Default)]
 pub struct ErrorData {
     pub errors: Vec<Payload>,
+    /// Respond to notices with `201 Created` and an empty body.
+    pub empty_response: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct Payload {
     #[serde(default)]
     pub error: ErrorPayload,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<RequestPayload>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct ErrorPayload {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub token: Option<Uuid>,
+    #[serde(default)]
+    pub class: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtrace: Option<Vec<BacktraceFrame>>,
+    #[serde(default)]
+    pub causes: Vec<CausePayload>,
+}
+
+impl ErrorPayload {
+    /// Classes of the causes, outermost first.
+    pub fn cause_classes(&self) -> Vec<&str> {
+        self.causes
+            .iter()
+            .map(|cause| cause.class.as_str())
+            .collect()
+    }
+
+    /// Messages of the causes, outermost first.
+    pub fn cause_messages(&self) -> Vec<&str> {
+        self.causes
+            .iter()
+            .map(|cause| cause.message.as_str())
+            .collect()
+    }
+
+    /// Returns `true` if a frame of the backtrace has a method containing `method`.
+    pub fn has_frame(&self, method: &str) -> bool {
+        has_frame(&self.backtrace, method)
+    }
+
+    /// Panics unless the messages of the error and its causes are `messages`,
+    /// outermost first.
+    pub fn assert_cause_chain(&self, messages: &[&str]) {
+        let actual = std::iter::once(self.message.as_str())
+            .chain(self.causes.iter().map(|cause| cause.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, messages, "unexpected cause chain of {:#?}", self);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CausePayload {
+    #[serde(default)]
+    pub class: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtrace: Option<Vec<BacktraceFrame>>,
+}
+
+impl CausePayload {
+    /// Returns `true` if a frame of the backtrace has a method containing `method`.
+    pub fn has_frame(&self, method: &str) -> bool {
+        has_frame(&self.backtrace, method)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BacktraceFrame {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub method: String,
+}
+
+fn has_frame(backtrace: &Option<Vec<BacktraceFrame>>, method: &str) -> bool {
+    backtrace
+        .iter()
+        .flatten()
+        .any(|frame| frame.method.contains(method))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RequestPayload {
+    #[serde(default)]
+    pub context: HashMap<String, serde_json::Value>,
 }
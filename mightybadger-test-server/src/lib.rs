@@ -1,29 +1,102 @@
 use futures::channel::oneshot;
 use hyper::server::Server;
 use std::net::SocketAddr;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use tokio::task::JoinHandle;
 
-pub use crate::data::ErrorData;
+pub use crate::data::{
+    BacktraceFrame, CausePayload, ErrorData, ErrorPayload, Payload, RequestPayload,
+};
+pub use hyper::StatusCode;
 
 mod data;
 mod service;
 pub mod sync;
+pub mod testing;
+
+/// Counts requests to `/v1/notices`.
+#[derive(Debug, Default)]
+pub(crate) struct RequestCounter {
+    /// Requests being processed.
+    pub(crate) pending: AtomicUsize,
+    /// Requests whose processing has finished.
+    pub(crate) completed: AtomicUsize,
+}
+
+/// Marks a request as pending until dropped.
+pub(crate) struct PendingRequest<'a>(&'a RequestCounter);
+
+impl<'a> PendingRequest<'a> {
+    pub(crate) fn new(counter: &'a RequestCounter) -> Self {
+        counter.pending.fetch_add(1, Ordering::SeqCst);
+        PendingRequest(counter)
+    }
+}
+
+impl Drop for PendingRequest<'_> {
+    fn drop(&mut self) {
+        self.0.completed.fetch_add(1, Ordering::SeqCst);
+        self.0.pending.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Behavior of a [`TestServer`](struct.TestServer.html) for a test scenario.
+#[derive(Debug, Clone, Default)]
+pub struct TestServerConfig {
+    /// Rejects notices with `403 Forbidden` unless `X-API-Key` is this key.
+    pub require_api_key: Option<String>,
+    /// Statuses to respond to the first notices with, one per notice,
+    /// before responding with `201 Created` as usual.
+    /// Notices answered with an error status are not recorded.
+    pub initial_status_queue: Vec<StatusCode>,
+    /// `Retry-After` header of the responses with an error status
+    /// from `initial_status_queue`, e.g. `"1"`.
+    pub retry_after: Option<String>,
+    /// Delay before responding to each notice, in milliseconds.
+    /// The async server needs a runtime with the time driver enabled for it.
+    pub response_delay_ms: u64,
+}
+
+/// Settings and mutable state shared with the service.
+#[derive(Debug)]
+pub(crate) struct ServerState {
+    pub(crate) config: TestServerConfig,
+    /// The rest of `config.initial_status_queue`.
+    pub(crate) status_queue: Mutex<Vec<StatusCode>>,
+}
 
 #[derive(Debug)]
 pub struct TestServer {
     data: Arc<RwLock<ErrorData>>,
+    counter: Arc<RequestCounter>,
+    state: Arc<ServerState>,
     addr: SocketAddr,
     start_shutdown: Option<oneshot::Sender<()>>,
     task: Option<JoinHandle<()>>,
 }
 
+impl Default for TestServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TestServer {
     pub fn new() -> Self {
+        Self::with_config(TestServerConfig::default())
+    }
+
+    pub fn with_config(config: TestServerConfig) -> Self {
         let data = Arc::new(RwLock::new(ErrorData::default()));
+        let counter = Arc::new(RequestCounter::default());
+        let state = Arc::new(ServerState {
+            status_queue: Mutex::new(config.initial_status_queue.clone()),
+            config,
+        });
 
         let addr = "127.0.0.1:0".parse::<SocketAddr>().unwrap();
-        let service = crate::service::Service::new(&data);
+        let service = crate::service::Service::new(&data, &counter, &state);
         let server = Server::bind(&addr).serve(service);
         let addr = server.local_addr();
 
@@ -37,6 +110,8 @@ impl TestServer {
 
         Self {
             data,
+            counter,
+            state,
             addr,
             start_shutdown: Some(tx),
             task: Some(task),
@@ -47,10 +122,31 @@ impl TestServer {
         &self.data
     }
 
+    pub fn config(&self) -> &TestServerConfig {
+        &self.state.config
+    }
+
+    /// Forgets the recorded notices and refills the status queue,
+    /// so that the server can be reused by another test.
+    pub fn reset(&self) {
+        self.data.write().unwrap().errors.clear();
+        *self.state.status_queue.lock().unwrap() = self.state.config.initial_status_queue.clone();
+    }
+
     pub fn addr(&self) -> SocketAddr {
         self.addr
     }
 
+    /// Number of notice requests currently being processed.
+    pub fn pending(&self) -> usize {
+        self.counter.pending.load(Ordering::SeqCst)
+    }
+
+    /// Number of notice requests processed so far, including rejected ones.
+    pub fn completed(&self) -> usize {
+        self.counter.completed.load(Ordering::SeqCst)
+    }
+
     pub fn start_shutdown(&mut self) {
         if let Some(start_shutdown) = self.start_shutdown.take() {
             start_shutdown.send(()).ok();
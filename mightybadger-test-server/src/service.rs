@@ -7,19 +7,30 @@ use hyper::service::Service as TowerService;
 use hyper::{Body, Method, Request, Response, StatusCode};
 use std::convert::Infallible;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use uuid::Uuid;
 
 use crate::data::Payload;
-use crate::ErrorData;
+use crate::{ErrorData, PendingRequest, RequestCounter, ServerState};
 
 #[derive(Debug, Clone)]
 pub(crate) struct Service {
     data: Arc<RwLock<ErrorData>>,
+    counter: Arc<RequestCounter>,
+    state: Arc<ServerState>,
 }
 
 impl Service {
-    pub(crate) fn new(data: &Arc<RwLock<ErrorData>>) -> Self {
-        Self { data: data.clone() }
+    pub(crate) fn new(
+        data: &Arc<RwLock<ErrorData>>,
+        counter: &Arc<RequestCounter>,
+        state: &Arc<ServerState>,
+    ) -> Self {
+        Self {
+            data: data.clone(),
+            counter: counter.clone(),
+            state: state.clone(),
+        }
     }
 
     async fn serve(&self, req: Request<Body>) -> Response<Body> {
@@ -45,6 +56,35 @@ impl Service {
     }
 
     async fn create_notice(&self, mut req: Request<Body>) -> Response<Body> {
+        let _pending = PendingRequest::new(&self.counter);
+        let config = &self.state.config;
+        if config.response_delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(config.response_delay_ms)).await;
+        }
+        if let Some(ref api_key) = config.require_api_key {
+            let given = req.headers().get("X-API-Key");
+            if given.and_then(|given| given.to_str().ok()) != Some(api_key.as_str()) {
+                return Response::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .body(Body::empty())
+                    .unwrap();
+            }
+        }
+        let status = {
+            let mut status_queue = self.state.status_queue.lock().unwrap();
+            if status_queue.is_empty() {
+                None
+            } else {
+                Some(status_queue.remove(0))
+            }
+        };
+        if let Some(status) = status.filter(|status| !status.is_success()) {
+            let mut builder = Response::builder().status(status);
+            if let Some(ref retry_after) = config.retry_after {
+                builder = builder.header(hyper::header::RETRY_AFTER, retry_after.as_str());
+            }
+            return builder.body(Body::empty()).unwrap();
+        }
         let body = std::mem::replace(req.body_mut(), Body::empty());
         let body = if let Ok(body) = body::to_bytes(body).await {
             body
@@ -63,9 +103,16 @@ impl Service {
                 .unwrap();
         };
         let uuid = body.error.token.unwrap_or_else(Uuid::new_v4);
-        {
+        let empty_response = {
             let mut data = self.data.write().unwrap();
             data.errors.push(body);
+            data.empty_response
+        };
+        if empty_response {
+            return Response::builder()
+                .status(StatusCode::CREATED)
+                .body(Body::empty())
+                .unwrap();
         }
         Response::builder()
             .status(StatusCode::CREATED)
@@ -2,10 +2,11 @@ use std::net::SocketAddr;
 use std::sync::mpsc;
 use std::sync::{Arc, RwLock};
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use tokio::runtime;
 
-pub use crate::ErrorData;
 use crate::TestServer as AsyncTestServer;
+pub use crate::{ErrorData, TestServerConfig};
 
 #[derive(Debug)]
 pub struct TestServer {
@@ -13,16 +14,27 @@ pub struct TestServer {
     thread: Option<JoinHandle<()>>,
 }
 
+impl Default for TestServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TestServer {
     pub fn new() -> Self {
+        Self::with_config(TestServerConfig::default())
+    }
+
+    pub fn with_config(config: TestServerConfig) -> Self {
         let rt = runtime::Builder::new_current_thread()
             .enable_io()
+            .enable_time()
             .build()
             .unwrap();
         let (tx, rx) = mpsc::sync_channel(0);
         let thread = thread::spawn(move || {
             rt.block_on(async move {
-                let mut inner = AsyncTestServer::new();
+                let mut inner = AsyncTestServer::with_config(config);
                 let waiter = inner.take_shutdown().unwrap();
                 tx.send(inner).ok();
                 waiter.await.ok();
@@ -39,10 +51,55 @@ impl TestServer {
         self.inner.data()
     }
 
+    pub fn config(&self) -> &TestServerConfig {
+        self.inner.config()
+    }
+
+    /// Forgets the recorded notices and refills the status queue,
+    /// so that the server can be reused by another test.
+    pub fn reset(&self) {
+        self.inner.reset();
+    }
+
     pub fn addr(&self) -> SocketAddr {
         self.inner.addr()
     }
 
+    /// Number of notice requests currently being processed.
+    pub fn pending(&self) -> usize {
+        self.inner.pending()
+    }
+
+    /// Number of notice requests processed so far, including rejected ones.
+    pub fn completed(&self) -> usize {
+        self.inner.completed()
+    }
+
+    /// Blocks until no notice request is being processed.
+    ///
+    /// This doesn't cover requests which haven't reached the server yet;
+    /// use [`wait_for_notices`](#method.wait_for_notices) if the number of
+    /// notices is known.
+    pub fn drain_pending(&self) {
+        while self.pending() > 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Blocks until at least `count` notice requests have been processed.
+    ///
+    /// Returns `false` if it didn't happen within `timeout`.
+    pub fn wait_for_notices(&self, count: usize, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.completed() < count {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+
     pub fn start_shutdown(&mut self) {
         self.inner.start_shutdown();
     }
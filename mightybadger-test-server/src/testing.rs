@@ -0,0 +1,77 @@
+//! Assertions for tests using the async [`TestServer`](../struct.TestServer.html).
+//!
+//! They poll the server with `tokio::time::sleep`, so they can be awaited in
+//! `#[tokio::test]` tests without blocking the runtime serving the notices.
+//! The runtime needs the time driver enabled.
+
+use std::time::Duration;
+
+use tokio::time::{sleep, Instant};
+
+use crate::TestServer;
+
+/// Interval between polls of the recorded notices.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Returns the classes of the notices recorded so far.
+fn recorded_classes(server: &TestServer) -> Vec<String> {
+    let data = server.data().read().unwrap();
+    data.errors
+        .iter()
+        .map(|payload| payload.error.class.clone())
+        .collect()
+}
+
+/// Waits until the server has recorded exactly `n` notices.
+///
+/// ## Panics
+///
+/// Panics if more than `n` notices are recorded, or if fewer than `n` are
+/// recorded within `timeout`, listing the classes of the recorded notices.
+pub async fn drain_and_assert_count(server: &TestServer, n: usize, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let classes = recorded_classes(server);
+        if classes.len() > n {
+            panic!(
+                "expected {} notices, but {} were recorded: {:?}",
+                n,
+                classes.len(),
+                classes
+            );
+        } else if classes.len() == n && server.pending() == 0 {
+            return;
+        } else if Instant::now() >= deadline {
+            panic!(
+                "expected {} notices within {:?}, but {} were recorded: {:?}",
+                n,
+                timeout,
+                classes.len(),
+                classes
+            );
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Waits until the server has recorded a notice of the error class `class`.
+///
+/// ## Panics
+///
+/// Panics if no such notice is recorded within `timeout`, listing the
+/// classes of the recorded notices.
+pub async fn drain_and_assert_class(server: &TestServer, class: &str, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let classes = recorded_classes(server);
+        if classes.iter().any(|recorded| recorded == class) {
+            return;
+        } else if Instant::now() >= deadline {
+            panic!(
+                "expected a notice of class {} within {:?}, but got: {:?}",
+                class, timeout, classes
+            );
+        }
+        sleep(POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,69 @@
+//! Configuration from AWS Secrets Manager.
+//!
+//! This module is available with the `aws-secrets` feature.
+
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_secretsmanager::error::SdkError;
+use aws_sdk_secretsmanager::operation::get_secret_value::GetSecretValueError;
+use failure::{Backtrace, Fail};
+use serde_derive::Deserialize;
+
+use crate::config::configure;
+
+/// Error occurred during [`configure_from_secrets_manager`][configure_from_secrets_manager].
+///
+/// [configure_from_secrets_manager]: fn.configure_from_secrets_manager.html
+#[derive(Debug, Fail)]
+pub enum SecretsManagerError {
+    #[fail(display = "could not fetch the secret")]
+    FetchFailed(#[cause] Box<SdkError<GetSecretValueError>>, Backtrace),
+    #[fail(display = "the secret is not a string")]
+    NotAString(Backtrace),
+    #[fail(display = "could not parse the secret")]
+    ParseFailed(#[cause] serde_json::Error, Backtrace),
+}
+
+#[derive(Deserialize)]
+struct Secret {
+    api_key: String,
+}
+
+/// Sets the API key from a secret stored in AWS Secrets Manager.
+///
+/// The secret must be a JSON string of the form `{"api_key": "..."}`.
+/// AWS credentials are resolved from the default provider chain.
+///
+/// ## Example
+///
+/// ```no_run
+/// # async fn example() -> Result<(), mightybadger::SecretsManagerError> {
+/// mightybadger::configure_from_secrets_manager("prod/honeybadger", "us-east-1").await?;
+/// mightybadger::setup();
+/// # Ok(())
+/// # }
+/// ```
+pub async fn configure_from_secrets_manager(
+    secret_id: &str,
+    region: &str,
+) -> Result<(), SecretsManagerError> {
+    let aws_config = aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new(region.to_owned()))
+        .load()
+        .await;
+    let client = aws_sdk_secretsmanager::Client::new(&aws_config);
+    let output = client
+        .get_secret_value()
+        .secret_id(secret_id)
+        .send()
+        .await
+        .map_err(|e| SecretsManagerError::FetchFailed(Box::new(e), Backtrace::new()))?;
+    let secret = output
+        .secret_string()
+        .ok_or_else(|| SecretsManagerError::NotAString(Backtrace::new()))?;
+    let secret = serde_json::from_str::<Secret>(secret)
+        .map_err(|e| SecretsManagerError::ParseFailed(e, Backtrace::new()))?;
+    configure(|config| {
+        config.api_key = Some(secret.api_key);
+    });
+    Ok(())
+}
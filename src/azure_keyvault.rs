@@ -0,0 +1,106 @@
+//! Configuration from Azure Key Vault.
+//!
+//! This module is available with the `azure-keyvault` feature.
+
+use azure_core::error::ErrorKind;
+use azure_core::http::StatusCode;
+use azure_identity::ManagedIdentityCredential;
+use azure_security_keyvault_secrets::SecretClient;
+use failure::{Backtrace, Fail};
+
+use crate::config::configure;
+
+/// Error occurred during [`configure_from_azure_keyvault`][configure_from_azure_keyvault].
+///
+/// [configure_from_azure_keyvault]: fn.configure_from_azure_keyvault.html
+#[derive(Debug, Fail)]
+pub enum AzureKeyVaultError {
+    /// No token could be obtained from the managed identity, or the
+    /// identity is not allowed to read the secret.
+    #[fail(display = "could not authenticate to Key Vault")]
+    AuthenticationFailed(#[cause] azure_core::Error, Backtrace),
+    #[fail(display = "could not fetch the secret")]
+    FetchFailed(#[cause] azure_core::Error, Backtrace),
+    #[fail(display = "the secret has no value")]
+    NoValue(Backtrace),
+}
+
+impl From<azure_core::Error> for AzureKeyVaultError {
+    fn from(e: azure_core::Error) -> Self {
+        let auth = match e.kind() {
+            ErrorKind::Credential => true,
+            ErrorKind::HttpResponse { status, .. } => {
+                *status == StatusCode::Unauthorized || *status == StatusCode::Forbidden
+            }
+            _ => false,
+        };
+        if auth {
+            AzureKeyVaultError::AuthenticationFailed(e, Backtrace::new())
+        } else {
+            AzureKeyVaultError::FetchFailed(e, Backtrace::new())
+        }
+    }
+}
+
+/// Sets the API key from a secret stored in Azure Key Vault.
+///
+/// The value of the secret `secret_name` in the vault at `vault_url`
+/// (e.g. `https://my-vault.vault.azure.net/`) is used as the API key.
+/// The managed identity of the Azure VM or Function is used to authenticate.
+///
+/// ## Example
+///
+/// ```no_run
+/// # async fn example() -> Result<(), mightybadger::AzureKeyVaultError> {
+/// mightybadger::configure_from_azure_keyvault(
+///     "https://my-vault.vault.azure.net/",
+///     "honeybadger-api-key",
+/// )
+/// .await?;
+/// mightybadger::setup();
+/// # Ok(())
+/// # }
+/// ```
+pub async fn configure_from_azure_keyvault(
+    vault_url: &str,
+    secret_name: &str,
+) -> Result<(), AzureKeyVaultError> {
+    let credential = ManagedIdentityCredential::new(None)?;
+    let client = SecretClient::new(vault_url, credential, None)?;
+    let secret = client.get_secret(secret_name, None).await?.into_model()?;
+    let api_key = secret
+        .value
+        .ok_or_else(|| AzureKeyVaultError::NoValue(Backtrace::new()))?;
+    configure(|config| {
+        config.api_key = Some(api_key);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        let e = azure_core::Error::with_message(ErrorKind::Credential, "no token");
+        assert!(matches!(
+            AzureKeyVaultError::from(e),
+            AzureKeyVaultError::AuthenticationFailed(..)
+        ));
+        let e = azure_core::Error::from(ErrorKind::HttpResponse {
+            status: StatusCode::Forbidden,
+            error_code: None,
+            raw_response: None,
+        });
+        assert!(matches!(
+            AzureKeyVaultError::from(e),
+            AzureKeyVaultError::AuthenticationFailed(..)
+        ));
+        let e = azure_core::Error::with_message(ErrorKind::Connection, "refused");
+        assert!(matches!(
+            AzureKeyVaultError::from(e),
+            AzureKeyVaultError::FetchFailed(..)
+        ));
+    }
+}
@@ -1,22 +1,50 @@
+//! Parsing of backtraces into Honeybadger backtrace entries.
+
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::mem;
+use std::path::Path;
 
 use failure::Backtrace;
 
+use crate::config::Config;
 use crate::payload::BacktraceEntry;
 
+/// A frame of a parsed backtrace.
+///
+/// Returned by [`parse_backtrace_verbose`][parse_backtrace_verbose].
+///
+/// [parse_backtrace_verbose]: fn.parse_backtrace_verbose.html
 #[derive(Debug, Clone)]
 pub struct BacktraceLine {
+    /// The line number, if known.
     pub line: Option<u32>,
+    /// The source file, if known.
     pub file: Option<String>,
+    /// The function name.
     pub method: String,
 }
 
 pub fn parse(bt: &Backtrace) -> Vec<BacktraceLine> {
-    let bt = bt.to_string();
+    parse_str(&bt.to_string())
+}
+
+/// Parses a backtrace, and also returns the lines which could not be interpreted.
+///
+/// Useful to diagnose odd frames on a particular platform or toolchain.
+pub fn parse_verbose(bt: &Backtrace) -> (Vec<BacktraceLine>, Vec<String>) {
+    parse_str_verbose(&bt.to_string())
+}
 
+/// Parses the `Display` output of `failure::Backtrace` or `std::backtrace::Backtrace`.
+pub fn parse_str(bt: &str) -> Vec<BacktraceLine> {
+    parse_str_verbose(bt).0
+}
+
+/// Same as `parse_str`, but also returns the lines which could not be interpreted.
+pub fn parse_str_verbose(bt: &str) -> (Vec<BacktraceLine>, Vec<String>) {
+    let mut unparsed = Vec::new();
     let mut last_file: Option<(String, u32)> = None;
     let mut last_method: Option<String> = None;
     let mut bt_lines = Vec::new();
@@ -33,16 +61,17 @@ pub fn parse(bt: &Backtrace) -> Vec<BacktraceLine> {
                 last_file.take();
             }
         };
-    };
+    }
 
     for line in bt.lines() {
         let line = line.trim();
         if line == "stack backtrace:" {
             continue;
         }
+        let raw = line;
 
         // Skip "<frameno>:"
-        let line = if line.chars().nth(0).unwrap_or(' ').is_numeric() {
+        let line = if line.chars().next().unwrap_or(' ').is_numeric() {
             let pos = line.find(':').map(|x| x + 1).unwrap_or(line.len());
             &line[pos..]
         } else {
@@ -51,9 +80,10 @@ pub fn parse(bt: &Backtrace) -> Vec<BacktraceLine> {
         let line = line.trim_start();
 
         // Skip "0x<ptr>"
-        let line = if line.starts_with("0x") {
-            let line = &line["0x".len()..];
-            let pos = line.find(|c: char| !c.is_digit(16)).unwrap_or(line.len());
+        let line = if let Some(line) = line.strip_prefix("0x") {
+            let pos = line
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(line.len());
             &line[pos..]
         } else {
             line
@@ -61,27 +91,43 @@ pub fn parse(bt: &Backtrace) -> Vec<BacktraceLine> {
         let line = line.trim_start();
 
         // Skip "-"
-        let line = if line.starts_with("-") {
-            &line["-".len()..]
-        } else {
-            line
-        };
+        let line = line.strip_prefix('-').unwrap_or(line);
         let line = line.trim_start();
 
-        if line == "" {
+        // std adds "note: Some details are omitted, ..."
+        if line.is_empty() || line.starts_with("note: ") {
             continue;
         }
 
         // at <file>:<line>
-        if line.starts_with("at ") {
-            let line = &line["at ".len()..];
+        if let Some(line) = line.strip_prefix("at ") {
+            if last_method.is_none() || last_file.is_some() {
+                // Not preceded by a frame, or a second location for a frame.
+                unparsed.push(raw.to_string());
+            }
             let line = line.trim_start();
+            // std prints "<file>:<line>:<column>"
+            let line = match line.rfind(':') {
+                Some(pos)
+                    if line[..pos]
+                        .rsplit(':')
+                        .next()
+                        .unwrap()
+                        .parse::<u32>()
+                        .is_ok() =>
+                {
+                    &line[..pos]
+                }
+                _ => line,
+            };
             if let Some(pos) = line.rfind(':') {
-                last_file = Some((
-                    line[..pos].to_string(),
-                    line[pos + ":".len()..].parse().unwrap_or(1),
-                ));
+                let lineno = line[pos + ":".len()..].parse().unwrap_or_else(|_| {
+                    unparsed.push(raw.to_string());
+                    1
+                });
+                last_file = Some((line[..pos].to_string(), lineno));
             } else {
+                unparsed.push(raw.to_string());
                 last_file = Some((line.to_string(), 1));
             }
             continue;
@@ -98,12 +144,13 @@ pub fn parse(bt: &Backtrace) -> Vec<BacktraceLine> {
         last_method = Some(line.to_string());
     }
     flush!();
-    bt_lines
+    (bt_lines, unparsed)
 }
 
 pub fn trim_backtrace(bt_lines: &mut Vec<BacktraceLine>) {
     let trim_paths = [
         "mightybadger::notify::",
+        "mightybadger::simulate_panic::",
         "backtrace::backtrace::capture::Backtrace::new::",
         "backtrace::backtrace::capture::Backtrace::new_unresolved::",
         "failure::backtrace::Backtrace::new::",
@@ -119,29 +166,95 @@ pub fn trim_backtrace(bt_lines: &mut Vec<BacktraceLine>) {
         "<core::result::Result<T, E>>::expect::",
         "<core::result::Result<T, E>>::unwrap_err::",
         "<core::result::Result<T, E>>::expect_err::",
+        "std::backtrace::Backtrace::",
+        "anyhow::",
+        "<anyhow::Error as ",
+        "eyre::",
+        "<eyre::Report as ",
     ];
     let pos = bt_lines
         .iter()
         .rposition(|bt_line| {
-            trim_paths
-                .iter()
-                .any(|trim_path| bt_line.method.starts_with(trim_path))
+            trim_paths.iter().any(|trim_path| {
+                // Recent toolchains print paths without the trailing hash.
+                bt_line.method.starts_with(trim_path)
+                    || bt_line.method == trim_path.trim_end_matches("::")
+            })
         })
         .map(|x| x + 1)
         .unwrap_or(0);
 
     bt_lines.drain(..pos);
+
+    // Drop the remaining frames of the reporting machinery above the error,
+    // unless nothing else is left.
+    let noise = bt_lines
+        .iter()
+        .take_while(|bt_line| is_noise(&bt_line.method))
+        .count();
+    if noise < bt_lines.len() {
+        bt_lines.drain(..noise);
+    }
+}
+
+/// Prefixes of the methods of frames in the machinery of panics,
+/// backtraces, and error reporting.
+const NOISE_PREFIXES: &[&str] = &[
+    "mightybadger::",
+    "backtrace::backtrace::",
+    "backtrace::capture::",
+    "failure::backtrace::",
+    "std::backtrace::",
+    "std::backtrace_rs::",
+    "std::panic::",
+    "std::panicking::",
+    "core::panicking::",
+    "core::ops::function::",
+    "std::rt::",
+    "std::sys::",
+    "std::sys_common::",
+    "std::thread::",
+    "__rust_",
+    "rust_begin_unwind",
+    "__libc_start",
+    "<unknown>",
+];
+
+/// Returns `true` if a frame of `method` is in the machinery of panics,
+/// backtraces, or error reporting.
+///
+/// See [`BacktraceEntry::is_noise`](../payload/struct.BacktraceEntry.html#method.is_noise).
+pub fn is_noise(method: &str) -> bool {
+    NOISE_PREFIXES
+        .iter()
+        .any(|prefix| method.starts_with(prefix))
+        // e.g. `<F as core::ops::function::FnOnce<()>>::call_once`
+        || (method.starts_with('<') && method.contains(" as core::ops::function::"))
 }
 
-pub fn decorate(bt_lines: Vec<BacktraceLine>) -> Vec<BacktraceEntry> {
+/// Returns `true` if `file` is in the project at `root`.
+///
+/// See [`BacktraceEntry::is_in_app`](../payload/struct.BacktraceEntry.html#method.is_in_app).
+pub fn is_in_app(file: &str, root: Option<&str>) -> bool {
+    if let Some(root) = root {
+        if Path::new(file).starts_with(root) {
+            return true;
+        }
+    }
+    // Dependencies and the standard library are printed with absolute paths.
+    Path::new(file).is_relative()
+}
+
+pub fn decorate(bt_lines: Vec<BacktraceLine>, config: &Config) -> Vec<BacktraceEntry> {
+    let revision = config.revision_or_detected();
     bt_lines
         .into_iter()
         .map(|bt_line| {
-            let source = if let (Some(line), &Some(ref file)) = (bt_line.line, &bt_line.file) {
+            let source = if let (Some(line), Some(file)) = (bt_line.line, &bt_line.file) {
                 let line = line.saturating_sub(1);
                 let skip = line.saturating_sub(2);
                 let upto = line.saturating_add(3);
-                if let Ok(file) = File::open(&file) {
+                if let Ok(file) = File::open(file) {
                     let mut source = BTreeMap::new();
                     let mut file = BufReader::new(file);
                     let mut line = String::new();
@@ -156,7 +269,7 @@ pub fn decorate(bt_lines: Vec<BacktraceLine>) -> Vec<BacktraceEntry> {
                         }
                         if lineno >= skip {
                             let lineno = lineno.saturating_add(1);
-                            let line = mem::replace(&mut line, String::new());
+                            let line = mem::take(&mut line);
                             source.insert(lineno, line);
                         }
                     }
@@ -167,20 +280,66 @@ pub fn decorate(bt_lines: Vec<BacktraceLine>) -> Vec<BacktraceEntry> {
             } else {
                 None
             };
+            let source_url = revision
+                .as_ref()
+                .and_then(|revision| source_url(&bt_line, revision, config));
+            let noise = is_noise(&bt_line.method);
             BacktraceEntry {
                 number: bt_line.line.map(|line| line.to_string()),
                 file: bt_line.file,
                 method: bt_line.method,
-                source: source,
+                source,
+                source_url,
+                noise,
             }
         })
         .collect::<Vec<_>>()
 }
 
-pub fn parse_and_decorate(bt: &Backtrace) -> Vec<BacktraceEntry> {
+/// Formats `config.source_url_template` for a frame in the project.
+fn source_url(bt_line: &BacktraceLine, revision: &str, config: &Config) -> Option<String> {
+    let template = config.source_url_template.as_ref()?;
+    let file = bt_line.file.as_ref()?;
+    let line = bt_line.line?;
+    let file = if let Some(ref root) = config.root {
+        Path::new(file)
+            .strip_prefix(root)
+            .ok()
+            .and_then(|file| file.to_str())
+            .unwrap_or(file)
+    } else {
+        file
+    };
+    let file = file.strip_prefix("./").unwrap_or(file);
+    // Absolute paths point outside of the project (e.g. to dependencies).
+    if file.starts_with('/') {
+        return None;
+    }
+    Some(
+        template
+            .replace("{file}", file)
+            .replace("{line}", &line.to_string())
+            .replace("{revision}", revision),
+    )
+}
+
+pub fn parse_and_decorate(bt: &Backtrace, config: &Config) -> Vec<BacktraceEntry> {
     let mut bt_lines = parse(bt);
     trim_backtrace(&mut bt_lines);
-    decorate(bt_lines)
+    decorate(bt_lines, config)
+}
+
+pub fn parse_and_decorate_std(
+    bt: &std::backtrace::Backtrace,
+    config: &Config,
+) -> Vec<BacktraceEntry> {
+    // Otherwise the placeholder text, e.g. "disabled backtrace", is parsed as a frame.
+    if bt.status() != std::backtrace::BacktraceStatus::Captured {
+        return Vec::new();
+    }
+    let mut bt_lines = parse_str(&bt.to_string());
+    trim_backtrace(&mut bt_lines);
+    decorate(bt_lines, config)
 }
 
 #[cfg(test)]
@@ -215,4 +374,159 @@ mod tests {
         env::set_var("RUST_BACKTRACE", "1");
         f();
     }
+
+    #[test]
+    fn test_parse_std_format() {
+        let bt = "   0: std::backtrace::Backtrace::capture
+             at /rustc/library/std/src/backtrace.rs:296:9
+   1: app::main
+             at ./src/main.rs:12:5
+   2: <unknown>
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+";
+        let mut bt_lines = parse_str(bt);
+        trim_backtrace(&mut bt_lines);
+        assert_eq!(bt_lines.len(), 2);
+        assert_eq!(bt_lines[0].method, "app::main");
+        assert_eq!(bt_lines[0].file.as_deref(), Some("./src/main.rs"));
+        assert_eq!(bt_lines[0].line, Some(12));
+        assert_eq!(bt_lines[1].method, "<unknown>");
+        assert_eq!(bt_lines[1].file, None);
+    }
+
+    #[test]
+    fn test_parse_verbose() {
+        let bt = "stack backtrace:
+   0: app::main
+             at ./src/main.rs:12
+             at ./src/main.rs:13
+   1: app::run
+             at ./src/lib.rs
+   2: app::start
+             at ./src/lib.rs:abc
+      app::start::inlined
+             at ./src/lib.rs:20
+             at ./src/lib.rs:21
+";
+        let (bt_lines, unparsed) = parse_str_verbose(bt);
+        assert_eq!(bt_lines.len(), 4);
+        assert_eq!(bt_lines[0].line, Some(13));
+        assert_eq!(bt_lines[3].method, "app::start::inlined");
+        assert_eq!(
+            unparsed,
+            [
+                "at ./src/main.rs:13",
+                "at ./src/lib.rs",
+                "at ./src/lib.rs:abc",
+                "at ./src/lib.rs:21",
+            ]
+        );
+
+        let (bt_lines, unparsed) = parse_verbose(&Backtrace::new());
+        assert_eq!(parse_str(bt).len(), 4);
+        assert!(unparsed.is_empty(), "{:?} in {:?}", unparsed, bt_lines);
+    }
+
+    #[test]
+    fn test_trim_backtrace() {
+        let bt_line = |method: &str| BacktraceLine {
+            line: None,
+            file: None,
+            method: method.to_string(),
+        };
+        let mut bt_lines = vec![
+            bt_line("failure::backtrace::Backtrace::new::h0123456789abcdef"),
+            bt_line("failure::backtrace::Backtrace::new"),
+            bt_line("app::main"),
+        ];
+        trim_backtrace(&mut bt_lines);
+        assert_eq!(bt_lines.len(), 1);
+        assert_eq!(bt_lines[0].method, "app::main");
+
+        let mut bt_lines = vec![
+            bt_line("std::panicking::begin_panic_handler::{{closure}}"),
+            bt_line("mightybadger::capture_panics"),
+            bt_line("app::main"),
+            bt_line("core::ops::function::FnOnce::call_once"),
+        ];
+        trim_backtrace(&mut bt_lines);
+        assert_eq!(bt_lines.len(), 2);
+        assert_eq!(bt_lines[0].method, "app::main");
+
+        // Noise is kept if nothing else is left.
+        let mut bt_lines = vec![bt_line("std::rt::lang_start")];
+        trim_backtrace(&mut bt_lines);
+        assert_eq!(bt_lines.len(), 1);
+    }
+
+    #[test]
+    fn test_is_noise() {
+        assert!(is_noise("std::panicking::try::do_call"));
+        assert!(is_noise("core::panicking::panic_fmt"));
+        assert!(is_noise("backtrace::backtrace::trace"));
+        assert!(is_noise(
+            "<alloc::boxed::Box<F,A> as core::ops::function::FnOnce<Args>>::call_once"
+        ));
+        assert!(is_noise("mightybadger::notify_std_error"));
+        assert!(!is_noise("app::main"));
+        assert!(!is_noise("mightybadger_app::main"));
+        assert!(!is_noise("<app::Handler as app::Service>::call"));
+    }
+
+    #[test]
+    fn test_is_in_app() {
+        assert!(is_in_app("./src/main.rs", None));
+        assert!(is_in_app("src/main.rs", None));
+        assert!(!is_in_app("/rustc/abcdef/library/std/src/rt.rs", None));
+        assert!(!is_in_app(
+            "/home/user/.cargo/registry/src/failure-0.1.8/src/lib.rs",
+            None
+        ));
+        assert!(is_in_app("/srv/app/src/main.rs", Some("/srv/app")));
+        assert!(!is_in_app("/srv/application/src/main.rs", Some("/srv/app")));
+    }
+
+    #[test]
+    fn test_source_url() {
+        let config = Config {
+            root: Some("/home/ubuntu/app".to_string()),
+            revision: Some("0123abc".to_string()),
+            source_url_template: Some(
+                "https://github.com/org/repo/blob/{revision}/{file}#L{line}".to_string(),
+            ),
+            ..Default::default()
+        };
+        let bt_line = |file: &str| BacktraceLine {
+            line: Some(42),
+            file: Some(file.to_string()),
+            method: "app::main".to_string(),
+        };
+        assert_eq!(
+            source_url(&bt_line("/home/ubuntu/app/src/main.rs"), "0123abc", &config).as_deref(),
+            Some("https://github.com/org/repo/blob/0123abc/src/main.rs#L42")
+        );
+        assert_eq!(
+            source_url(&bt_line("./src/lib.rs"), "0123abc", &config).as_deref(),
+            Some("https://github.com/org/repo/blob/0123abc/src/lib.rs#L42")
+        );
+        assert_eq!(
+            source_url(&bt_line("/rustc/library/std/src/rt.rs"), "0123abc", &config),
+            None
+        );
+        assert_eq!(
+            source_url(
+                &bt_line("/home/ubuntu/application/src/main.rs"),
+                "0123abc",
+                &config
+            ),
+            None
+        );
+        // No revision is detected outside of the configured root.
+        let config = Config {
+            revision: None,
+            ..config
+        };
+        let entries = decorate(vec![bt_line("src/main.rs")], &config);
+        assert_eq!(entries[0].source_url, None);
+    }
 }
@@ -0,0 +1,21 @@
+//! Pluggable transport for notices.
+//!
+//! By default, notices are sent to the Honeybadger API server over HTTP.
+//! Set [`Config::http_client`][http_client] to replace the transport,
+//! e.g. to capture notices in tests.
+//!
+//! [http_client]: ../config/struct.Config.html#structfield.http_client
+
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::payload::Payload;
+use crate::HoneybadgerError;
+
+/// Transport that delivers a notice payload.
+pub trait HttpClient: fmt::Debug + Send + Sync {
+    /// Sends the payload and returns the id of the created notice.
+    fn report(&self, payload: &Payload, config: &Config) -> Result<Uuid, HoneybadgerError>;
+}
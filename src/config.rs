@@ -10,15 +10,27 @@
 //! [configure]: fn.configure.html
 //! [read_config]: fn.read_config.html
 
+use std::collections::HashMap;
 use std::env;
+use std::ffi::OsString;
+use std::fmt;
 use std::mem;
 use std::ops::Deref;
 use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::{RwLock, RwLockReadGuard};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::Duration;
 
+use failure::{Backtrace, Fail};
 use lazy_static::lazy_static;
 
+use crate::client::HttpClient;
+#[cfg(feature = "log-filter")]
+use crate::error_filter::ErrorFilter;
+use crate::git::{self, GitHead};
+use crate::stats::StatsBackend;
+
 /// Honeybadger configuration.
 ///
 /// It roughly corresponds with [the Ruby notifier configuration][ruby-config].
@@ -39,7 +51,7 @@ use lazy_static::lazy_static;
 ///     config.env = Some("production".to_string());
 /// });
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[derive(Clone, Default)]
 pub struct Config {
     /// The API key for your Honeybadger project.
     pub api_key: Option<String>,
@@ -48,20 +60,468 @@ pub struct Config {
     /// Enable/disable reporting of data.
     /// Defaults to `false` for `"test"`, `"development"`, and `"cucumber"` environments.
     pub report_data: Option<bool>,
+    /// Whether to print notices to stderr in a readable form when they are
+    /// not sent because `report_data` is off, e.g. in development.
+    /// Defaults to `false`.
+    pub dev_display: Option<bool>,
+    /// Severity of notices reported without one, e.g. `"error"`.
+    /// Sent as a `severity:<severity>` tag.
+    /// Defaults to no severity.
+    pub default_severity: Option<String>,
+    /// The fraction of successful requests reported by
+    /// [`sample_success`][::sample_success], from `0.0` to `1.0`,
+    /// e.g. `0.001` for one in a thousand.
+    /// Defaults to `0.0`, i.e. none.
+    ///
+    /// [::sample_success]: ../fn.sample_success.html
+    pub success_sample_rate: Option<SampleRate>,
+    /// The fraction of panics reported, from `0.0` to `1.0`.
+    /// Explicit `notify` calls are not sampled.
+    /// Defaults to `1.0`, i.e. all.
+    pub panic_sample_rate: Option<SampleRate>,
+    /// Panics whose message contains any of these strings are not reported,
+    /// e.g. `["broken pipe"]`. Explicit `notify` calls are not filtered.
+    /// Defaults to none.
+    pub panic_ignore_messages: Option<Vec<String>>,
     /// The project's absolute root path.
     pub root: Option<String>,
     /// The project's git revision.
+    /// Defaults to the commit checked out at `root`, if it is in a git checkout,
+    /// as detected when `root` is set.
+    /// The branch name is also sent as `context.git_branch` in that case.
     pub revision: Option<String>,
     /// The hostname of the current box.
     pub hostname: Option<String>,
+    /// Template of links from backtrace frames to a code host.
+    /// `{file}` (relative to `root`), `{line}`, and `{revision}` are substituted,
+    /// e.g. `"https://github.com/org/repo/blob/{revision}/{file}#L{line}"`.
+    /// Links are only generated when the revision is known.
+    pub source_url_template: Option<String>,
+    /// Template of a link from each notice to an external system, such as a
+    /// log viewer or an APM trace, sent as `context.log_url`.
+    /// `{notice_id}`, `{request_id}`, `{transaction_id}`, and `{env}` are
+    /// substituted, percent-encoded, e.g.
+    /// `"https://logs.example.com/search?request_id={request_id}"`.
+    /// The request ID is taken from `context.request_id` or the
+    /// `X-Request-Id` header. No link is attached if a variable used in
+    /// the template is unknown.
+    pub log_url_template: Option<String>,
+    /// Whether to report `server.time` in the local time zone instead of UTC.
+    /// The time is sent with its UTC offset in either case,
+    /// e.g. `2019-01-01 09:00:00 +0900`.
+    /// Defaults to `false`.
+    pub local_time: Option<bool>,
+    /// Custom deployment metadata (e.g. commit author and pipeline ID),
+    /// sent as `server.attributes`.
+    pub server_attributes: HashMap<String, String>,
+    /// Whether to attach the command-line arguments and the working directory
+    /// of the process to notices, as `context.args` and `context.cwd`.
+    /// Meant for command-line programs; the values of options matching
+    /// `request.filter_keys` (e.g. `--password=...`) are filtered.
+    /// Defaults to `false`.
+    pub capture_process_info: Option<bool>,
     /// HTTP connection options.
     pub connection: ConnectionConfig,
     /// Request data filtering options.
     pub request: RequestConfig,
+    /// Other projects every notice is also sent to, as pairs of the API key
+    /// and the connection settings overriding `connection`.
+    ///
+    /// A failure to one destination doesn't prevent sending to the others.
+    /// The outcome for each destination is returned by
+    /// [`last_destination_statuses`][last_destination_statuses].
+    ///
+    /// [last_destination_statuses]: ../fn.last_destination_statuses.html
+    pub additional_destinations: Vec<(String, ConnectionConfig)>,
+    /// Limits notices of each error class to at most `.0` per `.1`,
+    /// e.g. `(10, Duration::from_secs(60))` for 10 notices per minute.
+    /// Notices over the limit are dropped locally.
+    /// Defaults to no limit.
+    pub per_class_rate_limit: Option<(u32, Duration)>,
+    /// `RUST_LOG`-style directives for error classes not to report,
+    /// e.g. `"app::db=off,app::db::TimeoutError=on"`.
+    ///
+    /// Each comma-separated directive is a prefix of error classes followed
+    /// by `=off` or `=on`, and the longest matching prefix wins.
+    /// `off` alone turns off all errors not turned on by other directives.
+    /// The directives are parsed when the configuration is set, and invalid
+    /// ones are ignored with a warning.
+    /// Defaults to reporting everything.
+    #[cfg(feature = "log-filter")]
+    pub error_filter: Option<String>,
+    /// Whether to send notices from a background thread.
+    /// Defaults to `false`. Ignored on WebAssembly, which has no threads.
+    ///
+    /// Queued notices are lost if the process exits before they are sent;
+    /// see [`flush`][::flush] and [`flush_on_exit`][::flush_on_exit].
+    ///
+    /// [::flush]: ../fn.flush.html
+    /// [::flush_on_exit]: ../fn.flush_on_exit.html
+    pub async_send: Option<bool>,
+    /// Whether to leave collecting stats (memory and load) to the background
+    /// worker, so that `notify` returns without reading `/proc`.
+    /// Only effective with `async_send`.
+    /// Defaults to `false`.
+    pub deferred_stats: Option<bool>,
+    /// The maximum number of notices sent synchronously at the same time.
+    /// Defaults to no limit.
+    ///
+    /// Notices over the limit are dropped instead of waiting, so that
+    /// a burst of errors doesn't tie up the threads of the application
+    /// in reporting them. Dropped notices are counted by
+    /// [`dropped_notice_count`][::dropped_notice_count].
+    ///
+    /// [::dropped_notice_count]: ../fn.dropped_notice_count.html
+    pub max_concurrent_sends: Option<usize>,
+    /// The maximum number of notices this process ever sends,
+    /// e.g. `Some(1)` for a command-line tool reporting only its first error.
+    /// Defaults to no limit.
+    ///
+    /// Notices over the limit are dropped with a log message, so that
+    /// a looping process doesn't send thousands of the same report.
+    pub max_notices_per_process: Option<usize>,
+    /// Custom transport for notices.
+    /// Defaults to sending them to the Honeybadger API server.
+    /// Required on WebAssembly, where the default transport cannot open connections.
+    pub http_client: Option<Arc<dyn HttpClient>>,
+    /// Source of the memory and load statistics sent with notices.
+    /// Defaults to the backend for the target platform; see
+    /// [`stats`][stats].
+    ///
+    /// [stats]: ../stats/index.html
+    pub stats_backend: Option<Arc<dyn StatsBackend>>,
+    /// Called after the configuration is reloaded by
+    /// [`configure_from_env_forced`][configure_from_env_forced],
+    /// e.g. on `SIGHUP` (see [`install_sighup_reload`][::install_sighup_reload]).
+    ///
+    /// [configure_from_env_forced]: fn.configure_from_env_forced.html
+    /// [::install_sighup_reload]: ../fn.install_sighup_reload.html
+    pub on_config_reload: Option<Arc<dyn Fn() + Send + Sync>>,
     #[doc(hidden)]
     pub _non_exhaustive: (),
 }
 
+impl Config {
+    /// Returns whether notices are sent, according to `report_data` and `env`.
+    pub(crate) fn reports(&self) -> bool {
+        self.report_data.unwrap_or_else(|| {
+            let env = self.env.as_deref().unwrap_or("");
+            ["test", "development", "cucumber"]
+                .iter()
+                .all(|&s| env != s)
+        })
+    }
+
+    /// Returns `revision`, or the commit detected at `root` if it is unset.
+    pub(crate) fn revision_or_detected(&self) -> Option<String> {
+        if let Some(ref revision) = self.revision {
+            return Some(revision.clone());
+        }
+        let config = read_config();
+        // The commit is detected for the root of the global configuration.
+        if config.root != self.root {
+            return None;
+        }
+        config.derived().detected_revision().cloned()
+    }
+
+    /// Checks the configuration for mistakes which would make reporting fail.
+    ///
+    /// The API key is only required if notices are sent
+    /// (see [`report_data`](#structfield.report_data)).
+    pub fn validate(&self) -> Result<(), ConfigValidationError> {
+        fn invalid(field: &'static str, value: impl fmt::Debug) -> ConfigValidationError {
+            ConfigValidationError::InvalidValue(field, format!("{:?}", value), Backtrace::new())
+        }
+
+        if self.reports() && self.api_key.as_deref().unwrap_or("").is_empty() {
+            return Err(ConfigValidationError::MissingApiKey(Backtrace::new()));
+        }
+        if let Some(ref host) = self.connection.host {
+            if host.is_empty() || host.contains(|c: char| c == '/' || c.is_whitespace()) {
+                return Err(invalid("connection.host", host));
+            }
+        }
+        if self.connection.port == Some(0) {
+            return Err(invalid("connection.port", 0));
+        }
+        #[cfg(not(unix))]
+        {
+            if let Some(ref socket_path) = self.connection.socket_path {
+                return Err(invalid("connection.socket_path", socket_path));
+            }
+        }
+        if self.connection.max_connections == Some(0) {
+            return Err(invalid("connection.max_connections", 0));
+        }
+        if self.max_concurrent_sends == Some(0) {
+            return Err(invalid("max_concurrent_sends", 0));
+        }
+        if let Some((count, period)) = self.per_class_rate_limit {
+            if count == 0 || period == Duration::from_secs(0) {
+                return Err(invalid("per_class_rate_limit", (count, period)));
+            }
+        }
+        for (api_key, _) in &self.additional_destinations {
+            if api_key.is_empty() {
+                return Err(invalid("additional_destinations", api_key));
+            }
+        }
+        if let Some(ref patterns) = self.request.filter_value_patterns {
+            for pattern in patterns {
+                #[cfg(feature = "regex")]
+                let valid = regex::Regex::new(pattern).is_ok();
+                #[cfg(not(feature = "regex"))]
+                let valid = false;
+                if !valid {
+                    return Err(invalid("request.filter_value_patterns", pattern));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a copy with the connection fields set in `connection` overridden.
+    pub(crate) fn with_connection(&self, connection: &ConnectionConfig) -> Config {
+        let mut config = self.clone();
+        let ConnectionConfig {
+            secure,
+            host,
+            port,
+            strict_response,
+            socket_path,
+            max_response_body_bytes,
+            max_connections,
+            retries,
+            retry_status_codes,
+            timeout,
+            _non_exhaustive,
+        } = connection;
+        fn set<T: Clone>(entry: &mut Option<T>, value: &Option<T>) {
+            if value.is_some() {
+                entry.clone_from(value);
+            }
+        }
+        set(&mut config.connection.secure, secure);
+        set(&mut config.connection.host, host);
+        set(&mut config.connection.port, port);
+        set(&mut config.connection.strict_response, strict_response);
+        set(&mut config.connection.socket_path, socket_path);
+        set(
+            &mut config.connection.max_response_body_bytes,
+            max_response_body_bytes,
+        );
+        set(&mut config.connection.max_connections, max_connections);
+        set(&mut config.connection.retries, retries);
+        set(
+            &mut config.connection.retry_status_codes,
+            retry_status_codes,
+        );
+        set(&mut config.connection.timeout, timeout);
+        config
+    }
+}
+
+/// Error returned by [`Config::validate`][validate].
+///
+/// [validate]: struct.Config.html#method.validate
+#[derive(Debug, Fail)]
+pub enum ConfigValidationError {
+    #[fail(display = "API key is missing")]
+    MissingApiKey(Backtrace),
+    #[fail(display = "invalid value for {}: {}", _0, _1)]
+    InvalidValue(&'static str, String, Backtrace),
+}
+
+impl PartialEq for Config {
+    fn eq(&self, other: &Self) -> bool {
+        let Config {
+            api_key,
+            env,
+            report_data,
+            dev_display,
+            default_severity,
+            success_sample_rate,
+            panic_sample_rate,
+            panic_ignore_messages,
+            root,
+            revision,
+            hostname,
+            source_url_template,
+            log_url_template,
+            local_time,
+            server_attributes,
+            capture_process_info,
+            connection,
+            request,
+            additional_destinations,
+            per_class_rate_limit,
+            #[cfg(feature = "log-filter")]
+            error_filter,
+            async_send,
+            deferred_stats,
+            max_concurrent_sends,
+            max_notices_per_process,
+            http_client,
+            stats_backend,
+            on_config_reload,
+            _non_exhaustive,
+        } = self;
+        #[cfg(feature = "log-filter")]
+        let error_filter_eq = *error_filter == other.error_filter;
+        #[cfg(not(feature = "log-filter"))]
+        let error_filter_eq = true;
+        *api_key == other.api_key
+            && *env == other.env
+            && *report_data == other.report_data
+            && *dev_display == other.dev_display
+            && *default_severity == other.default_severity
+            && *success_sample_rate == other.success_sample_rate
+            && *panic_sample_rate == other.panic_sample_rate
+            && *panic_ignore_messages == other.panic_ignore_messages
+            && *root == other.root
+            && *revision == other.revision
+            && *hostname == other.hostname
+            && *source_url_template == other.source_url_template
+            && *log_url_template == other.log_url_template
+            && *local_time == other.local_time
+            && *server_attributes == other.server_attributes
+            && *capture_process_info == other.capture_process_info
+            && *connection == other.connection
+            && *request == other.request
+            && *additional_destinations == other.additional_destinations
+            && *per_class_rate_limit == other.per_class_rate_limit
+            && error_filter_eq
+            && *async_send == other.async_send
+            && *deferred_stats == other.deferred_stats
+            && *max_concurrent_sends == other.max_concurrent_sends
+            && *max_notices_per_process == other.max_notices_per_process
+            && arc_ptr_eq(http_client, &other.http_client)
+            && arc_ptr_eq(stats_backend, &other.stats_backend)
+            && arc_ptr_eq(on_config_reload, &other.on_config_reload)
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Config {
+            api_key,
+            env,
+            report_data,
+            dev_display,
+            default_severity,
+            success_sample_rate,
+            panic_sample_rate,
+            panic_ignore_messages,
+            root,
+            revision,
+            hostname,
+            source_url_template,
+            log_url_template,
+            local_time,
+            server_attributes,
+            capture_process_info,
+            connection,
+            request,
+            additional_destinations,
+            per_class_rate_limit,
+            #[cfg(feature = "log-filter")]
+            error_filter,
+            async_send,
+            deferred_stats,
+            max_concurrent_sends,
+            max_notices_per_process,
+            http_client,
+            stats_backend,
+            on_config_reload,
+            _non_exhaustive,
+        } = self;
+        let mut f = f.debug_struct("Config");
+        f.field("api_key", api_key)
+            .field("env", env)
+            .field("report_data", report_data)
+            .field("dev_display", dev_display)
+            .field("default_severity", default_severity)
+            .field("success_sample_rate", success_sample_rate)
+            .field("panic_sample_rate", panic_sample_rate)
+            .field("panic_ignore_messages", panic_ignore_messages)
+            .field("root", root)
+            .field("revision", revision)
+            .field("hostname", hostname)
+            .field("source_url_template", source_url_template)
+            .field("log_url_template", log_url_template)
+            .field("local_time", local_time)
+            .field("server_attributes", server_attributes)
+            .field("capture_process_info", capture_process_info)
+            .field("connection", connection)
+            .field("request", request)
+            .field("additional_destinations", additional_destinations)
+            .field("per_class_rate_limit", per_class_rate_limit);
+        #[cfg(feature = "log-filter")]
+        f.field("error_filter", error_filter);
+        f.field("async_send", async_send)
+            .field("deferred_stats", deferred_stats)
+            .field("max_concurrent_sends", max_concurrent_sends)
+            .field("max_notices_per_process", max_notices_per_process)
+            .field("http_client", http_client)
+            .field("stats_backend", stats_backend)
+            .field(
+                "on_config_reload",
+                &on_config_reload.as_ref().map(|_| "Fn()"),
+            )
+            .finish()
+    }
+}
+
+impl Eq for Config {}
+
+/// Compares trait objects by identity.
+fn arc_ptr_eq<T: ?Sized>(a: &Option<Arc<T>>, b: &Option<Arc<T>>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const (),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// A fraction from `0.0` to `1.0`, for
+/// [`success_sample_rate`][success_sample_rate] and
+/// [`panic_sample_rate`][panic_sample_rate].
+///
+/// Unlike `f64`, it is never NaN, so it implements `Eq`.
+///
+/// [success_sample_rate]: struct.Config.html#structfield.success_sample_rate
+/// [panic_sample_rate]: struct.Config.html#structfield.panic_sample_rate
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct SampleRate(f64);
+
+impl SampleRate {
+    /// Returns `None` unless `rate` is from `0.0` to `1.0`.
+    pub fn new(rate: f64) -> Option<SampleRate> {
+        if (0.0..=1.0).contains(&rate) {
+            Some(SampleRate(rate))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the fraction.
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl Eq for SampleRate {}
+
+impl FromStr for SampleRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rate = s.parse::<f64>().map_err(|e| e.to_string())?;
+        SampleRate::new(rate).ok_or_else(|| "not from 0.0 to 1.0".to_string())
+    }
+}
+
 /// HTTP connection options.
 ///
 /// This is part of [`Config`][Config] data structure.
@@ -78,6 +538,49 @@ pub struct ConnectionConfig {
     /// The port to use when sending data.
     /// Defaults to 443.
     pub port: Option<u16>,
+    /// Whether to fail when the server accepts a notice (`201 Created`)
+    /// but its response body is empty or not the expected JSON.
+    /// Defaults to `false`, in which case the notice id is taken from the payload.
+    pub strict_response: Option<bool>,
+    /// Path to a Unix domain socket to send data over, instead of TCP.
+    /// `host` is still sent as the `Host` header; `secure` and `port` are ignored.
+    /// Only supported on Unix.
+    pub socket_path: Option<PathBuf>,
+    /// The maximum size of the response body to read, in bytes.
+    /// Larger responses fail with `ResponseDecodeFailed`.
+    /// Defaults to 4096.
+    pub max_response_body_bytes: Option<usize>,
+    /// The number of notices the background worker of `async_send` sends
+    /// in parallel, each over its own connection.
+    /// Defaults to 1, i.e. queued notices are sent one by one in order.
+    ///
+    /// With more than one, a burst of notices is sent faster, but they may
+    /// arrive out of order. Only the global setting is effective, and it is
+    /// read once, when the worker starts, i.e. when the first notice is
+    /// queued; later changes, e.g. by `configure` or a reload, do not resize
+    /// the running worker.
+    pub max_connections: Option<usize>,
+    /// The number of times a notice is sent again when the server responds
+    /// with one of [`retry_status_codes`](#structfield.retry_status_codes),
+    /// waiting 200ms before the first retry and twice as long before each next.
+    /// For `429` and `503`, the `Retry-After` header of the response is
+    /// waited for instead, if any; the notice is not retried if it asks
+    /// to wait more than a minute.
+    /// Defaults to 0, i.e. no retries.
+    ///
+    /// Only the built-in transport retries, not a custom `Config::http_client`.
+    pub retries: Option<u32>,
+    /// Statuses of responses which are retried, e.g. `502` and `504`
+    /// for backends signaling transient failures with them.
+    /// Defaults to `[429, 503]`.
+    pub retry_status_codes: Option<Vec<u16>>,
+    /// The time limit of sending a notice, including connecting.
+    /// Defaults to no limit, except that each read and write over
+    /// `socket_path` is limited to 30 seconds.
+    ///
+    /// Over `socket_path`, it limits each read and write instead.
+    /// It is not applied to a custom `Config::http_client`.
+    pub timeout: Option<Duration>,
     #[doc(hidden)]
     pub _non_exhaustive: (),
 }
@@ -92,6 +595,49 @@ pub struct RequestConfig {
     /// A list of keys to filter when sending request data.
     /// Defaults to `["password", "HTTP_AUTHORIZATION"]`.
     pub filter_keys: Option<Vec<String>>,
+    /// The number of recent database queries to keep per thread.
+    /// Defaults to 10.
+    pub max_queries: Option<usize>,
+    /// Whether to replace literals in recorded queries with `?`.
+    /// Defaults to `true`.
+    pub filter_queries: Option<bool>,
+    /// The maximum size of a request body attached to the notice, in bytes.
+    /// Defaults to 16384.
+    pub max_body_bytes: Option<usize>,
+    /// Whether to report errors raised while handling `OPTIONS` and `HEAD`
+    /// requests, such as CORS preflights and health probes.
+    /// Defaults to `false`.
+    pub report_preflight: Option<bool>,
+    /// Request paths not to report errors for, such as health checks.
+    /// Each entry matches the whole path; `*` matches any sequence of characters,
+    /// e.g. `["/healthz", "/status/*"]`.
+    /// Defaults to none.
+    pub ignore_paths: Option<Vec<String>>,
+    /// Names of response headers that
+    /// [`RequestInfo::capture_response_headers`][capture_response_headers]
+    /// attaches to notices of error responses, compared case-insensitively.
+    /// Defaults to `["Content-Type"]`.
+    ///
+    /// [capture_response_headers]: ../payload/struct.RequestInfo.html#method.capture_response_headers
+    pub response_headers: Option<Vec<String>>,
+    /// The maximum number of entries kept in each of `cgi_data`, `params`,
+    /// `session`, and `context` of the request data.
+    /// Entries are kept in the order of their keys, and the rest is
+    /// replaced with a single `"[OMITTED]"` entry.
+    /// Defaults to unbounded.
+    pub max_request_entries: Option<usize>,
+    /// Regular expressions for values to filter when sending request data,
+    /// regardless of their keys, e.g. `[r"^\d{13,16}$"]` for card numbers.
+    /// Defaults to none.
+    ///
+    /// The patterns are compiled when the configuration is set, and every
+    /// string value in the request data is scanned; keep the list short.
+    /// Invalid patterns are reported by [`validate`][Config::validate] and
+    /// otherwise ignored.
+    ///
+    /// The patterns are applied only with the `regex` feature; without it,
+    /// setting them is reported by [`validate`][Config::validate].
+    pub filter_value_patterns: Option<Vec<String>>,
     #[doc(hidden)]
     pub _non_exhaustive: (),
 }
@@ -108,11 +654,74 @@ impl RequestConfig {
                 .any(|s| key.contains(s))
         }
     }
+
+    /// Returns `true` if errors from requests with the HTTP method
+    /// should be reported.
+    ///
+    /// Middlewares can use it to skip collecting request data early.
+    /// Notices whose context has a `REQUEST_METHOD` in `cgi_data` are
+    /// checked against it in any case.
+    pub fn report_method(&self, method: &str) -> bool {
+        self.report_preflight.unwrap_or(false)
+            || !(method.eq_ignore_ascii_case("OPTIONS") || method.eq_ignore_ascii_case("HEAD"))
+    }
+
+    /// Returns `true` if errors from requests to the URL should be reported,
+    /// i.e. its path doesn't match [`ignore_paths`](#structfield.ignore_paths).
+    ///
+    /// Both absolute URLs and bare paths are accepted; the query is ignored.
+    /// Notices are checked against the `url` of their context in any case.
+    pub fn report_path(&self, url: &str) -> bool {
+        let ignore_paths = if let Some(ref ignore_paths) = self.ignore_paths {
+            ignore_paths
+        } else {
+            return true;
+        };
+        let path = url_path(url);
+        !ignore_paths.iter().any(|pattern| glob_match(pattern, path))
+    }
+}
+
+/// Extracts the path from an absolute URL or a path with a query.
+fn url_path(url: &str) -> &str {
+    let path = if let Some(pos) = url.find("://") {
+        let rest = &url[pos + "://".len()..];
+        rest.find('/').map(|pos| &rest[pos..]).unwrap_or("/")
+    } else {
+        url
+    };
+    path.split(['?', '#']).next().unwrap_or(path)
+}
+
+/// Matches `text` against `pattern`, where `*` matches any sequence of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let mut text = if let Some(text) = text.strip_prefix(first) {
+        text
+    } else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let last = if let Some(last) = parts.pop() {
+        last
+    } else {
+        // No `*` in the pattern.
+        return text.is_empty();
+    };
+    for part in parts {
+        if let Some(pos) = text.find(part) {
+            text = &text[pos + part.len()..];
+        } else {
+            return false;
+        }
+    }
+    text.ends_with(last)
 }
 
 lazy_static! {
-    /// Global Honeybadger configuration.
-    static ref CONFIG: RwLock<Config> = RwLock::new(Config::default());
+    /// Global Honeybadger configuration, and the values derived from it.
+    static ref CONFIG: RwLock<(Config, Arc<Derived>)> = RwLock::new(Default::default());
     /// The copy of the global configuration. Used by `configure`.
     static ref CONFIG_PROXY: RwLock<Config> = RwLock::new(Config::default());
 }
@@ -121,61 +730,385 @@ lazy_static! {
 ///
 /// Replaces the config only if the field is `None`.
 ///
+/// If `HONEYBADGER_ENV` is unset, the environment name is taken from
+/// `RAILS_ENV`, `RACK_ENV`, `APP_ENV`, or `NODE_ENV`, in this order.
+///
 /// It is called as a part of [`mightybadger::setup`][::setup].
 ///
 /// [::setup]: ../fn.setup.html
 pub fn configure_from_env() {
-    fn set_string(entry: &mut Option<String>, env_name: &str) {
-        if entry.is_none() {
-            *entry = env::var_os(env_name).map(|s| s.to_string_lossy().to_string());
+    configure(|config| load_env(config, false))
+}
+
+/// Reloads configuration from the `HONEYBADGER_*` environment variables.
+///
+/// Unlike [`configure_from_env`][configure_from_env], fields are replaced
+/// whenever the corresponding variable is set; other fields are kept.
+/// [`Config::on_config_reload`][Config::on_config_reload] is called afterwards.
+///
+/// [configure_from_env]: fn.configure_from_env.html
+/// [Config::on_config_reload]: struct.Config.html#structfield.on_config_reload
+pub fn configure_from_env_forced() {
+    configure(|config| load_env(config, true));
+    let on_config_reload = read_config().on_config_reload.clone();
+    if let Some(on_config_reload) = on_config_reload {
+        on_config_reload();
+    }
+}
+
+/// Same as [`configure_from_env`][configure_from_env], but reports
+/// the variables which could not be parsed and validates the result.
+///
+/// Variables with invalid values (e.g. `HONEYBADGER_CONNECTION_PORT=abc`)
+/// are ignored as in `configure_from_env`, and returned as warnings.
+/// If the resulting configuration doesn't pass
+/// [`Config::validate`][validate], e.g. because the API key is missing,
+/// the error is returned and the configuration is left untouched.
+///
+/// [configure_from_env]: fn.configure_from_env.html
+/// [validate]: struct.Config.html#method.validate
+///
+/// ## Example
+///
+/// ```no_run
+/// match mightybadger::configure_from_env_with_validation() {
+///     Ok(warnings) => {
+///         for warning in warnings {
+///             eprintln!("{}", warning);
+///         }
+///     }
+///     Err(e) => panic!("invalid Honeybadger configuration: {}", e),
+/// }
+/// ```
+pub fn configure_from_env_with_validation() -> Result<Vec<ConfigWarning>, ConfigValidationError> {
+    let mut result = Ok(Vec::new());
+    configure(|config| {
+        let mut scratch = config.clone();
+        let mut warnings = Vec::new();
+        load_env_with_warnings(&mut scratch, false, &mut warnings);
+        result = scratch.validate().map(|()| warnings);
+        if result.is_ok() {
+            *config = scratch;
+        }
+    });
+    result
+}
+
+/// An environment variable ignored by
+/// [`configure_from_env_with_validation`][configure_from_env_with_validation]
+/// because its value could not be parsed.
+///
+/// [configure_from_env_with_validation]: fn.configure_from_env_with_validation.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigWarning {
+    /// The name of the variable, e.g. `HONEYBADGER_CONNECTION_PORT`.
+    pub env_name: &'static str,
+    /// The value of the variable.
+    pub value: String,
+    /// Why the value was rejected.
+    pub reason: String,
+}
+
+impl ConfigWarning {
+    fn new(env_name: &'static str, value: String, reason: String) -> Self {
+        ConfigWarning {
+            env_name,
+            value,
+            reason,
         }
     }
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ignoring {}={:?}: {}",
+            self.env_name, self.value, self.reason
+        )
+    }
+}
+
+/// Sets fields from the environment variables.
+/// Fields already set are only replaced if `force` is `true`.
+fn load_env(config: &mut Config, force: bool) {
+    load_env_with_warnings(config, force, &mut Vec::new());
+}
 
-    fn set_parseable<T: FromStr>(entry: &mut Option<T>, env_name: &str) {
-        if entry.is_none() {
-            *entry =
-                env::var_os(env_name).and_then(|s| s.to_string_lossy().to_string().parse().ok());
+/// Same as `load_env`, but records the variables which could not be parsed.
+fn load_env_with_warnings(config: &mut Config, force: bool, warnings: &mut Vec<ConfigWarning>) {
+    fn string(env_name: &str) -> Option<String> {
+        env::var_os(env_name).map(|s| s.to_string_lossy().to_string())
+    }
+
+    fn parseable<T>(warnings: &mut Vec<ConfigWarning>, env_name: &'static str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = string(env_name)?;
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warnings.push(ConfigWarning::new(env_name, value, e.to_string()));
+                None
+            }
         }
     }
 
-    fn set_bool(entry: &mut Option<bool>, env_name: &str) {
-        if entry.is_none() {
-            *entry = env::var_os(env_name).map(|s| {
-                let s = s.to_string_lossy().to_string();
-                ["true", "t", "1"].iter().any(|t| s.eq_ignore_ascii_case(t))
-            });
+    fn boolean(warnings: &mut Vec<ConfigWarning>, env_name: &'static str) -> Option<bool> {
+        let value = string(env_name)?;
+        let truthy = ["true", "t", "1"]
+            .iter()
+            .any(|t| value.eq_ignore_ascii_case(t));
+        let falsy = ["false", "f", "0"]
+            .iter()
+            .any(|f| value.eq_ignore_ascii_case(f));
+        if !truthy && !falsy {
+            let reason = "not a boolean; treated as false".to_string();
+            warnings.push(ConfigWarning::new(env_name, value, reason));
         }
+        Some(truthy)
     }
 
-    fn set_string_array(entry: &mut Option<Vec<String>>, env_name: &str) {
-        if entry.is_none() {
-            *entry = env::var_os(env_name).map(|s| {
-                let s = s.to_string_lossy().to_string();
-                s.split(",")
-                    .map(|s| s.trim().to_string())
-                    .collect::<Vec<_>>()
-            });
+    fn string_array(env_name: &str) -> Option<Vec<String>> {
+        string(env_name).map(|s| {
+            s.split(",")
+                .map(|s| s.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+    }
+
+    macro_rules! set {
+        ($entry:expr, $value:expr) => {
+            let value = $value;
+            if value.is_some() && (force || $entry.is_none()) {
+                $entry = value;
+            }
+        };
+    }
+
+    set!(config.api_key, string("HONEYBADGER_API_KEY"));
+    set!(config.env, detect_env(string));
+    set!(
+        config.report_data,
+        boolean(warnings, "HONEYBADGER_REPORT_DATA")
+    );
+    set!(
+        config.dev_display,
+        boolean(warnings, "HONEYBADGER_DEV_DISPLAY")
+    );
+    set!(
+        config.default_severity,
+        string("HONEYBADGER_DEFAULT_SEVERITY")
+    );
+    set!(
+        config.success_sample_rate,
+        parseable(warnings, "HONEYBADGER_SUCCESS_SAMPLE_RATE")
+    );
+    set!(
+        config.panic_sample_rate,
+        parseable(warnings, "HONEYBADGER_PANIC_SAMPLE_RATE")
+    );
+    set!(
+        config.panic_ignore_messages,
+        string_array("HONEYBADGER_PANIC_IGNORE_MESSAGES")
+    );
+    set!(config.root, string("HONEYBADGER_ROOT"));
+    set!(config.revision, string("HONEYBADGER_REVISION"));
+    set!(config.hostname, string("HONEYBADGER_HOSTNAME"));
+    set!(
+        config.capture_process_info,
+        boolean(warnings, "HONEYBADGER_CAPTURE_PROCESS_INFO")
+    );
+    set!(
+        config.local_time,
+        boolean(warnings, "HONEYBADGER_LOCAL_TIME")
+    );
+    set!(
+        config.async_send,
+        boolean(warnings, "HONEYBADGER_ASYNC_SEND")
+    );
+    set!(
+        config.deferred_stats,
+        boolean(warnings, "HONEYBADGER_DEFERRED_STATS")
+    );
+    set!(
+        config.max_concurrent_sends,
+        parseable(warnings, "HONEYBADGER_MAX_CONCURRENT_SENDS")
+    );
+    set!(
+        config.max_notices_per_process,
+        parseable(warnings, "HONEYBADGER_MAX_NOTICES_PER_PROCESS")
+    );
+    #[cfg(feature = "log-filter")]
+    set!(config.error_filter, string("HONEYBADGER_FILTER"));
+    set!(
+        config.source_url_template,
+        string("HONEYBADGER_SOURCE_URL_TEMPLATE")
+    );
+    set!(
+        config.log_url_template,
+        string("HONEYBADGER_LOG_URL_TEMPLATE")
+    );
+    set!(
+        config.connection.secure,
+        boolean(warnings, "HONEYBADGER_CONNECTION_SECURE")
+    );
+    set!(
+        config.connection.host,
+        string("HONEYBADGER_CONNECTION_HOST")
+    );
+    set!(
+        config.connection.port,
+        parseable(warnings, "HONEYBADGER_CONNECTION_PORT")
+    );
+    set!(
+        config.connection.strict_response,
+        boolean(warnings, "HONEYBADGER_CONNECTION_STRICT_RESPONSE")
+    );
+    set!(
+        config.connection.socket_path,
+        parseable(warnings, "HONEYBADGER_CONNECTION_SOCKET_PATH")
+    );
+    set!(
+        config.connection.max_response_body_bytes,
+        parseable(warnings, "HONEYBADGER_CONNECTION_MAX_RESPONSE_BYTES")
+    );
+    set!(
+        config.connection.max_connections,
+        parseable(warnings, "HONEYBADGER_CONNECTION_MAX_CONNECTIONS")
+    );
+    set!(
+        config.connection.retries,
+        parseable(warnings, "HONEYBADGER_CONNECTION_RETRIES")
+    );
+    set!(
+        config.connection.retry_status_codes,
+        string_array("HONEYBADGER_RETRY_STATUS_CODES").and_then(|codes| {
+            let parsed = codes
+                .iter()
+                .map(|code| code.parse().ok())
+                .collect::<Option<Vec<u16>>>();
+            if parsed.is_none() {
+                warnings.push(ConfigWarning::new(
+                    "HONEYBADGER_RETRY_STATUS_CODES",
+                    codes.join(","),
+                    "not a list of status codes".to_string(),
+                ));
+            }
+            parsed
+        })
+    );
+    set!(
+        config.request.filter_keys,
+        string_array("HONEYBADGER_REQUEST_FILTER_KEYS")
+    );
+    set!(
+        config.request.max_queries,
+        parseable(warnings, "HONEYBADGER_REQUEST_MAX_QUERIES")
+    );
+    set!(
+        config.request.filter_queries,
+        boolean(warnings, "HONEYBADGER_REQUEST_FILTER_QUERIES")
+    );
+    set!(
+        config.request.max_body_bytes,
+        parseable(warnings, "HONEYBADGER_REQUEST_MAX_BODY_BYTES")
+    );
+    set!(
+        config.request.report_preflight,
+        boolean(warnings, "HONEYBADGER_REQUEST_REPORT_PREFLIGHT")
+    );
+    set!(
+        config.request.max_request_entries,
+        parseable(warnings, "HONEYBADGER_REQUEST_MAX_REQUEST_ENTRIES")
+    );
+    set!(
+        config.request.ignore_paths,
+        string_array("HONEYBADGER_REQUEST_IGNORE_PATHS")
+    );
+    set!(
+        config.request.response_headers,
+        string_array("HONEYBADGER_REQUEST_RESPONSE_HEADERS")
+    );
+    for (key, value) in server_attributes(env::vars_os()) {
+        if force || !config.server_attributes.contains_key(&key) {
+            config.server_attributes.insert(key, value);
         }
     }
+}
+
+/// Prefix of the environment variables for `Config::server_attributes`.
+const SERVER_ATTRIBUTE_PREFIX: &str = "HONEYBADGER_SERVER_ATTRIBUTE_";
 
+/// Extracts the server attributes from `HONEYBADGER_SERVER_ATTRIBUTE_{KEY}={VALUE}`.
+/// Keys are lowercased.
+fn server_attributes<I>(vars: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    vars.into_iter()
+        .filter_map(|(name, value)| {
+            let name = name.to_string_lossy();
+            let key = name.strip_prefix(SERVER_ATTRIBUTE_PREFIX)?;
+            if key.is_empty() {
+                return None;
+            }
+            Some((
+                key.to_ascii_lowercase(),
+                value.to_string_lossy().to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Environment variables consulted for the environment name, in order of precedence.
+const ENV_NAME_VARS: &[&str] = &[
+    "HONEYBADGER_ENV",
+    "RAILS_ENV",
+    "RACK_ENV",
+    "APP_ENV",
+    "NODE_ENV",
+];
+
+/// Returns the first non-empty value of `ENV_NAME_VARS`.
+fn detect_env<F>(get_var: F) -> Option<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    ENV_NAME_VARS
+        .iter()
+        .filter_map(|name| get_var(name))
+        .find(|value| !value.is_empty())
+}
+
+/// Same as [`configure`][configure] followed by
+/// [`configure_from_env`][configure_from_env], but only if the resulting
+/// configuration passes [`Config::validate`][validate].
+///
+/// Otherwise the configuration is left untouched. Used by
+/// [`mightybadger::configure_and_setup`][::configure_and_setup].
+///
+/// [configure]: fn.configure.html
+/// [configure_from_env]: fn.configure_from_env.html
+/// [validate]: struct.Config.html#method.validate
+/// [::configure_and_setup]: ../fn.configure_and_setup.html
+pub fn configure_validated<F>(f: F) -> Result<(), ConfigValidationError>
+where
+    F: FnOnce(&mut Config),
+{
+    let mut result = Ok(());
     configure(|config| {
-        set_string(&mut config.api_key, "HONEYBADGER_API_KEY");
-        set_string(&mut config.env, "HONEYBADGER_ENV");
-        set_bool(&mut config.report_data, "HONEYBADGER_REPORT_DATA");
-        set_string(&mut config.root, "HONEYBADGER_ROOT");
-        set_string(&mut config.revision, "HONEYBADGER_REVISION");
-        set_string(&mut config.hostname, "HONEYBADGER_HOSTNAME");
-        set_bool(
-            &mut config.connection.secure,
-            "HONEYBADGER_CONNECTION_SECURE",
-        );
-        set_string(&mut config.connection.host, "HONEYBADGER_CONNECTION_HOST");
-        set_parseable(&mut config.connection.port, "HONEYBADGER_CONNECTION_PORT");
-        set_string_array(
-            &mut config.request.filter_keys,
-            "HONEYBADGER_REQUEST_FILTER_KEYS",
-        );
-    })
+        let mut scratch = config.clone();
+        f(&mut scratch);
+        load_env(&mut scratch, false);
+        result = scratch.validate();
+        if result.is_ok() {
+            *config = scratch;
+        }
+    });
+    result
 }
 
 /// Modifies Honeybadger configuration.
@@ -241,10 +1174,78 @@ where
 /// Since we only do `mem::replace` after lock acquisition (even without dropping),
 /// it is guaranteed not to poison `CONFIG`.
 fn replace_config(new_config: Config) -> Config {
+    let derived = {
+        let config = read_config();
+        Derived::new(&new_config, &config, config.derived())
+    };
     let mut config = CONFIG
         .write()
         .expect("Could not acquire write-lock for mightybadger::config::CONFIG.");
-    mem::replace(&mut config, new_config)
+    mem::replace(&mut *config, (new_config, Arc::new(derived))).0
+}
+
+/// Values derived from the global configuration.
+///
+/// They are computed when the configuration is set, rather than for each notice.
+#[derive(Debug, Default)]
+pub(crate) struct Derived {
+    /// `request.filter_value_patterns`, compiled.
+    #[cfg(feature = "regex")]
+    pub(crate) value_patterns: Option<regex::RegexSet>,
+    /// `error_filter`, parsed.
+    #[cfg(feature = "log-filter")]
+    pub(crate) error_filter: Option<ErrorFilter>,
+    /// The checked-out commit of the git repository containing `root`.
+    pub(crate) git_head: Option<GitHead>,
+}
+
+impl Derived {
+    /// The revision detected at `root`, which is the default of `revision`.
+    pub(crate) fn detected_revision(&self) -> Option<&String> {
+        self.git_head.as_ref()?.revision.as_ref()
+    }
+
+    /// Derives the values for `config`, reusing those from `old` whose source
+    /// in `old_config` is unchanged.
+    fn new(config: &Config, old_config: &Config, old: &Derived) -> Derived {
+        Derived {
+            #[cfg(feature = "regex")]
+            value_patterns: if config.request.filter_value_patterns
+                == old_config.request.filter_value_patterns
+            {
+                old.value_patterns.clone()
+            } else {
+                config
+                    .request
+                    .filter_value_patterns
+                    .as_ref()
+                    .and_then(|patterns| compile_value_patterns(patterns))
+            },
+            #[cfg(feature = "log-filter")]
+            error_filter: if config.error_filter == old_config.error_filter {
+                old.error_filter.clone()
+            } else {
+                config.error_filter.as_deref().map(ErrorFilter::parse)
+            },
+            git_head: if config.root == old_config.root {
+                old.git_head.clone()
+            } else {
+                config
+                    .root
+                    .as_ref()
+                    .and_then(|root| git::detect_revision(Path::new(root)))
+            },
+        }
+    }
+}
+
+/// Compiles `filter_value_patterns`, skipping the invalid ones.
+#[cfg(feature = "regex")]
+pub(crate) fn compile_value_patterns(patterns: &[String]) -> Option<regex::RegexSet> {
+    let patterns = patterns
+        .iter()
+        .filter(|pattern| regex::Regex::new(pattern).is_ok());
+    regex::RegexSet::new(patterns).ok()
 }
 
 /// Read-lock to the global configuration.
@@ -253,12 +1254,25 @@ fn replace_config(new_config: Config) -> Config {
 ///
 /// [read_config]: fn.read_config.html
 #[derive(Debug)]
-pub struct ConfigReadGuard(RwLockReadGuard<'static, Config>);
+pub struct ConfigReadGuard(RwLockReadGuard<'static, (Config, Arc<Derived>)>);
+
+impl ConfigReadGuard {
+    /// The values derived from the configuration.
+    pub(crate) fn derived(&self) -> &Derived {
+        &(self.0).1
+    }
+
+    /// A copy of the configuration together with its derived values,
+    /// so that a notice is assembled from a single snapshot.
+    pub(crate) fn snapshot(&self) -> (Config, Arc<Derived>) {
+        ((self.0).0.clone(), (self.0).1.clone())
+    }
+}
 
 impl Deref for ConfigReadGuard {
     type Target = Config;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &(self.0).0
     }
 }
 
@@ -338,6 +1352,146 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_sample_rate() {
+        assert_eq!(SampleRate::new(0.25).map(SampleRate::get), Some(0.25));
+        assert_eq!(SampleRate::new(1.5), None);
+        assert_eq!(SampleRate::new(f64::NAN), None);
+        assert_eq!("0.001".parse::<SampleRate>(), Ok(SampleRate(0.001)));
+        assert!("-1".parse::<SampleRate>().is_err());
+        assert!("x".parse::<SampleRate>().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = Config {
+            env: Some("development".to_string()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        config.env = Some("production".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigValidationError::MissingApiKey(_))
+        ));
+        config.api_key = Some("abcd1234".to_string());
+        assert!(config.validate().is_ok());
+        config.connection.host = Some("https://api.honeybadger.io/".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigValidationError::InvalidValue("connection.host", ..))
+        ));
+        config.connection.host = None;
+        config.request.filter_value_patterns = Some(vec!["(".to_string()]);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigValidationError::InvalidValue(
+                "request.filter_value_patterns",
+                ..
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_configure_validated() {
+        let _guard = reset();
+        let result = configure_validated(|config| {
+            config.env = Some("production".to_string());
+            config.api_key = Some("abcd1234".to_string());
+            config.connection.port = Some(0);
+        });
+        assert!(matches!(
+            result,
+            Err(ConfigValidationError::InvalidValue("connection.port", ..))
+        ));
+        assert_eq!(read_config().env, None);
+        let result = configure_validated(|config| {
+            config.env = Some("production".to_string());
+            config.api_key = Some("abcd1234".to_string());
+        });
+        assert!(result.is_ok());
+        assert_eq!(read_config().env, Some("production".to_string()));
+    }
+
+    #[test]
+    fn test_report_method() {
+        let mut config = RequestConfig::default();
+        assert!(config.report_method("GET"));
+        assert!(!config.report_method("OPTIONS"));
+        assert!(!config.report_method("head"));
+        config.report_preflight = Some(true);
+        assert!(config.report_method("OPTIONS"));
+    }
+
+    #[test]
+    fn test_report_path() {
+        let mut config = RequestConfig::default();
+        assert!(config.report_path("/healthz"));
+        config.ignore_paths = Some(vec!["/healthz".to_string(), "/status/*".to_string()]);
+        assert!(!config.report_path("/healthz"));
+        assert!(!config.report_path("http://localhost:8080/healthz?verbose=1"));
+        assert!(config.report_path("/healthz/deep"));
+        assert!(config.report_path("/api/healthz"));
+        assert!(!config.report_path("/status/db"));
+        assert!(config.report_path("/status"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("a*c", "abc"));
+        assert!(glob_match("a*c", "ac"));
+        assert!(glob_match("*b*", "abc"));
+        assert!(!glob_match("a*c", "abcd"));
+        assert!(!glob_match("a*b*c", "acb"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_detect_env() {
+        fn detect(vars: &[(&str, &str)]) -> Option<String> {
+            detect_env(|name| {
+                vars.iter()
+                    .find(|&&(k, _)| k == name)
+                    .map(|&(_, v)| v.to_string())
+            })
+        }
+        assert_eq!(detect(&[]), None);
+        assert_eq!(
+            detect(&[("NODE_ENV", "staging"), ("APP_ENV", "production")]),
+            Some("production".to_string())
+        );
+        assert_eq!(
+            detect(&[("RAILS_ENV", "production"), ("HONEYBADGER_ENV", "test")]),
+            Some("test".to_string())
+        );
+        assert_eq!(
+            detect(&[("HONEYBADGER_ENV", ""), ("RACK_ENV", "development")]),
+            Some("development".to_string())
+        );
+    }
+
+    #[test]
+    fn test_server_attributes() {
+        let vars = vec![
+            ("HONEYBADGER_SERVER_ATTRIBUTE_PIPELINE_ID", "1234"),
+            ("HONEYBADGER_SERVER_ATTRIBUTE_", "empty"),
+            ("HONEYBADGER_API_KEY", "abcd"),
+            ("HONEYBADGER_SERVER_ATTRIBUTE_deploy_trigger", "manual"),
+        ];
+        let vars = vars
+            .into_iter()
+            .map(|(k, v)| (OsString::from(k), OsString::from(v)));
+        let mut attributes = server_attributes(vars);
+        attributes.sort();
+        assert_eq!(
+            attributes,
+            vec![
+                ("deploy_trigger".to_string(), "manual".to_string()),
+                ("pipeline_id".to_string(), "1234".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_read_config_in_configure() {
         let _guard = reset();
@@ -2,17 +2,39 @@
 //! similar to the one provided by `scoped_tls`, but it allows both
 //! scoped and guarded modifications of the thread-local context.
 
-use std::cell::RefCell;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::panic::{catch_unwind, UnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
 
+use failure::Fail;
 use scoped_tls::scoped_thread_local;
+use serde_derive::{Deserialize, Serialize};
 
-use crate::payload::RequestInfo;
+use crate::config;
+use crate::payload::{ErrorInfo, QueryInfo, RequestInfo};
+use crate::{FailOrError, NoticeOptions};
 
 scoped_thread_local!(
     static SCOPED_CONTEXT: RequestInfo
 );
 thread_local! {
-    static DEFAULT_CONTEXT: RefCell<Option<RequestInfo>> = RefCell::new(None);
+    static DEFAULT_CONTEXT: RefCell<Option<RequestInfo>> = const { RefCell::new(None) };
+    static QUERIES: RefCell<VecDeque<QueryInfo>> = const { RefCell::new(VecDeque::new()) };
+    /// Labels of the enclosing `with_error_boundary` calls.
+    static BOUNDARY_LABELS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    /// Whether the panic hook has reported the panic being unwound.
+    static PANIC_REPORTED: Cell<bool> = const { Cell::new(false) };
+    /// Exceptions recorded by `record_exception`.
+    static EXCEPTIONS: RefCell<Vec<ErrorInfo>> = const { RefCell::new(Vec::new()) };
+    /// Names of the enclosing `with_span` calls.
+    static SPANS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
 }
 
 pub fn get() -> Option<RequestInfo> {
@@ -43,3 +65,799 @@ pub fn unset() {
         *ctx = None;
     });
 }
+
+/// Tags errors reported from the current thread with a transaction ID,
+/// stored as `transaction_id` in the context.
+///
+/// Use the same ID for the errors of one logical transaction, e.g. a
+/// database error and the handler error it causes, possibly across
+/// threads and processes. Honeybadger doesn't merge notices by context
+/// fields: they are still grouped into errors by class and location (or
+/// fingerprint). The ID is shown in the context of each notice, and
+/// searching for `context.transaction_id:"<id>"` lists the related notices.
+///
+/// Like [`set`][set], this updates the thread's default context, creating
+/// one if there is none. Contexts installed by [`with`][with] are not
+/// modified; set the field on them directly.
+///
+/// [set]: fn.set.html
+/// [with]: fn.with.html
+///
+/// ## Example
+///
+/// ```
+/// let id = mightybadger::context::generate_transaction_id();
+/// mightybadger::context::set_transaction_id(id);
+/// // Errors reported here carry `context.transaction_id`.
+/// ```
+pub fn set_transaction_id(id: impl Into<String>) {
+    DEFAULT_CONTEXT.with(|ctx| {
+        ctx.borrow_mut()
+            .get_or_insert_with(RequestInfo::default)
+            .context
+            .insert("transaction_id".to_string(), id.into().into());
+    });
+}
+
+/// Creates a new random ID for [`set_transaction_id`][set_transaction_id].
+///
+/// [set_transaction_id]: fn.set_transaction_id.html
+pub fn generate_transaction_id() -> String {
+    crate::random_uuid()
+        .expect("random UUIDs are always generated")
+        .to_string()
+}
+
+/// Makes a new context from environment variables, e.g. those passed to a
+/// serverless function or a batch job, and sets it like [`set`][set].
+///
+/// Each pair of `mapping` is the name of an environment variable and the
+/// key it is stored under. The keys `url`, `component`, and `action` set
+/// the fields of the same names, and upper-case keys such as `REQUEST_URI`
+/// are stored in `cgi_data`. Other keys are stored in `context`.
+/// Unset variables are skipped.
+///
+/// [set]: fn.set.html
+///
+/// ## Example
+///
+/// ```
+/// mightybadger::context::set_from_env(&[
+///     ("REQUEST_ID", "request_id"),
+///     ("TRACE_ID", "trace_id"),
+///     ("JOB_URL", "url"),
+/// ]);
+/// ```
+pub fn set_from_env(mapping: &[(&str, &str)]) {
+    let mut request = RequestInfo::default();
+    for &(env_name, key) in mapping {
+        let value = match std::env::var(env_name) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        match key {
+            "url" => request.url = value,
+            "component" => request.component = value,
+            "action" => request.action = value,
+            _ if is_cgi_name(key) => {
+                request.cgi_data.insert(key.to_string(), value);
+            }
+            _ => {
+                request.context.insert(key.to_string(), value.into());
+            }
+        }
+    }
+    set(request);
+}
+
+/// Returns whether `key` looks like a CGI variable, e.g. `HTTP_X_REQUEST_ID`.
+fn is_cgi_name(key: &str) -> bool {
+    key.starts_with(|c: char| c.is_ascii_uppercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Runs `f` with a copy of the current context modified by `m`.
+///
+/// The modification only applies within `f`, like [`with`][with].
+/// If there is no context, `m` is applied to an empty one.
+///
+/// [with]: fn.with.html
+///
+/// ## Example
+///
+/// ```
+/// mightybadger::context::modify(
+///     |request| request.component = "mailer".to_string(),
+///     || {
+///         // Errors reported here have the component `mailer`.
+///     },
+/// );
+/// ```
+pub fn modify<R, M, F>(m: M, f: F) -> R
+where
+    M: FnOnce(&mut RequestInfo),
+    F: FnOnce() -> R,
+{
+    let mut request = get().unwrap_or_default();
+    m(&mut request);
+    with(&request, f)
+}
+
+/// Runs `fut` with `r` as the context, like [`with`][with] for futures.
+///
+/// The context is set around each `poll` of `fut`, so errors reported
+/// from any part of it carry the context, even after it is resumed from
+/// an `.await` on another thread. The context of the thread polling the
+/// returned future is not visible to `fut`.
+///
+/// [with]: fn.with.html
+///
+/// ## Example
+///
+/// ```
+/// use mightybadger::payload::RequestInfo;
+///
+/// let request = RequestInfo {
+///     component: "worker".to_string(),
+///     ..Default::default()
+/// };
+/// let task = mightybadger::context::instrument(request, async {
+///     // Errors reported here have the component `worker`.
+/// });
+/// # drop(task);
+/// ```
+pub fn instrument<F: Future>(r: RequestInfo, fut: F) -> Instrumented<F> {
+    Instrumented { context: r, fut }
+}
+
+/// Future returned by [`instrument`][instrument].
+///
+/// [instrument]: fn.instrument.html
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Instrumented<F> {
+    context: RequestInfo,
+    fut: F,
+}
+
+impl<F> Instrumented<F> {
+    /// Returns the context the future is run with.
+    pub fn context(&self) -> &RequestInfo {
+        &self.context
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // SAFETY: `fut` is pinned along with `self` and never moved out of it,
+        // while `context` is never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let fut = unsafe { Pin::new_unchecked(&mut this.fut) };
+        with(&this.context, || fut.poll(cx))
+    }
+}
+
+/// Runs `f` in a named span; see the [`with_span!`][with_span] macro.
+///
+/// `component` and `action` of the context are set to `name` and `action`
+/// within `f`, and notices reported within `f` are tagged with `name`
+/// (and the names of the enclosing spans).
+///
+/// [with_span]: ../macro.with_span.html
+pub fn with_span<R, F>(name: &str, action: &str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct SpanGuard;
+
+    impl Drop for SpanGuard {
+        fn drop(&mut self) {
+            SPANS.with(|spans| spans.borrow_mut().pop());
+        }
+    }
+
+    SPANS.with(|spans| spans.borrow_mut().push(name.to_string()));
+    let _guard = SpanGuard;
+    modify(
+        |request| {
+            request.component = name.to_string();
+            request.action = action.to_string();
+        },
+        f,
+    )
+}
+
+/// Names of the enclosing `with_span` calls, outermost first.
+pub(crate) fn span_names() -> Vec<String> {
+    SPANS.with(|spans| spans.borrow().clone())
+}
+
+/// Makes `r` the current thread's context until the guard is dropped.
+///
+/// Unlike [`set`][set], the previous context (or the absence of one) is
+/// restored on drop, so early returns and panics cannot leak the context
+/// into the next request handled by the thread.
+///
+/// [set]: fn.set.html
+///
+/// ## Example
+///
+/// ```
+/// fn handle(request: mightybadger::payload::RequestInfo) {
+///     let _guard = mightybadger::context::guard(request);
+///     // Errors reported here carry the request information.
+/// }
+/// # handle(Default::default());
+/// ```
+pub fn guard(r: RequestInfo) -> ContextGuard {
+    ContextGuard::new(r)
+}
+
+/// Runs `f`, reporting a panic from it with `label` as a tag.
+///
+/// The panic is caught and returned as `Err`, like `std::panic::catch_unwind`,
+/// so that a supervisor can decide what to do with it.
+/// If the panic hook is [installed][::install_hook], the panic is reported
+/// from the hook (with the labels of all enclosing boundaries as tags);
+/// otherwise it is reported here.
+///
+/// [::install_hook]: ../fn.install_hook.html
+///
+/// ## Example
+///
+/// ```
+/// let result = mightybadger::context::with_error_boundary("plugin:foo", || {
+///     // Run the plugin...
+/// });
+/// if result.is_err() {
+///     // Disable the plugin...
+/// }
+/// ```
+pub fn with_error_boundary<R, F>(label: &str, f: F) -> Result<R, Box<dyn Any + Send>>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    catch_and_report(Some(label), f)
+}
+
+/// Implementation of `with_error_boundary` and `capture_panics`.
+pub(crate) fn catch_and_report<R, F>(label: Option<&str>, f: F) -> Result<R, Box<dyn Any + Send>>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    if let Some(label) = label {
+        BOUNDARY_LABELS.with(|labels| labels.borrow_mut().push(label.to_string()));
+    }
+    PANIC_REPORTED.with(|reported| reported.set(false));
+    let result = catch_unwind(f);
+    if label.is_some() {
+        BOUNDARY_LABELS.with(|labels| labels.borrow_mut().pop());
+    }
+    if let Err(ref payload) = result {
+        if !take_panic_reported() {
+            let tags = match label {
+                Some(ref label) => std::slice::from_ref(label),
+                None => &[],
+            };
+            crate::notify_panic(&crate::Panic::from_payload(&**payload), tags);
+        }
+    }
+    result
+}
+
+/// Labels of the enclosing `with_error_boundary` calls, outermost first.
+pub(crate) fn boundary_labels() -> Vec<String> {
+    BOUNDARY_LABELS.with(|labels| labels.borrow().clone())
+}
+
+/// Records that the panic hook has reported the panic being unwound.
+pub(crate) fn mark_panic_reported() {
+    PANIC_REPORTED.with(|reported| reported.set(true));
+}
+
+/// Returns whether the panic hook has reported the last panic on this thread,
+/// and forgets it.
+pub(crate) fn take_panic_reported() -> bool {
+    PANIC_REPORTED.with(|reported| reported.replace(false))
+}
+
+/// Owned copy of a context, for moving it across threads or `async` boundaries.
+///
+/// Created by [`fork`][fork].
+///
+/// [fork]: fn.fork.html
+#[derive(Debug, Clone)]
+pub struct OwnedContext(RequestInfo);
+
+impl OwnedContext {
+    /// Makes this context the current thread's context until the guard is dropped.
+    ///
+    /// Like [`set`][set], it is shadowed by an enclosing [`with`][with] scope.
+    ///
+    /// [set]: fn.set.html
+    /// [with]: fn.with.html
+    pub fn activate(&self) -> ContextGuard {
+        ContextGuard::new(self.0.clone())
+    }
+
+    /// Returns the forked request information.
+    pub fn into_inner(mut self) -> RequestInfo {
+        mem::take(&mut self.0)
+    }
+}
+
+/// Does nothing: dropping a forked context doesn't affect any thread's
+/// context, which only the guard returned by [`activate`][activate] restores.
+///
+/// Implemented anyway so that adding cleanup here later is not a breaking
+/// change.
+///
+/// [activate]: struct.OwnedContext.html#method.activate
+impl Drop for OwnedContext {
+    fn drop(&mut self) {}
+}
+
+/// Clones the current context so that it can be moved to another thread.
+///
+/// Returns `None` if there is no context.
+///
+/// ## Example
+///
+/// ```
+/// use std::thread;
+///
+/// mightybadger::context::set(Default::default());
+/// let context = mightybadger::context::fork();
+/// thread::spawn(move || {
+///     let _guard = context.as_ref().map(|context| context.activate());
+///     // Errors reported here carry the parent's context.
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub fn fork() -> Option<OwnedContext> {
+    get().map(OwnedContext)
+}
+
+/// The current context serialized to bytes, for carrying it across an FFI boundary.
+///
+/// Created by [`snapshot_context`][snapshot_context].
+/// The bytes are not filtered, so they should not leave the process.
+///
+/// [snapshot_context]: fn.snapshot_context.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextSnapshot(Vec<u8>);
+
+/// The contents of `ContextSnapshot`.
+#[derive(Serialize, Deserialize)]
+struct SnapshotData {
+    request: Option<RequestInfo>,
+    /// Not serialized as a part of `RequestInfo`.
+    api_key_override: Option<String>,
+}
+
+impl ContextSnapshot {
+    /// Returns the serialized context.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Reads a snapshot from the bytes returned by [`as_bytes`](#method.as_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<ContextSnapshot, serde_json::Error> {
+        serde_json::from_slice::<SnapshotData>(bytes)?;
+        Ok(ContextSnapshot(bytes.to_vec()))
+    }
+}
+
+/// Serializes the current context, including an enclosing [`with`][with] scope.
+///
+/// Unlike [`fork`][fork], the snapshot is plain bytes, so it can be handed to
+/// foreign code and back. For example, a JNI library can take a snapshot
+/// before handing work to a thread started in Java, and
+/// [`restore_context`][restore_context] it when Java calls back into Rust
+/// on that thread, which has no context of its own.
+///
+/// [with]: fn.with.html
+/// [fork]: fn.fork.html
+/// [restore_context]: fn.restore_context.html
+pub fn snapshot_context() -> ContextSnapshot {
+    let mut request = get();
+    let api_key_override = request
+        .as_mut()
+        .and_then(|request| request.api_key_override.take());
+    let data = SnapshotData {
+        request,
+        api_key_override,
+    };
+    ContextSnapshot(serde_json::to_vec(&data).expect("could not serialize the context"))
+}
+
+/// Makes the context in `snapshot` the current thread's context, like [`set`][set].
+///
+/// Call [`unset`][unset] when returning to foreign code if the thread
+/// may run unrelated work afterwards.
+///
+/// [set]: fn.set.html
+/// [unset]: fn.unset.html
+pub fn restore_context(snapshot: &ContextSnapshot) {
+    // Snapshots are either serialized here or validated by `from_bytes`.
+    let data =
+        serde_json::from_slice::<SnapshotData>(&snapshot.0).expect("invalid context snapshot");
+    match data.request {
+        Some(mut request) => {
+            request.api_key_override = data.api_key_override;
+            set(request);
+        }
+        None => unset(),
+    }
+}
+
+/// Restores the previous context when dropped.
+///
+/// Returned by [`guard`][guard] and [`OwnedContext::activate`][activate].
+///
+/// [guard]: fn.guard.html
+/// [activate]: struct.OwnedContext.html#method.activate
+#[derive(Debug)]
+#[must_use = "the context is restored as soon as the guard is dropped"]
+pub struct ContextGuard {
+    prev: Option<RequestInfo>,
+    /// The guard must be dropped on the thread it was created on.
+    _marker: PhantomData<*const ()>,
+}
+
+impl ContextGuard {
+    fn new(r: RequestInfo) -> Self {
+        let prev = DEFAULT_CONTEXT.with(|ctx| ctx.borrow_mut().replace(r));
+        ContextGuard {
+            prev,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        DEFAULT_CONTEXT.with(|ctx| *ctx.borrow_mut() = prev);
+    }
+}
+
+/// Records a database query executed on the current thread.
+///
+/// The last [`max_queries`][max_queries] queries are kept and attached to
+/// the notice as `request.context.queries`. Literals are replaced with `?`
+/// unless [`filter_queries`][filter_queries] is disabled.
+///
+/// Call it from the logging callback of your database library, and call
+/// [`clear_queries`][clear_queries] at the start of each request.
+///
+/// [max_queries]: ../config/struct.RequestConfig.html#structfield.max_queries
+/// [filter_queries]: ../config/struct.RequestConfig.html#structfield.filter_queries
+/// [clear_queries]: fn.clear_queries.html
+///
+/// ## Example
+///
+/// ```
+/// use std::time::Instant;
+///
+/// let sql = "SELECT * FROM users WHERE id = 42";
+/// let start = Instant::now();
+/// // ... execute the query ...
+/// mightybadger::context::add_query(sql, start.elapsed());
+/// ```
+pub fn add_query(sql: &str, duration: Duration) {
+    let (max_queries, filter_queries) = {
+        let config = config::read_config();
+        (
+            config.request.max_queries.unwrap_or(10),
+            config.request.filter_queries.unwrap_or(true),
+        )
+    };
+    let sql = if filter_queries {
+        filter_sql(sql)
+    } else {
+        sql.to_string()
+    };
+    let duration = duration.as_secs_f64() * 1000.0;
+    QUERIES.with(|queries| {
+        let mut queries = queries.borrow_mut();
+        queries.push_back(QueryInfo { sql, duration });
+        while queries.len() > max_queries {
+            queries.pop_front();
+        }
+    });
+}
+
+/// Forgets the queries recorded on the current thread.
+pub fn clear_queries() {
+    QUERIES.with(|queries| queries.borrow_mut().clear());
+}
+
+pub(crate) fn queries() -> Vec<QueryInfo> {
+    QUERIES.with(|queries| queries.borrow().iter().cloned().collect())
+}
+
+/// Records a non-fatal error on the current thread without reporting it yet.
+///
+/// The recorded errors are sent by [`flush_exceptions`][flush_exceptions],
+/// e.g. at the end of a request.
+///
+/// [flush_exceptions]: fn.flush_exceptions.html
+///
+/// ## Example
+///
+/// ```
+/// let error = failure::err_msg("cache miss");
+/// mightybadger::context::record_exception(error.as_fail(), &["cache"]);
+/// // ...
+/// mightybadger::context::flush_exceptions();
+/// ```
+pub fn record_exception(error: &dyn Fail, tags: &[&str]) {
+    let config = config::read_config();
+    let options = NoticeOptions {
+        tags,
+        ..Default::default()
+    };
+    let exception = crate::error_info(FailOrError::Fail(error), &None, &options, &config);
+    EXCEPTIONS.with(|exceptions| exceptions.borrow_mut().push(exception));
+}
+
+/// Same as [`record_exception`][record_exception] without tags.
+///
+/// Meant for request handlers that accumulate non-fatal errors, e.g. from a
+/// validation pipeline: record each error as it occurs, then call
+/// [`flush_exceptions_with`][flush_exceptions_with] at the end of the
+/// request to send them.
+///
+/// [record_exception]: fn.record_exception.html
+/// [flush_exceptions_with]: fn.flush_exceptions_with.html
+pub fn record_error(error: &dyn Fail) {
+    record_exception(error, &[]);
+}
+
+/// How [`flush_exceptions_with`][flush_exceptions_with] sends the recorded
+/// errors.
+///
+/// [flush_exceptions_with]: fn.flush_exceptions_with.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aggregation {
+    /// One notice. The first error becomes the error of the notice, and the
+    /// others, each followed by its own causes, are appended to its causes.
+    /// The tags of all errors are merged.
+    #[default]
+    Combined,
+    /// One notice per error, each with its own tags.
+    Separate,
+}
+
+/// Sends the errors recorded by [`record_exception`][record_exception]
+/// on the current thread as one notice, and forgets them.
+///
+/// Same as [`flush_exceptions_with`][flush_exceptions_with] with
+/// [`Aggregation::Combined`][Aggregation::Combined].
+///
+/// [record_exception]: fn.record_exception.html
+/// [flush_exceptions_with]: fn.flush_exceptions_with.html
+/// [Aggregation::Combined]: enum.Aggregation.html#variant.Combined
+pub fn flush_exceptions() {
+    flush_exceptions_with(Aggregation::Combined);
+}
+
+/// Sends the errors recorded by [`record_exception`][record_exception]
+/// on the current thread as `aggregation` says, and forgets them.
+///
+/// Nothing is sent if no errors have been recorded.
+///
+/// [record_exception]: fn.record_exception.html
+///
+/// ## Example
+///
+/// ```
+/// use mightybadger::context::{self, Aggregation};
+///
+/// for field in &["email", "name"] {
+///     let error = failure::format_err!("invalid {}", field);
+///     context::record_error(error.as_fail());
+/// }
+/// context::flush_exceptions_with(Aggregation::Separate);
+/// ```
+pub fn flush_exceptions_with(aggregation: Aggregation) {
+    let exceptions = EXCEPTIONS.with(|exceptions| mem::take(&mut *exceptions.borrow_mut()));
+    if !exceptions.is_empty() {
+        crate::notify_recorded(exceptions, aggregation);
+    }
+}
+
+/// Replaces string and numeric literals with `?`.
+fn filter_sql(sql: &str) -> String {
+    let mut filtered = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut prev_ident = false;
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // Skip to the closing quote, honoring the `''` escape.
+            while let Some(c) = chars.next() {
+                if c == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            filtered.push('?');
+            prev_ident = false;
+        } else if c.is_ascii_digit() && !prev_ident {
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '.' {
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            filtered.push('?');
+            prev_ident = false;
+        } else {
+            filtered.push(c);
+            prev_ident = c.is_alphanumeric() || c == '_';
+        }
+    }
+    filtered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_sql() {
+        assert_eq!(
+            filter_sql("SELECT * FROM t1 WHERE name = 'O''Brien' AND age > 42.5"),
+            "SELECT * FROM t1 WHERE name = ? AND age > ?"
+        );
+        assert_eq!(
+            filter_sql("SELECT \"col2\" FROM t"),
+            "SELECT \"col2\" FROM t"
+        );
+    }
+
+    #[test]
+    fn test_set_from_env() {
+        std::env::set_var("MIGHTYBADGER_TEST_REQUEST_ID", "req-1");
+        std::env::set_var("MIGHTYBADGER_TEST_JOB_URL", "https://example.com/jobs/1");
+        std::env::set_var("MIGHTYBADGER_TEST_METHOD", "POST");
+        set_from_env(&[
+            ("MIGHTYBADGER_TEST_REQUEST_ID", "request_id"),
+            ("MIGHTYBADGER_TEST_JOB_URL", "url"),
+            ("MIGHTYBADGER_TEST_METHOD", "REQUEST_METHOD"),
+            ("MIGHTYBADGER_TEST_UNSET", "trace_id"),
+        ]);
+        let request = get().unwrap();
+        assert_eq!(request.url, "https://example.com/jobs/1");
+        assert_eq!(request.cgi_data["REQUEST_METHOD"], "POST");
+        assert_eq!(request.context["request_id"], "req-1");
+        assert!(!request.context.contains_key("trace_id"));
+        unset();
+    }
+
+    #[test]
+    fn test_fork() {
+        set(RequestInfo {
+            url: "http://example.com/".to_string(),
+            ..Default::default()
+        });
+        let context = fork().unwrap();
+        std::thread::spawn(move || {
+            assert!(get().is_none());
+            {
+                let _guard = context.activate();
+                assert_eq!(get().unwrap().url, "http://example.com/");
+            }
+            assert!(get().is_none());
+        })
+        .join()
+        .unwrap();
+        unset();
+    }
+
+    #[test]
+    fn test_set_transaction_id() {
+        let id = generate_transaction_id();
+        assert_eq!(id.len(), 36);
+        assert_ne!(id, generate_transaction_id());
+        unset();
+        set_transaction_id(id.clone());
+        let request = get().unwrap();
+        assert_eq!(request.context["transaction_id"], id.as_str());
+        unset();
+    }
+
+    #[test]
+    fn test_snapshot_context() {
+        std::thread::spawn(|| {
+            let snapshot = snapshot_context();
+            let mut request = RequestInfo {
+                url: "http://example.com/a".to_string(),
+                api_key_override: Some("tenant-key".to_string()),
+                ..Default::default()
+            };
+            request
+                .context
+                .insert("user_id".to_string(), serde_json::json!(42));
+            set(request);
+            let bytes = snapshot_context().as_bytes().to_vec();
+
+            restore_context(&snapshot);
+            assert!(get().is_none());
+
+            let snapshot = ContextSnapshot::from_bytes(&bytes).unwrap();
+            restore_context(&snapshot);
+            let request = get().unwrap();
+            assert_eq!(request.url, "http://example.com/a");
+            assert_eq!(request.context["user_id"], 42);
+            assert_eq!(request.api_key_override.as_deref(), Some("tenant-key"));
+
+            assert!(ContextSnapshot::from_bytes(b"garbage").is_err());
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_guard() {
+        std::thread::spawn(|| {
+            let request = |url: &str| RequestInfo {
+                url: url.to_string(),
+                ..Default::default()
+            };
+            {
+                let _guard = guard(request("http://example.com/a"));
+                assert_eq!(get().unwrap().url, "http://example.com/a");
+            }
+            assert!(get().is_none());
+
+            set(request("http://example.com/b"));
+            {
+                let _guard = guard(request("http://example.com/c"));
+                assert_eq!(get().unwrap().url, "http://example.com/c");
+            }
+            assert_eq!(get().unwrap().url, "http://example.com/b");
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_with_span() {
+        let component = || get().map(|request| request.component);
+        with_span("billing", "charge", || {
+            assert_eq!(component().as_deref(), Some("billing"));
+            assert_eq!(get().unwrap().action, "charge");
+            with_span("stripe", "", || {
+                assert_eq!(component().as_deref(), Some("stripe"));
+                assert_eq!(span_names(), ["billing", "stripe"]);
+            });
+            assert_eq!(component().as_deref(), Some("billing"));
+        });
+        assert!(span_names().is_empty());
+    }
+
+    #[test]
+    fn test_add_query() {
+        clear_queries();
+        for i in 0..15 {
+            add_query(&format!("SELECT {}", i), Duration::from_millis(3));
+        }
+        let queries = queries();
+        assert_eq!(queries.len(), 10);
+        assert_eq!(queries[0].sql, "SELECT ?");
+        assert_eq!(queries[0].duration, 3.0);
+        clear_queries();
+        assert!(super::queries().is_empty());
+    }
+}
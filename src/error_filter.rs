@@ -0,0 +1,115 @@
+//! `RUST_LOG`-style filtering of error classes.
+//!
+//! This module is available with the `log-filter` feature.
+
+/// A single `path=off` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Directive {
+    /// Prefix of error classes it applies to, or `None` for all.
+    name: Option<String>,
+    enabled: bool,
+}
+
+/// Parsed [`Config::error_filter`][error_filter].
+///
+/// [error_filter]: ../config/struct.Config.html#structfield.error_filter
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct ErrorFilter {
+    /// Sorted by the length of the name, so that the last match is the most specific.
+    directives: Vec<Directive>,
+}
+
+impl ErrorFilter {
+    /// Parses comma-separated directives such as
+    /// `"app::db=off,app::db::TimeoutError=on"`.
+    ///
+    /// Each directive is a path prefix followed by `=off` or `=on`;
+    /// a path alone means `on`, and `off` alone applies to all errors.
+    /// Invalid directives are ignored with a warning.
+    pub(crate) fn parse(spec: &str) -> Self {
+        let mut directives = Vec::new();
+        for part in spec.split(',').map(|part| part.trim()) {
+            if part.is_empty() {
+                continue;
+            }
+            let mut parts = part.split('=');
+            let (name, enabled) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(part), None, None) => match parse_switch(part) {
+                    Some(enabled) => (None, enabled),
+                    None => (Some(part), true),
+                },
+                (Some(name), Some(value), None) => match parse_switch(value) {
+                    Some(enabled) => (Some(name).filter(|name| !name.is_empty()), enabled),
+                    None => {
+                        eprintln!(
+                            "** [Honeybadger] Ignoring invalid error filter directive: {}",
+                            part
+                        );
+                        continue;
+                    }
+                },
+                _ => {
+                    eprintln!(
+                        "** [Honeybadger] Ignoring invalid error filter directive: {}",
+                        part
+                    );
+                    continue;
+                }
+            };
+            directives.push(Directive {
+                name: name.map(|name| name.to_string()),
+                enabled,
+            });
+        }
+        directives.sort_by_key(|directive| directive.name.as_ref().map_or(0, |name| name.len()));
+        ErrorFilter { directives }
+    }
+
+    /// Returns `true` if errors of the class should be reported.
+    pub(crate) fn enabled(&self, class: &str) -> bool {
+        let directive = self
+            .directives
+            .iter()
+            .rev()
+            .find(|directive| match directive.name {
+                Some(ref name) => class.starts_with(name.as_str()),
+                None => true,
+            });
+        match directive {
+            Some(directive) => directive.enabled,
+            None => true,
+        }
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("off") {
+        Some(false)
+    } else if value.eq_ignore_ascii_case("on") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_filter() {
+        let filter = ErrorFilter::parse("app::db=off, app::db::TimeoutError=on,std::num");
+        assert!(!filter.enabled("app::db::ConnectionError"));
+        assert!(filter.enabled("app::db::TimeoutError"));
+        assert!(filter.enabled("app::http::Error"));
+        assert!(filter.enabled("std::num::ParseIntError"));
+
+        let filter = ErrorFilter::parse("off,app=on");
+        assert!(!filter.enabled("std::io::Error"));
+        assert!(filter.enabled("app::Error"));
+
+        let filter = ErrorFilter::parse("app=maybe,std::io::Error=off");
+        assert!(filter.enabled("app::Error"));
+        assert!(!filter.enabled("std::io::Error"));
+    }
+}
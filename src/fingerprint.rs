@@ -0,0 +1,96 @@
+//! Grouping errors by fingerprints derived from the errors themselves.
+//!
+//! Honeybadger groups notices with the same fingerprint together.
+//! The fingerprint of a notice is decided in this order:
+//!
+//! 1. The one given explicitly by [`notify_with_fingerprint`][notify_with_fingerprint]
+//!    or [`notify_std_error_with_fingerprint`][notify_std_error_with_fingerprint].
+//! 2. The one returned by [`Fingerprint::fingerprint`][Fingerprint::fingerprint],
+//!    if the type of the error is [registered][register].
+//! 3. Otherwise, Honeybadger's default grouping (by class and location).
+//!
+//! [notify_with_fingerprint]: ../fn.notify_with_fingerprint.html
+//! [notify_std_error_with_fingerprint]: ../fn.notify_std_error_with_fingerprint.html
+//! [Fingerprint::fingerprint]: trait.Fingerprint.html#tymethod.fingerprint
+//! [register]: fn.register.html
+//!
+//! ## Example
+//!
+//! ```
+//! use std::fmt;
+//! use mightybadger::fingerprint::{self, Fingerprint};
+//!
+//! #[derive(Debug)]
+//! struct TimeoutError {
+//!     endpoint: String,
+//! }
+//!
+//! impl fmt::Display for TimeoutError {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         write!(f, "request to {} timed out", self.endpoint)
+//!     }
+//! }
+//!
+//! impl std::error::Error for TimeoutError {}
+//!
+//! impl Fingerprint for TimeoutError {
+//!     fn fingerprint(&self) -> Option<String> {
+//!         Some(format!("timeout:{}", self.endpoint))
+//!     }
+//! }
+//!
+//! fingerprint::register_std_error::<TimeoutError>();
+//! ```
+
+use failure::Fail;
+use lazy_static::lazy_static;
+
+use crate::registry::Registry;
+use crate::FailOrError;
+
+/// Errors which know how they should be grouped.
+///
+/// Implementations take effect once the type is registered with
+/// [`register`][register] or [`register_std_error`][register_std_error].
+///
+/// [register]: fn.register.html
+/// [register_std_error]: fn.register_std_error.html
+pub trait Fingerprint {
+    /// Returns the fingerprint, or `None` to fall back to the default grouping.
+    fn fingerprint(&self) -> Option<String>;
+}
+
+type Extractor = Box<dyn Fn(FailOrError<'_>) -> Option<String> + Send + Sync>;
+
+lazy_static! {
+    static ref EXTRACTORS: Registry<Extractor> = Registry::new();
+}
+
+/// Uses [`Fingerprint`][Fingerprint] of `E` for errors passed to `notify`.
+///
+/// [Fingerprint]: trait.Fingerprint.html
+pub fn register<E: Fail + Fingerprint>() {
+    EXTRACTORS.push(Box::new(|error| match error {
+        FailOrError::Fail(error) => error.downcast_ref::<E>()?.fingerprint(),
+        FailOrError::StdError(_) => None,
+    }));
+}
+
+/// Uses [`Fingerprint`][Fingerprint] of `E` for errors passed to either
+/// `notify` or `notify_std_error`.
+///
+/// [Fingerprint]: trait.Fingerprint.html
+pub fn register_std_error<E>()
+where
+    E: std::error::Error + Fingerprint + Send + Sync + 'static,
+{
+    EXTRACTORS.push(Box::new(|error| match error {
+        FailOrError::Fail(error) => error.downcast_ref::<E>()?.fingerprint(),
+        FailOrError::StdError(error) => error.downcast_ref::<E>()?.fingerprint(),
+    }));
+}
+
+/// Returns the fingerprint from the first registered type matching the error.
+pub(crate) fn fingerprint(error: FailOrError<'_>) -> Option<String> {
+    EXTRACTORS.find_map(|extractor| extractor(error))
+}
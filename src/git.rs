@@ -0,0 +1,110 @@
+//! Detection of the git revision and branch of the project.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The checked-out commit of a git repository.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct GitHead {
+    /// The commit hash.
+    pub revision: Option<String>,
+    /// The branch name, or `None` if `HEAD` is detached.
+    pub branch: Option<String>,
+}
+
+/// Reads `HEAD` of the git repository containing `root`.
+///
+/// Returns `None` if `root` is not in a git checkout.
+pub(crate) fn detect_revision(root: &Path) -> Option<GitHead> {
+    let git_dir = find_git_dir(root)?;
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    if let Some(reference) = head.strip_prefix("ref: ") {
+        Some(GitHead {
+            revision: resolve_ref(&git_dir, reference),
+            branch: Some(
+                reference
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(reference)
+                    .to_string(),
+            ),
+        })
+    } else {
+        Some(GitHead {
+            revision: Some(head.to_string()).filter(|head| !head.is_empty()),
+            branch: None,
+        })
+    }
+}
+
+/// Finds the `.git` directory of `root` or its ancestors.
+fn find_git_dir(root: &Path) -> Option<PathBuf> {
+    for dir in root.ancestors() {
+        let dot_git = dir.join(".git");
+        if dot_git.is_dir() {
+            return Some(dot_git);
+        } else if dot_git.is_file() {
+            // Worktrees and submodules have a `gitdir: <path>` file instead.
+            let content = fs::read_to_string(&dot_git).ok()?;
+            let git_dir = content.trim().strip_prefix("gitdir: ")?;
+            return Some(dir.join(git_dir));
+        }
+    }
+    None
+}
+
+/// Resolves a reference like `refs/heads/main` to a commit hash.
+fn resolve_ref(git_dir: &Path, reference: &str) -> Option<String> {
+    if let Ok(revision) = fs::read_to_string(git_dir.join(reference)) {
+        return Some(revision.trim().to_string());
+    }
+    // Worktrees keep the shared refs in the main repository.
+    let common_dir = fs::read_to_string(git_dir.join("commondir"))
+        .map(|common_dir| git_dir.join(common_dir.trim()))
+        .unwrap_or_else(|_| git_dir.to_path_buf());
+    if let Ok(revision) = fs::read_to_string(common_dir.join(reference)) {
+        return Some(revision.trim().to_string());
+    }
+    let packed_refs = fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed_refs.lines().find_map(|line| {
+        let (revision, name) = line.split_once(' ')?;
+        if name == reference {
+            Some(revision.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_detect_revision() {
+        let root = env::temp_dir().join(format!("mightybadger-git-{}", std::process::id()));
+        let git_dir = root.join(".git");
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature/x\n").unwrap();
+        fs::write(
+            git_dir.join("packed-refs"),
+            "# pack-refs with: peeled\n0123abcd refs/heads/feature/x\n",
+        )
+        .unwrap();
+        let head = detect_revision(&root.join("src")).unwrap();
+        assert_eq!(head.revision.as_deref(), Some("0123abcd"));
+        assert_eq!(head.branch.as_deref(), Some("feature/x"));
+
+        fs::write(git_dir.join("HEAD"), "4567cdef\n").unwrap();
+        let head = detect_revision(&root).unwrap();
+        assert_eq!(head.revision.as_deref(), Some("4567cdef"));
+        assert_eq!(head.branch, None);
+
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(detect_revision(&root), None);
+    }
+}
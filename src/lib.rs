@@ -1,68 +1,639 @@
 //! Honeybadger notifier for Rust.
 
+// `failure_derive` expands to impls nested in anonymous consts.
+#![allow(non_local_definitions)]
+
+/// Logs at the debug level if the `log` feature is enabled.
+#[cfg(feature = "log")]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { log::debug!($($arg)*) };
+}
+// Type-checks the arguments without evaluating them.
+#[cfg(not(feature = "log"))]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {{
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    }};
+}
+
+/// Logs at the trace level if the `log` feature is enabled.
+#[cfg(feature = "log")]
+macro_rules! log_trace {
+    ($($arg:tt)*) => { log::trace!($($arg)*) };
+}
+#[cfg(not(feature = "log"))]
+macro_rules! log_trace {
+    ($($arg:tt)*) => {{
+        if false {
+            let _ = format_args!($($arg)*);
+        }
+    }};
+}
+
+/// Runs a block in a named span for error attribution.
+///
+/// Within the block, `component` (and `action`, if given) of the context
+/// are set, and notices are tagged with the span name. The overrides are
+/// removed when the block exits, even by a panic.
+/// This is a shorthand of [`context::with_span`][with_span].
+///
+/// The block is run in a closure: `return` and `?` in it exit the block,
+/// whose value is the value of the macro.
+///
+/// [with_span]: context/fn.with_span.html
+///
+/// ## Example
+///
+/// ```
+/// let total = mightybadger::with_span!(name: "billing", action: "charge", {
+///     // Errors reported here are tagged with `billing`.
+///     40 + 2
+/// });
+/// assert_eq!(total, 42);
+/// ```
+#[macro_export]
+macro_rules! with_span {
+    (name: $name:expr, action: $action:expr, $body:block) => {
+        $crate::context::with_span($name, $action, || $body)
+    };
+    (name: $name:expr, $body:block) => {
+        $crate::context::with_span($name, "", || $body)
+    };
+}
+
+#[cfg(feature = "aws-secrets")]
+mod aws_secrets;
+#[cfg(feature = "azure-keyvault")]
+mod azure_keyvault;
 mod btparse;
+pub mod client;
 pub mod config;
 pub mod context;
+#[cfg(feature = "log-filter")]
+mod error_filter;
+pub mod fingerprint;
+mod git;
+pub mod metadata;
 pub mod payload;
-mod stats;
+mod rate_limit;
+mod registry;
+mod replay;
+#[cfg(feature = "reqwest-backend")]
+mod reqwest_client;
+#[cfg(all(unix, feature = "signal"))]
+mod signal;
+pub mod stats;
+mod subscribe;
+#[cfg(any(test, feature = "test-server"))]
+pub mod testing;
+#[cfg(feature = "tokio")]
+mod tokio_metrics;
+#[cfg(unix)]
+mod unix_socket;
+#[cfg(feature = "validate")]
+mod validate;
+#[cfg(feature = "vault")]
+mod vault;
+mod worker;
 
 use crate::payload::*;
+use crate::registry::Registry;
 use crate::HoneybadgerError::*;
-use attohttpc::header::{ACCEPT, CONTENT_TYPE, USER_AGENT};
+use attohttpc::header::{ACCEPT, CONTENT_TYPE, RETRY_AFTER, USER_AGENT};
 use attohttpc::StatusCode;
 use failure::{Backtrace, Fail};
+use lazy_static::lazy_static;
 use rand::RngCore;
 use serde_derive::Deserialize;
+use std::any::Any;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::panic::{set_hook, take_hook, PanicInfo};
+use std::future::Future;
+use std::io::{self, Read};
+#[allow(deprecated)]
+use std::panic::{
+    catch_unwind, set_hook, take_hook, AssertUnwindSafe, Location, PanicInfo, UnwindSafe,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use uuid::Uuid;
 
+#[cfg(feature = "aws-secrets")]
+pub use crate::aws_secrets::{configure_from_secrets_manager, SecretsManagerError};
+#[cfg(feature = "azure-keyvault")]
+pub use crate::azure_keyvault::{configure_from_azure_keyvault, AzureKeyVaultError};
+pub use crate::btparse::{parse_verbose as parse_backtrace_verbose, BacktraceLine};
+pub use crate::client::HttpClient;
 pub use crate::config::configure;
 pub use crate::config::configure_from_env;
+pub use crate::config::configure_from_env_with_validation;
+pub use crate::config::ConfigValidationError;
+pub use crate::config::ConfigWarning;
+pub use crate::fingerprint::Fingerprint;
+pub use crate::metadata::HoneybadgerMetadata;
 pub use crate::payload::Payload;
+pub use crate::replay::replay_notices;
+#[cfg(feature = "reqwest-backend")]
+pub use crate::reqwest_client::ReqwestHttpClient;
+#[cfg(all(unix, feature = "signal"))]
+pub use crate::signal::install_sighup_reload;
+pub use crate::stats::StatsBackend;
+pub use crate::subscribe::subscribe;
+#[cfg(feature = "vault")]
+pub use crate::vault::{configure_from_vault, VaultError};
+pub use crate::worker::{flush, flush_on_exit, graceful_shutdown, FlushGuard, ShutdownError};
+#[cfg(feature = "macros")]
+pub use mightybadger_macros::{with_error_context, HoneybadgerMetadata};
 
-#[derive(Debug, Fail)]
-#[fail(display = "{}", message)]
+/// A boxed `std::error::Error`, made reportable by [`notify`][notify].
+///
+/// `Box<dyn std::error::Error + Send + Sync>` doesn't implement `Fail`,
+/// so wrap it in this type, or use [`notify_boxed`][notify_boxed].
+/// The notice is made from the inner error: its class, message, and causes.
+///
+/// [notify]: fn.notify.html
+/// [notify_boxed]: fn.notify_boxed.html
+#[derive(Debug)]
+pub struct Error(pub Box<dyn std::error::Error + Send + Sync>);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Fail for Error {
+    fn backtrace(&self) -> Option<&Backtrace> {
+        None
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+    fn from(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        Error(error)
+    }
+}
+
+#[derive(Debug)]
 pub struct Panic {
     message: String,
-    backtrace: Backtrace,
+    /// Captured regardless of `RUST_BACKTRACE`, so that panics carry frames
+    /// unless disabled by [`disable_backtrace`][disable_backtrace].
+    ///
+    /// [disable_backtrace]: fn.disable_backtrace.html
+    backtrace: std::backtrace::Backtrace,
 }
 
+impl fmt::Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Fail for Panic {}
+
 impl Panic {
+    #[allow(deprecated)]
     fn new(panic_info: &PanicInfo<'_>) -> Self {
-        let message = if let Some(message) = panic_info.payload().downcast_ref::<String>() {
+        Self::from_payload(panic_info.payload())
+    }
+
+    /// Creates the error from the payload of a caught panic.
+    pub(crate) fn from_payload(payload: &(dyn Any + Send)) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<String>() {
             message.to_string()
-        } else if let Some(&message) = panic_info.payload().downcast_ref::<&'static str>() {
+        } else if let Some(&message) = payload.downcast_ref::<&'static str>() {
             message.to_string()
         } else {
-            "Box<Any>".to_string()
+            PANIC_EXTRACTORS
+                .find_map(|extractor| extractor(payload))
+                .unwrap_or_else(|| "Box<Any>".to_string())
         };
-        let backtrace = Backtrace::new();
-        Panic { message, backtrace }
+        Panic {
+            message,
+            backtrace: capture_panic_backtrace(),
+        }
+    }
+}
+
+/// Captures the backtrace of a panic, unless `RUST_FAILURE_BACKTRACE=0`.
+fn capture_panic_backtrace() -> std::backtrace::Backtrace {
+    if matches!(std::env::var_os("RUST_FAILURE_BACKTRACE"), Some(value) if value == "0") {
+        std::backtrace::Backtrace::disabled()
+    } else {
+        std::backtrace::Backtrace::force_capture()
+    }
+}
+
+/// Describes a panic payload, returning `None` if it is of another type.
+type PanicExtractor = fn(&dyn Any) -> Option<String>;
+
+lazy_static! {
+    static ref PANIC_EXTRACTORS: Registry<PanicExtractor> = Registry::new();
+}
+
+/// Registers a function that describes panic payloads of custom types.
+///
+/// Payloads which are not strings (e.g. from `std::panic::panic_any`) are
+/// passed to the registered extractors in order of registration, and the
+/// first message returned is used. Otherwise the message is `"Box<Any>"`.
+///
+/// ## Example
+///
+/// ```
+/// struct ExitCode(i32);
+///
+/// mightybadger::register_panic_extractor(|payload| {
+///     let code = payload.downcast_ref::<ExitCode>()?;
+///     Some(format!("exited with code {}", code.0))
+/// });
+/// ```
+pub fn register_panic_extractor(extractor: fn(&dyn Any) -> Option<String>) {
+    PANIC_EXTRACTORS.push(extractor);
+}
+
+/// Edits assembled notices; see [`set_notice_preprocessor`][set_notice_preprocessor].
+///
+/// [set_notice_preprocessor]: fn.set_notice_preprocessor.html
+pub type NoticePreprocessor = Box<dyn Fn(&mut Payload) + Send + Sync>;
+
+/// Shared, so that it is called without holding the lock.
+type SharedPreprocessor = Arc<dyn Fn(&mut Payload) + Send + Sync>;
+
+lazy_static! {
+    static ref NOTICE_PREPROCESSOR: RwLock<Option<SharedPreprocessor>> = RwLock::new(None);
+}
+
+/// Sets a function which edits every notice before it is sent, replacing
+/// the previous one.
+///
+/// It is called with the assembled payload, after the request data has been
+/// filtered, so it sees what would be sent otherwise. Any edit it makes is
+/// sent as is, e.g. adding tags or removing fields; note that secrets it
+/// adds are not filtered.
+///
+/// ## Example
+///
+/// ```
+/// mightybadger::set_notice_preprocessor(Box::new(|payload| {
+///     payload.error.tags.push("worker".to_string());
+///     if let Some(ref mut request) = payload.request {
+///         request.session.clear();
+///     }
+/// }));
+/// ```
+pub fn set_notice_preprocessor(preprocessor: NoticePreprocessor) {
+    *NOTICE_PREPROCESSOR
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = Some(Arc::from(preprocessor));
+}
+
+/// Reports a synthetic [`Panic`][Panic] with `message`, without panicking.
+///
+/// The notice looks like one from the panic hook, so it can be used to check
+/// the setup from application code, e.g. from a health check endpoint,
+/// without crashing the thread.
+///
+/// [Panic]: struct.Panic.html
+///
+/// ## Example
+///
+/// ```no_run
+/// mightybadger::setup();
+/// mightybadger::simulate_panic("testing the panic hook");
+/// ```
+pub fn simulate_panic(message: impl Into<String>) {
+    let error = Panic {
+        message: message.into(),
+        backtrace: capture_panic_backtrace(),
+    };
+    notify(&error);
+}
+
+/// Synthetic error sent by [`trigger_test_notice`][trigger_test_notice].
+///
+/// [trigger_test_notice]: fn.trigger_test_notice.html
+#[derive(Debug)]
+struct TestNotice;
+
+impl fmt::Display for TestNotice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "Testing mightybadger via trigger_test_notice(). If you can see this, it works.",
+        )
+    }
+}
+
+impl Fail for TestNotice {
+    fn name(&self) -> Option<&str> {
+        Some("TestNotice")
     }
 }
 
+/// The notice sent by [`sample_success`][sample_success].
+///
+/// [sample_success]: fn.sample_success.html
+#[derive(Debug)]
+struct SuccessSample(u16);
+
+impl fmt::Display for SuccessSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sampled successful request (status {})", self.0)
+    }
+}
+
+impl Fail for SuccessSample {
+    fn name(&self) -> Option<&str> {
+        Some("SuccessSample")
+    }
+}
+
+/// At most 10 success samples are sent per minute.
+const SUCCESS_SAMPLE_LIMIT: (u32, std::time::Duration) = (10, std::time::Duration::from_secs(60));
+
 /// Error occurred during Honeybadger reporting.
 #[derive(Debug, Fail)]
 pub enum HoneybadgerError {
     #[fail(display = "Configured not to send reports")]
     NoReportData(Backtrace),
+    #[fail(display = "Configured not to report {} requests", _0)]
+    IgnoredRequestMethod(String, Backtrace),
+    #[fail(display = "Configured not to report requests to {}", _0)]
+    IgnoredRequestPath(String, Backtrace),
+    #[fail(display = "Configured not to report errors of class {}", _0)]
+    IgnoredErrorClass(String, Backtrace),
+    #[fail(display = "Rate limited notices of class {} locally", _0)]
+    ClassRateLimited(String, Backtrace),
+    #[fail(display = "Configured not to report panics with message {:?}", _0)]
+    IgnoredPanicMessage(String, Backtrace),
+    #[fail(display = "Skipped the panic by panic_sample_rate")]
+    PanicSampledOut(Backtrace),
+    #[fail(display = "Reached max_notices_per_process ({})", _0)]
+    NoticeLimitReached(usize, Backtrace),
+    #[fail(display = "Dropped the notice as too many notices are being sent")]
+    TooManyConcurrentSends(Backtrace),
     #[fail(display = "API key is missing")]
     NoApiKey(Backtrace),
     #[fail(display = "could not assemble payload")]
     CouldNotAssemblePayload(#[cause] serde_json::Error, Backtrace),
     #[fail(display = "HTTP request failed")]
     HttpRequestFailed(#[cause] attohttpc::Error, Backtrace),
+    #[cfg(feature = "reqwest-backend")]
+    #[fail(display = "HTTP request failed")]
+    ReqwestFailed(#[cause] reqwest::Error, Backtrace),
+    /// The status code (`429` or `503`) and the `Retry-After` header of the response.
     #[fail(display = "project is sending too many errors")]
-    TooManyRequests(Backtrace),
+    TooManyRequests(StatusCode, Option<std::time::Duration>, Backtrace),
     #[fail(display = "payment is required")]
     PaymentRequired(Backtrace),
+    /// The last four characters of the API key used for the notice, if it is
+    /// long enough to show a part of it.
     #[fail(display = "API key is invalid")]
-    Forbidden(Backtrace),
-    #[fail(display = "unknown response from server")]
-    UnknownResponse(Backtrace),
+    Forbidden(Option<String>, Backtrace),
+    /// The status code and the beginning of the body of the response.
+    #[fail(display = "unknown response from server: {} {:?}", _0, _1)]
+    UnknownResponse(StatusCode, String, Backtrace),
     #[fail(display = "failed to decode response body")]
     ResponseDecodeFailed(#[cause] attohttpc::Error, Backtrace),
+    #[fail(display = "could not read or write the notice file")]
+    NoticeFileFailed(#[cause] io::Error, Backtrace),
+}
+
+impl HoneybadgerError {
+    /// Returns the underlying error, e.g. of the HTTP client.
+    ///
+    /// This is the `#[cause]` of the variant as a `std::error::Error`.
+    pub fn std_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CouldNotAssemblePayload(e, _) => Some(e),
+            HttpRequestFailed(e, _) | ResponseDecodeFailed(e, _) => Some(e),
+            #[cfg(feature = "reqwest-backend")]
+            ReqwestFailed(e, _) => Some(e),
+            NoticeFileFailed(e, _) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Returns details of a failed request to the server as JSON, e.g. for
+    /// alerting on reporting failures, or `None` for the other errors,
+    /// such as notices dropped by the configuration.
+    ///
+    /// The object has a `kind` such as `"http_request_failed"` and, where
+    /// known, the `status` code of the response and the `message` of the
+    /// underlying error. For `TooManyRequests`, `retry_after` has the seconds
+    /// to wait from the `Retry-After` header, if any. For `Forbidden`,
+    /// `api_key_hint` has the last four characters of the API key used for
+    /// the notice.
+    pub fn context_json(&self) -> Option<serde_json::Value> {
+        let json = match self {
+            HttpRequestFailed(e, _) => {
+                let status = match e.kind() {
+                    attohttpc::ErrorKind::StatusCode(status)
+                    | attohttpc::ErrorKind::ConnectError {
+                        status_code: status,
+                        ..
+                    } => Some(status.as_u16()),
+                    _ => None,
+                };
+                serde_json::json!({
+                    "kind": "http_request_failed",
+                    "message": e.to_string(),
+                    "status": status,
+                })
+            }
+            #[cfg(feature = "reqwest-backend")]
+            ReqwestFailed(e, _) => serde_json::json!({
+                "kind": "http_request_failed",
+                "message": e.to_string(),
+                "status": e.status().map(|status| status.as_u16()),
+            }),
+            TooManyRequests(status, retry_after, _) => serde_json::json!({
+                "kind": "too_many_requests",
+                "status": status.as_u16(),
+                "retry_after": retry_after.map(|retry_after| retry_after.as_secs()),
+            }),
+            PaymentRequired(_) => serde_json::json!({
+                "kind": "payment_required",
+                "status": 402,
+            }),
+            Forbidden(api_key_hint, _) => serde_json::json!({
+                "kind": "forbidden",
+                "status": 403,
+                "api_key_hint": api_key_hint,
+            }),
+            UnknownResponse(status, body, _) => serde_json::json!({
+                "kind": "unknown_response",
+                "status": status.as_u16(),
+                "body": body,
+            }),
+            ResponseDecodeFailed(e, _) => serde_json::json!({
+                "kind": "response_decode_failed",
+                "message": e.to_string(),
+            }),
+            _ => return None,
+        };
+        Some(json)
+    }
+}
+
+/// Returns the last four characters of the API key, or `None` if the key
+/// is too short to show a part of it.
+fn api_key_hint(api_key: &str) -> Option<String> {
+    let len = api_key.chars().count();
+    if len < 8 {
+        return None;
+    }
+    Some(format!(
+        "...{}",
+        api_key.chars().skip(len - 4).collect::<String>()
+    ))
+}
+
+/// [`HoneybadgerError`][HoneybadgerError] as a `std::error::Error`,
+/// for `Box<dyn Error>` or `anyhow` based code.
+///
+/// `HoneybadgerError` cannot implement `std::error::Error` itself, as
+/// `failure` implements `Fail` for every `std::error::Error`. Unlike
+/// `failure::Compat`, `source` returns the underlying error.
+///
+/// `?` converts `HoneybadgerError` into `Box<dyn Error>` through this type:
+///
+/// ```no_run
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let id = mightybadger::trigger_test_notice()?;
+///     println!("sent {}", id);
+///     Ok(())
+/// }
+/// ```
+///
+/// [HoneybadgerError]: enum.HoneybadgerError.html
+#[derive(Debug)]
+pub struct HoneybadgerStdError(pub HoneybadgerError);
+
+impl fmt::Display for HoneybadgerStdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for HoneybadgerStdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.std_source()
+    }
+}
+
+impl From<HoneybadgerError> for HoneybadgerStdError {
+    fn from(e: HoneybadgerError) -> Self {
+        HoneybadgerStdError(e)
+    }
+}
+
+impl From<HoneybadgerError> for Box<dyn std::error::Error> {
+    fn from(e: HoneybadgerError) -> Self {
+        Box::new(HoneybadgerStdError(e))
+    }
+}
+
+impl From<HoneybadgerError> for Box<dyn std::error::Error + Send + Sync> {
+    fn from(e: HoneybadgerError) -> Self {
+        Box::new(HoneybadgerStdError(e))
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<HoneybadgerError> for anyhow::Error {
+    fn from(e: HoneybadgerError) -> Self {
+        anyhow::Error::new(HoneybadgerStdError(e))
+    }
+}
+
+/// Outcome of the last attempt to send a notice.
+///
+/// Returned by [`last_report_status`][last_report_status].
+///
+/// [last_report_status]: fn.last_report_status.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportOutcome {
+    /// The notice was accepted.
+    Success,
+    /// The server asked to slow down (`429 Too Many Requests` or `503 Service Unavailable`).
+    RateLimited,
+    /// The server rejected the notice (invalid API key or payment required).
+    Rejected,
+    /// The notice could not be delivered for another reason.
+    Failed,
+}
+
+/// 0 means no report has been attempted yet.
+static LAST_REPORT_STATUS: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// Outcomes of the last attempt for each destination.
+    static ref LAST_DESTINATION_STATUSES: Mutex<Vec<ReportOutcome>> = Mutex::new(Vec::new());
+}
+
+impl ReportOutcome {
+    fn of(result: &Result<HoneybadgerResponse, HoneybadgerError>) -> Self {
+        match result {
+            Ok(_) => ReportOutcome::Success,
+            Err(TooManyRequests(..)) => ReportOutcome::RateLimited,
+            Err(PaymentRequired(_)) | Err(Forbidden(..)) => ReportOutcome::Rejected,
+            Err(_) => ReportOutcome::Failed,
+        }
+    }
+}
+
+fn record_report_status(
+    result: &Result<HoneybadgerResponse, HoneybadgerError>,
+    destination_results: &[Result<HoneybadgerResponse, HoneybadgerError>],
+) {
+    let status = match ReportOutcome::of(result) {
+        ReportOutcome::Success => 1,
+        ReportOutcome::RateLimited => 2,
+        ReportOutcome::Rejected => 3,
+        ReportOutcome::Failed => 4,
+    };
+    LAST_REPORT_STATUS.store(status, Ordering::Relaxed);
+    if let Ok(mut statuses) = LAST_DESTINATION_STATUSES.lock() {
+        *statuses = destination_results.iter().map(ReportOutcome::of).collect();
+    }
+}
+
+/// Returns the outcome of the last attempt to send a notice from this process,
+/// or `None` if no notice has been sent yet.
+///
+/// Notices skipped before sending (e.g. because `report_data` is off) don't count.
+///
+/// This is meant for circuit breakers, e.g. to stop notifying for a while after
+/// repeated `RateLimited` outcomes. It is advisory: with concurrent notices,
+/// the status may already be outdated when it is read.
+pub fn last_report_status() -> Option<ReportOutcome> {
+    match LAST_REPORT_STATUS.load(Ordering::Relaxed) {
+        1 => Some(ReportOutcome::Success),
+        2 => Some(ReportOutcome::RateLimited),
+        3 => Some(ReportOutcome::Rejected),
+        4 => Some(ReportOutcome::Failed),
+        _ => None,
+    }
+}
+
+/// Returns the outcome of the last attempt to send a notice for each destination:
+/// the primary one first, then those in
+/// [`additional_destinations`][additional_destinations] in order.
+///
+/// [`last_report_status`][last_report_status] counts a notice as delivered if
+/// any destination accepts it; this tells which of them failed.
+/// The list is empty if no notice has been sent yet.
+///
+/// [additional_destinations]: config/struct.Config.html#structfield.additional_destinations
+/// [last_report_status]: fn.last_report_status.html
+pub fn last_destination_statuses() -> Vec<ReportOutcome> {
+    LAST_DESTINATION_STATUSES
+        .lock()
+        .map(|statuses| statuses.clone())
+        .unwrap_or_default()
 }
 
 #[derive(Deserialize)]
@@ -74,77 +645,935 @@ fn report(
     payload: &Payload,
     config: &config::Config,
 ) -> Result<HoneybadgerResponse, HoneybadgerError> {
-    let api_key = payload.api_key.clone();
-    let client_version = format!(
+    let token = payload
+        .error
+        .token
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| "nil".to_string());
+    if let Some(ref http_client) = config.http_client {
+        log_debug!("sending notice {} with {:?}", token, http_client);
+        let id = http_client.report(payload, config)?;
+        return Ok(HoneybadgerResponse { id });
+    }
+    let body =
+        serde_json::to_vec(payload).map_err(|e| CouldNotAssemblePayload(e, Backtrace::new()))?;
+    let retries = config.connection.retries.unwrap_or(0);
+    let retry_status_codes = config
+        .connection
+        .retry_status_codes
+        .as_deref()
+        .unwrap_or(&DEFAULT_RETRY_STATUS_CODES);
+    let mut attempt = 0;
+    let resp = loop {
+        let resp = post_notice(&body, &payload.api_key, config, &token)?;
+        if attempt < retries && retry_status_codes.contains(&resp.status.as_u16()) {
+            let delay = retry_delay(attempt, &resp);
+            if delay <= MAX_RETRY_AFTER {
+                log_debug!("notice {}: retrying in {:?}", token, delay);
+                std::thread::sleep(delay);
+                attempt += 1;
+                continue;
+            }
+        }
+        break resp;
+    };
+    log_trace!("notice {}: {}", token, String::from_utf8_lossy(&resp.body));
+    check_status(resp.status, resp.retry_after, &payload.api_key, &resp.body)?;
+    check_response_size(&resp.body, config)?;
+    decode_response(&resp.body, payload, config)
+}
+
+/// The default of `connection.retry_status_codes`.
+const DEFAULT_RETRY_STATUS_CODES: [u16; 2] = [429, 503];
+
+/// The longest `Retry-After` waited for; the notice is not retried if the
+/// server asks to wait longer.
+const MAX_RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The time to wait before the retry after `attempt` retries.
+///
+/// The `Retry-After` header of `429` and `503` responses takes precedence.
+fn retry_delay(attempt: u32, resp: &NoticeResponse) -> std::time::Duration {
+    match resp.status {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+            if let Some(retry_after) = resp.retry_after {
+                return retry_after;
+            }
+        }
+        _ => {}
+    }
+    std::time::Duration::from_millis(200u64.saturating_mul(1 << attempt.min(10)))
+}
+
+/// Parses a `Retry-After` header, which is either seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    // A date in the past means no wait.
+    Some(
+        date.signed_duration_since(chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// A response of the server to a notice, from the built-in transport.
+struct NoticeResponse {
+    status: StatusCode,
+    /// The `Retry-After` header, if it is valid.
+    retry_after: Option<std::time::Duration>,
+    /// Read up to one byte over `connection.max_response_body_bytes`.
+    body: Vec<u8>,
+}
+
+/// Sends the serialized notice with the built-in transport.
+fn post_notice(
+    body: &[u8],
+    api_key: &str,
+    config: &config::Config,
+    token: &str,
+) -> Result<NoticeResponse, HoneybadgerError> {
+    let client_version = client_version();
+    #[cfg(unix)]
+    {
+        if let Some(ref socket_path) = config.connection.socket_path {
+            let host = notice_host(config);
+            log_debug!(
+                "sending notice {} to {} ({} bytes)",
+                token,
+                socket_path.display(),
+                body.len()
+            );
+            let headers = [
+                ("X-API-Key", api_key),
+                ("Content-Type", "application/json"),
+                ("Accept", "application/json"),
+                ("User-Agent", client_version.as_str()),
+            ];
+            let (status, retry_after, body) = unix_socket::post(
+                socket_path,
+                host,
+                "/v1/notices",
+                &headers,
+                body,
+                config.connection.timeout,
+                max_response_body_bytes(config),
+            )
+            .map_err(|e| HttpRequestFailed(e.into(), Backtrace::new()))?;
+            log_debug!("notice {}: HTTP {}", token, status);
+            return Ok(NoticeResponse {
+                status,
+                retry_after: retry_after.as_deref().and_then(parse_retry_after),
+                body,
+            });
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if config.connection.socket_path.is_some() {
+            let e = io::Error::new(
+                io::ErrorKind::Unsupported,
+                "connection.socket_path is only supported on Unix",
+            );
+            return Err(HttpRequestFailed(e.into(), Backtrace::new()));
+        }
+    }
+    let url = notice_url(config);
+    log_debug!("sending notice {} to {} ({} bytes)", token, url, body.len());
+    let builder = attohttpc::post(&url);
+    let builder = match config.connection.timeout {
+        Some(timeout) => builder.timeout(timeout),
+        None => builder,
+    };
+    let resp = builder
+        .bytes(body)
+        .header("X-API-Key", api_key)
+        .header(CONTENT_TYPE, "application/json")
+        .header(ACCEPT, "application/json")
+        .header(USER_AGENT, client_version)
+        .send();
+    let resp = resp.map_err(|e| HttpRequestFailed(e, Backtrace::new()))?;
+    log_debug!("notice {}: HTTP {}", token, resp.status());
+    let (status, headers, reader) = resp.split();
+    let retry_after = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    let mut body = Vec::new();
+    // Read one more byte to tell whether the limit is exceeded.
+    let limit = (max_response_body_bytes(config) as u64).saturating_add(1);
+    reader
+        .take(limit)
+        .read_to_end(&mut body)
+        .map_err(|e| ResponseDecodeFailed(e.into(), Backtrace::new()))?;
+    Ok(NoticeResponse {
+        status,
+        retry_after,
+        body,
+    })
+}
+
+/// The `User-Agent` of notices.
+fn client_version() -> String {
+    format!(
         "HB-Rust {}; {}; {}",
         env!("CARGO_PKG_VERSION"),
         rustc_version_runtime::version(),
         env!("HONEYBADGER_CLIENT_ARCH"),
-    );
+    )
+}
+
+fn notice_host(config: &config::Config) -> &str {
+    config
+        .connection
+        .host
+        .as_deref()
+        .unwrap_or("api.honeybadger.io")
+}
+
+fn notice_url(config: &config::Config) -> String {
     let scheme = if config.connection.secure.unwrap_or(true) {
         "https"
     } else {
         "http"
     };
-    let host = config
-        .connection
-        .host
-        .as_ref()
-        .map(|x| x.as_str())
-        .unwrap_or("api.honeybadger.io");
     let port = config.connection.port.unwrap_or(443);
-    let url = format!("{}://{}:{}/v1/notices", scheme, host, port);
-    let resp = attohttpc::post(&url)
-        .json(payload)
-        .map_err(|e| {
-            if let attohttpc::ErrorKind::Json(_) = e.kind() {
-                if let attohttpc::ErrorKind::Json(e) = e.into_kind() {
-                    CouldNotAssemblePayload(e, Backtrace::new())
-                } else {
-                    unreachable!();
-                }
-            } else {
-                HttpRequestFailed(e, Backtrace::new())
-            }
-        })?
-        .header("X-API-Key", api_key)
-        .header(CONTENT_TYPE, "application/json")
-        .header(ACCEPT, "application/json")
-        .header(USER_AGENT, client_version)
-        .send();
-    let resp = resp.map_err(|e| HttpRequestFailed(e, Backtrace::new()))?;
-    match resp.status() {
+    format!("{}://{}:{}/v1/notices", scheme, notice_host(config), port)
+}
+
+fn max_response_body_bytes(config: &config::Config) -> usize {
+    config.connection.max_response_body_bytes.unwrap_or(4096)
+}
+
+fn check_response_size(body: &[u8], config: &config::Config) -> Result<(), HoneybadgerError> {
+    let max = max_response_body_bytes(config);
+    if body.len() > max {
+        let e = io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("response body exceeds {} bytes", max),
+        );
+        return Err(ResponseDecodeFailed(e.into(), Backtrace::new()));
+    }
+    Ok(())
+}
+
+/// The maximum number of characters of the body kept in `UnknownResponse`.
+const UNKNOWN_RESPONSE_BODY_CHARS: usize = 256;
+
+/// Checks the status of the response, whose body is `body`, to the notice
+/// sent with `api_key`.
+fn check_status(
+    status: StatusCode,
+    retry_after: Option<std::time::Duration>,
+    api_key: &str,
+    body: &[u8],
+) -> Result<(), HoneybadgerError> {
+    match status {
         StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
-            return Err(TooManyRequests(Backtrace::new()));
+            Err(TooManyRequests(status, retry_after, Backtrace::new()))
+        }
+        StatusCode::PAYMENT_REQUIRED => Err(PaymentRequired(Backtrace::new())),
+        StatusCode::FORBIDDEN => Err(Forbidden(api_key_hint(api_key), Backtrace::new())),
+        StatusCode::CREATED => Ok(()),
+        _ => {
+            let body = String::from_utf8_lossy(body)
+                .chars()
+                .take(UNKNOWN_RESPONSE_BODY_CHARS)
+                .collect();
+            Err(UnknownResponse(status, body, Backtrace::new()))
+        }
+    }
+}
+
+fn decode_response(
+    body: &[u8],
+    payload: &Payload,
+    config: &config::Config,
+) -> Result<HoneybadgerResponse, HoneybadgerError> {
+    if config.connection.strict_response.unwrap_or(false) {
+        return serde_json::from_slice(body)
+            .map_err(|e| ResponseDecodeFailed(e.into(), Backtrace::new()));
+    }
+    // Relays may accept the notice without echoing its id.
+    Ok(
+        serde_json::from_slice(body).unwrap_or_else(|_| HoneybadgerResponse {
+            id: payload.error.token.unwrap_or_else(Uuid::nil),
+        }),
+    )
+}
+
+#[allow(deprecated)]
+fn honeybadger_panic_hook(panic_info: &PanicInfo<'_>) {
+    let labels = context::boundary_labels();
+    let tags = labels
+        .iter()
+        .map(|label| label.as_str())
+        .collect::<Vec<_>>();
+    notify_panic(&Panic::new(panic_info), &tags);
+    context::mark_panic_reported();
+}
+
+/// Runs `f`, reporting a panic from it.
+///
+/// This is `std::panic::catch_unwind` which also reports the caught panic.
+/// The panic is returned as `Err`, not swallowed.
+/// If the panic hook is [installed][install_hook], the panic is reported
+/// once, from the hook. See also
+/// [`context::with_error_boundary`][with_error_boundary], which tags the notice.
+///
+/// [install_hook]: fn.install_hook.html
+/// [with_error_boundary]: context/fn.with_error_boundary.html
+///
+/// ## Tokio
+///
+/// Panics in tasks are reported by the panic hook when they happen,
+/// so the hook is all that is needed for `tokio::spawn`.
+/// Without the hook, wrap the closure passed to `spawn_blocking` with
+/// `capture_panics`, or pass the panic of a `JoinError` to
+/// [`rayon_panic_handler`][rayon_panic_handler]:
+///
+/// ```ignore
+/// if let Err(e) = handle.await {
+///     if e.is_panic() {
+///         mightybadger::rayon_panic_handler(e.into_panic());
+///     }
+/// }
+/// ```
+///
+/// [rayon_panic_handler]: fn.rayon_panic_handler.html
+pub fn capture_panics<T, F>(f: F) -> Result<T, Box<dyn Any + Send>>
+where
+    F: FnOnce() -> T + UnwindSafe,
+{
+    context::catch_and_report(None, f)
+}
+
+/// Reports a panic caught by a thread pool.
+///
+/// Install it with `rayon::ThreadPoolBuilder::panic_handler`, which
+/// otherwise aborts the process on panics in `spawn`ed jobs.
+/// The panic is not reported again if the panic hook has already done so.
+///
+/// ## Example
+///
+/// ```ignore
+/// let pool = rayon::ThreadPoolBuilder::new()
+///     .panic_handler(mightybadger::rayon_panic_handler)
+///     .build()?;
+/// ```
+pub fn rayon_panic_handler(payload: Box<dyn Any + Send>) {
+    if !context::take_panic_reported() {
+        notify_panic(&Panic::from_payload(&*payload), &[]);
+    }
+}
+
+/// Reports a panic, subject to `panic_sample_rate` and `panic_ignore_messages`.
+pub(crate) fn notify_panic(panic: &Panic, tags: &[&str]) {
+    let options = NoticeOptions {
+        tags,
+        origin: NoticeOrigin::Panic,
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(panic), &options)
+}
+
+pub fn notify(error: &dyn Fail) {
+    notify_either(FailOrError::Fail(error), &NoticeOptions::default())
+}
+
+pub fn notify_std_error(error: &(dyn std::error::Error + 'static)) {
+    notify_either(FailOrError::StdError(error), &NoticeOptions::default())
+}
+
+/// Reports a boxed `std::error::Error`.
+///
+/// Same as `notify(&mightybadger::Error(error))`; see [`Error`][Error].
+///
+/// [Error]: struct.Error.html
+///
+/// ## Example
+///
+/// ```no_run
+/// fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+///     let _port: u16 = "http".parse()?;
+///     Ok(())
+/// }
+///
+/// if let Err(error) = run() {
+///     mightybadger::notify_boxed(error);
+/// }
+/// ```
+pub fn notify_boxed(error: Box<dyn std::error::Error + Send + Sync>) {
+    notify(&Error(error))
+}
+
+/// Runs `f` and reports the error it returns, if any.
+///
+/// The result is returned unchanged, so that the caller can still handle it.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn load_config() -> Result<(), failure::Error> { Ok(()) }
+/// let result = mightybadger::notify_if_error(|| load_config().map_err(|e| e.compat()));
+/// ```
+pub fn notify_if_error<T, E, F>(f: F) -> Result<T, E>
+where
+    E: Fail,
+    F: FnOnce() -> Result<T, E>,
+{
+    let result = f();
+    if let Err(ref e) = result {
+        notify(e);
+    }
+    result
+}
+
+/// Same as [`notify_if_error`][notify_if_error], but for errors implementing
+/// `std::error::Error`.
+///
+/// [notify_if_error]: fn.notify_if_error.html
+///
+/// ## Example
+///
+/// ```no_run
+/// let port = mightybadger::notify_std_if_error(|| "8080".parse::<u16>());
+/// ```
+pub fn notify_std_if_error<T, E, F>(f: F) -> Result<T, E>
+where
+    E: std::error::Error + 'static,
+    F: FnOnce() -> Result<T, E>,
+{
+    let result = f();
+    if let Err(ref e) = result {
+        notify_std_error(e);
+    }
+    result
+}
+
+/// Wraps `fut` so that the error it resolves to, if any, is reported.
+///
+/// The async counterpart of [`notify_if_error`][notify_if_error]; the result
+/// is returned unchanged. It works with any `std::future::Future`,
+/// including the combinators of `futures` 0.3.
+///
+/// [notify_if_error]: fn.notify_if_error.html
+///
+/// ## Example
+///
+/// ```no_run
+/// # async fn fetch() -> Result<(), failure::Compat<failure::Error>> { Ok(()) }
+/// # async fn example() {
+/// let result = mightybadger::notify_future(fetch()).await;
+/// # }
+/// ```
+pub async fn notify_future<T, E, F>(fut: F) -> Result<T, E>
+where
+    E: Fail,
+    F: Future<Output = Result<T, E>>,
+{
+    let result = fut.await;
+    if let Err(ref e) = result {
+        notify(e);
+    }
+    result
+}
+
+/// Same as [`notify_future`][notify_future], but for errors implementing
+/// `std::error::Error`.
+///
+/// [notify_future]: fn.notify_future.html
+pub async fn notify_future_std_error<T, E, F>(fut: F) -> Result<T, E>
+where
+    E: std::error::Error + 'static,
+    F: Future<Output = Result<T, E>>,
+{
+    let result = fut.await;
+    if let Err(ref e) = result {
+        notify_std_error(e);
+    }
+    result
+}
+
+/// Reports an [`anyhow::Error`][anyhow::Error] with its chain of causes.
+///
+/// The backtrace captured by `anyhow` is used if there is one
+/// (see `RUST_LIB_BACKTRACE`); otherwise the current backtrace is used.
+///
+/// [anyhow::Error]: https://docs.rs/anyhow/1/anyhow/struct.Error.html
+#[cfg(feature = "anyhow")]
+pub fn notify_anyhow(error: &anyhow::Error) {
+    use std::backtrace::BacktraceStatus;
+
+    let backtrace = error.backtrace();
+    let options = NoticeOptions {
+        std_backtrace: Some(backtrace).filter(|bt| bt.status() == BacktraceStatus::Captured),
+        ..Default::default()
+    };
+    let error: &(dyn std::error::Error + 'static) = error.as_ref();
+    notify_either(FailOrError::StdError(error), &options)
+}
+
+/// Reports an [`eyre::Report`][eyre::Report] with its chain of causes.
+///
+/// The backtrace is taken at the call site, because handlers don't expose
+/// the backtrace they captured.
+///
+/// [eyre::Report]: https://docs.rs/eyre/0.6/eyre/struct.Report.html
+#[cfg(feature = "eyre")]
+pub fn notify_eyre(error: &eyre::Report) {
+    let error: &(dyn std::error::Error + 'static) = error.as_ref();
+    notify_either(FailOrError::StdError(error), &NoticeOptions::default())
+}
+
+/// Same as [`notify`][notify], but sends the notice with a different API key
+/// and connection settings.
+///
+/// `api_key`, if any, and the fields set in `connection` override the
+/// global API key and [`ConnectionConfig`][ConnectionConfig] for this
+/// notice only, e.g. to route a subsystem's errors to a separate project
+/// or endpoint. The global configuration is left untouched.
+///
+/// [notify]: fn.notify.html
+/// [ConnectionConfig]: config/struct.ConnectionConfig.html
+///
+/// ## Example
+///
+/// ```no_run
+/// use mightybadger::config::ConnectionConfig;
+///
+/// let connection = ConnectionConfig {
+///     host: Some("honeybadger.internal.example.com".to_string()),
+///     ..Default::default()
+/// };
+/// # let error = "x".parse::<i32>().unwrap_err();
+/// mightybadger::notify_std_error_to(Some("hbp_subsystem"), &connection, &error);
+/// ```
+pub fn notify_to(api_key: Option<&str>, connection: &config::ConnectionConfig, error: &dyn Fail) {
+    let options = NoticeOptions {
+        connection: Some(connection),
+        api_key,
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(error), &options)
+}
+
+/// Same as [`notify_std_error`][notify_std_error], but sends the notice with
+/// a different API key and connection settings. See [`notify_to`][notify_to].
+///
+/// [notify_std_error]: fn.notify_std_error.html
+/// [notify_to]: fn.notify_to.html
+pub fn notify_std_error_to(
+    api_key: Option<&str>,
+    connection: &config::ConnectionConfig,
+    error: &(dyn std::error::Error + 'static),
+) {
+    let options = NoticeOptions {
+        connection: Some(connection),
+        api_key,
+        ..Default::default()
+    };
+    notify_either(FailOrError::StdError(error), &options)
+}
+
+/// Same as [`notify`][notify], but attaches tags to the notice.
+///
+/// [notify]: fn.notify.html
+pub fn notify_with_tags(error: &dyn Fail, tags: &[&str]) {
+    let options = NoticeOptions {
+        tags,
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(error), &options)
+}
+
+/// Same as [`notify_std_error`][notify_std_error], but attaches tags to the notice.
+///
+/// [notify_std_error]: fn.notify_std_error.html
+pub fn notify_std_error_with_tags(error: &(dyn std::error::Error + 'static), tags: &[&str]) {
+    let options = NoticeOptions {
+        tags,
+        ..Default::default()
+    };
+    notify_either(FailOrError::StdError(error), &options)
+}
+
+/// Same as [`notify`][notify], but with the given severity, e.g. `"critical"`.
+///
+/// The severity is sent as a `severity:<severity>` tag, so that notices can
+/// be filtered by severity, and overrides
+/// [`Config::default_severity`][default_severity].
+///
+/// [notify]: fn.notify.html
+/// [default_severity]: config/struct.Config.html#structfield.default_severity
+pub fn notify_with_severity(error: &dyn Fail, severity: &str) {
+    let options = NoticeOptions {
+        severity: Some(severity),
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(error), &options)
+}
+
+/// Same as [`notify_std_error`][notify_std_error], but with the given severity.
+/// See [`notify_with_severity`][notify_with_severity].
+///
+/// [notify_std_error]: fn.notify_std_error.html
+/// [notify_with_severity]: fn.notify_with_severity.html
+pub fn notify_std_error_with_severity(error: &(dyn std::error::Error + 'static), severity: &str) {
+    let options = NoticeOptions {
+        severity: Some(severity),
+        ..Default::default()
+    };
+    notify_either(FailOrError::StdError(error), &options)
+}
+
+/// Reports an event that deserves attention but is not a failure,
+/// with the severity `"warning"`.
+///
+/// Use `warn` for conditions the program recovered from, e.g. a retried
+/// request or a fallback to a default value, and [`notify`][notify] for
+/// errors that made an operation fail. Warnings are tagged
+/// `severity:warning`, so that they can be filtered apart from errors
+/// in the dashboard.
+///
+/// [notify]: fn.notify.html
+pub fn warn(error: &dyn Fail) {
+    notify_with_severity(error, "warning")
+}
+
+/// Same as [`warn`][warn], but for `std::error::Error`.
+///
+/// [warn]: fn.warn.html
+pub fn warn_std_error(error: &(dyn std::error::Error + 'static)) {
+    notify_std_error_with_severity(error, "warning")
+}
+
+/// Same as [`notify`][notify], but groups the notice by the given fingerprint.
+///
+/// This takes precedence over [`Fingerprint`][Fingerprint] implementations;
+/// see [the `fingerprint` module](fingerprint/index.html).
+///
+/// [notify]: fn.notify.html
+/// [Fingerprint]: fingerprint/trait.Fingerprint.html
+pub fn notify_with_fingerprint(error: &dyn Fail, fingerprint: &str) {
+    let options = NoticeOptions {
+        fingerprint: Some(fingerprint),
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(error), &options)
+}
+
+/// Same as [`notify_std_error`][notify_std_error], but groups the notice by
+/// the given fingerprint. See [`notify_with_fingerprint`][notify_with_fingerprint].
+///
+/// [notify_std_error]: fn.notify_std_error.html
+/// [notify_with_fingerprint]: fn.notify_with_fingerprint.html
+pub fn notify_std_error_with_fingerprint(
+    error: &(dyn std::error::Error + 'static),
+    fingerprint: &str,
+) {
+    let options = NoticeOptions {
+        fingerprint: Some(fingerprint),
+        ..Default::default()
+    };
+    notify_either(FailOrError::StdError(error), &options)
+}
+
+/// Same as [`notify`][notify], but adds the fields of `extra` to the context.
+///
+/// `extra` must be a JSON object; its top-level fields are merged into
+/// `request.context` of the notice, overriding the fields of the same names.
+/// Other values are ignored with a warning.
+///
+/// [notify]: fn.notify.html
+///
+/// ## Example
+///
+/// ```no_run
+/// # let error = failure::err_msg("job failed");
+/// let extra = serde_json::json!({ "job_id": 42, "queue": "mailers" });
+/// mightybadger::notify_with_extra(error.as_fail(), extra);
+/// ```
+pub fn notify_with_extra(error: &dyn Fail, extra: serde_json::Value) {
+    let extra = match extra {
+        serde_json::Value::Object(extra) => extra,
+        _ => {
+            eprintln!("** [Honeybadger] Ignoring extra data which is not an object");
+            serde_json::Map::new()
+        }
+    };
+    let options = NoticeOptions {
+        extra: Some(&extra),
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(error), &options)
+}
+
+/// Reports the error and each of its causes as separate notices.
+///
+/// Use it when the causes in a `failure::Context` chain are distinct errors
+/// of their own. The notices of the causes have the id of the notice of
+/// `error` in `request.context.parent_honeybadger_id`, to link them together.
+///
+/// ## Example
+///
+/// ```no_run
+/// use failure::ResultExt;
+///
+/// # fn main() -> Result<(), failure::Error> {
+/// let result = "x".parse::<i32>().context("invalid port");
+/// if let Err(error) = result {
+///     mightybadger::notify_cause_chain_separately(&error);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn notify_cause_chain_separately(error: &dyn Fail) {
+    let parent_id = random_uuid();
+    notify_either_with_id(
+        FailOrError::Fail(error),
+        &parent_id,
+        &NoticeOptions::default(),
+    );
+    let mut extra = serde_json::Map::new();
+    extra.insert(
+        "parent_honeybadger_id".to_string(),
+        parent_id.map(|id| id.to_string()).into(),
+    );
+    let options = NoticeOptions {
+        extra: Some(&extra),
+        ..Default::default()
+    };
+    for cause in error.iter_causes() {
+        notify_either(FailOrError::Fail(cause), &options);
+    }
+}
+
+/// Same as [`notify`][notify], but records the caller's location as the
+/// topmost backtrace frame.
+///
+/// The location comes from `#[track_caller]` rather than from debug info,
+/// so it is accurate even in release builds with stripped symbols.
+///
+/// [notify]: fn.notify.html
+#[track_caller]
+pub fn notify_here(error: &dyn Fail) {
+    let options = NoticeOptions {
+        location: Some(Location::caller()),
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(error), &options)
+}
+
+/// Same as [`notify_std_error`][notify_std_error], but records the caller's
+/// location as the topmost backtrace frame.
+///
+/// [notify_std_error]: fn.notify_std_error.html
+#[track_caller]
+pub fn notify_std_error_here(error: &(dyn std::error::Error + 'static)) {
+    let options = NoticeOptions {
+        location: Some(Location::caller()),
+        ..Default::default()
+    };
+    notify_either(FailOrError::StdError(error), &options)
+}
+
+/// Reports a sampled fraction of successful requests, for comparing error
+/// rates against a baseline and checking that reporting works in production.
+///
+/// Call it at the end of each request, while its [context][context] is set,
+/// with the HTTP status of the response. Only `2xx` statuses are sampled, at
+/// [`Config::success_sample_rate`][success_sample_rate], which is off by default.
+/// Samples are notices of class `SuccessSample` with the severity `info` and
+/// the tag `sample:success`, and at most 10 are sent per minute.
+///
+/// [context]: context/index.html
+/// [success_sample_rate]: config/struct.Config.html#structfield.success_sample_rate
+pub fn sample_success(status: u16) {
+    if !(200..300).contains(&status) {
+        return;
+    }
+    let rate = config::read_config()
+        .success_sample_rate
+        .map_or(0.0, config::SampleRate::get);
+    if rate <= 0.0 || rand::random::<f64>() >= rate {
+        return;
+    }
+    // Keyed apart from error classes, which `per_class_rate_limit` uses.
+    if !rate_limit::allow("sample:success", SUCCESS_SAMPLE_LIMIT) {
+        return;
+    }
+    let options = NoticeOptions {
+        tags: &["sample:success"],
+        severity: Some("info"),
+        ..Default::default()
+    };
+    notify_either(FailOrError::Fail(&SuccessSample(status)), &options)
+}
+
+/// Sends a synthetic notice of class `TestNotice` to check that reporting works.
+///
+/// Like the `honeybadger test` command of the Ruby notifier, the notice goes
+/// through the same pipeline as real errors (configuration, context, stats,
+/// limits, and `async_send`).
+/// Returns the UUID of the notice on success, or the first failure if any of
+/// the destinations fails. If `async_send` is enabled, the UUID is returned
+/// once the notice is queued; failures to send it are only logged.
+///
+/// ## Example
+///
+/// ```no_run
+/// mightybadger::setup();
+/// match mightybadger::trigger_test_notice() {
+///     Ok(id) => println!("test notice sent: {}", id),
+///     Err(e) => println!("test notice failed: {}", e),
+/// }
+/// ```
+pub fn trigger_test_notice() -> Result<Uuid, HoneybadgerError> {
+    let id = random_uuid();
+    let (config, derived) = config::read_config().snapshot();
+    let payload = notify_internal(
+        FailOrError::Fail(&TestNotice),
+        &id,
+        &NoticeOptions::default(),
+        &config,
+        &derived,
+        false,
+    )?;
+    match deliver(payload, None, &config)? {
+        Some(mut results) => {
+            // Every destination should work.
+            let index = results.iter().position(Result::is_err).unwrap_or(0);
+            results.swap_remove(index).map(|resp| resp.id)
+        }
+        None => Ok(id.unwrap_or_else(Uuid::nil)),
+    }
+}
+
+/// The error reported by [`notify_tool`][notify_tool].
+///
+/// [notify_tool]: fn.notify_tool.html
+#[derive(Debug)]
+struct ToolError {
+    class: String,
+    message: String,
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Fail for ToolError {
+    fn name(&self) -> Option<&str> {
+        Some(&self.class)
+    }
+}
+
+/// Time limit of sending a notice from [`notify_tool`][notify_tool].
+///
+/// [notify_tool]: fn.notify_tool.html
+const TOOL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Reports an error from a build script or a procedural macro.
+///
+/// This is best-effort: the notice is sent synchronously with a short
+/// timeout and no retries, and failures (including panics within the
+/// notifier) are only logged, so reporting never fails the build.
+/// Stats and the context of the thread are not attached, and `details` are
+/// added to the context of the notice instead.
+/// Returns the UUID of the notice if it has been sent.
+///
+/// The configuration is not read from the environment automatically;
+/// call [`configure_from_env`][configure_from_env] first.
+///
+/// [configure_from_env]: fn.configure_from_env.html
+///
+/// ## Example
+///
+/// ```no_run
+/// // build.rs
+/// mightybadger::configure_from_env();
+/// if std::process::Command::new("protoc").status().is_err() {
+///     mightybadger::notify_tool(
+///         "build::ProtocMissing",
+///         "protoc could not be run",
+///         &[("crate", env!("CARGO_PKG_NAME"))],
+///     );
+/// }
+/// ```
+pub fn notify_tool(class: &str, message: &str, details: &[(&str, &str)]) -> Option<Uuid> {
+    let id = random_uuid();
+    let iddisp = id
+        .as_ref()
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| "nil".to_string());
+    let error = ToolError {
+        class: class.to_string(),
+        message: message.to_string(),
+    };
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let (mut config, derived) = config::read_config().snapshot();
+        let timeout = config
+            .connection
+            .timeout
+            .map_or(TOOL_TIMEOUT, |timeout| timeout.min(TOOL_TIMEOUT));
+        config.connection.timeout = Some(timeout);
+        config.connection.retries = Some(0);
+        let extra = details
+            .iter()
+            .map(|&(key, value)| (key.to_string(), value.into()))
+            .collect();
+        let options = NoticeOptions {
+            extra: Some(&extra),
+            origin: NoticeOrigin::Tool,
+            ..Default::default()
+        };
+        notify_internal(
+            FailOrError::Fail(&error),
+            &id,
+            &options,
+            &config,
+            &derived,
+            true,
+        )
+        .and_then(|payload| send(&payload, &config))
+    }));
+    match result {
+        Ok(result) => {
+            log_report(&result, &iddisp);
+            result.ok().map(|resp| resp.id)
+        }
+        Err(_) => {
+            eprintln!("** [Honeybadger] Error report panicked, id={}", iddisp);
+            None
         }
-        StatusCode::PAYMENT_REQUIRED => return Err(PaymentRequired(Backtrace::new())),
-        StatusCode::FORBIDDEN => return Err(Forbidden(Backtrace::new())),
-        StatusCode::CREATED => {}
-        _ => return Err(UnknownResponse(Backtrace::new())),
     }
-    resp.json()
-        .map_err(|e| ResponseDecodeFailed(e, Backtrace::new()))
-}
-
-fn honeybadger_panic_hook(panic_info: &PanicInfo<'_>) {
-    notify(&Panic::new(panic_info));
-}
-
-pub fn notify(error: &dyn Fail) {
-    notify_either(FailOrError::Fail(error))
-}
-
-pub fn notify_std_error(error: &(dyn std::error::Error + 'static)) {
-    notify_either(FailOrError::StdError(error))
 }
 
 #[derive(Debug, Clone, Copy)]
-enum FailOrError<'a> {
+pub(crate) enum FailOrError<'a> {
     Fail(&'a dyn Fail),
     StdError(&'a (dyn std::error::Error + 'static)),
 }
 
 impl<'a> FailOrError<'a> {
+    /// Unwraps [`Error`][Error] so that the notice is made from the inner error.
+    ///
+    /// [Error]: struct.Error.html
+    fn unwrap_boxed(self) -> FailOrError<'a> {
+        match self {
+            FailOrError::Fail(error) => match error.downcast_ref::<Error>() {
+                Some(error) => FailOrError::StdError(&*error.0),
+                None => self,
+            },
+            FailOrError::StdError(_) => self,
+        }
+    }
     fn cause(self) -> Option<FailOrError<'a>> {
         match self {
             FailOrError::Fail(error) => error.cause().map(FailOrError::Fail),
@@ -158,6 +1587,16 @@ impl<'a> FailOrError<'a> {
             None
         }
     }
+    /// The backtrace of a [`Panic`][Panic], which is not a `failure` backtrace.
+    ///
+    /// [Panic]: struct.Panic.html
+    fn panic_backtrace(self) -> Option<&'a std::backtrace::Backtrace> {
+        if let FailOrError::Fail(error) = self {
+            error.downcast_ref::<Panic>().map(|panic| &panic.backtrace)
+        } else {
+            None
+        }
+    }
 }
 impl<'a> fmt::Display for FailOrError<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -168,99 +1607,772 @@ impl<'a> fmt::Display for FailOrError<'a> {
     }
 }
 
-fn notify_either<'a>(error: FailOrError<'a>) {
+/// Per-notice settings that don't come from the global configuration.
+#[derive(Debug, Clone, Copy, Default)]
+struct NoticeOptions<'a> {
+    /// Call site recorded by the `*_here` functions.
+    location: Option<&'static Location<'static>>,
+    /// Connection settings overriding the global ones, set by `notify_to`.
+    connection: Option<&'a config::ConnectionConfig>,
+    /// API key overriding the global and per-request ones, set by `notify_to`.
+    api_key: Option<&'a str>,
+    /// Tags attached to the notice.
+    tags: &'a [&'a str],
+    /// Severity overriding `config.default_severity`.
+    severity: Option<&'a str>,
+    /// Fingerprint overriding the one from the error.
+    fingerprint: Option<&'a str>,
+    /// Fields added to the context.
+    extra: Option<&'a serde_json::Map<String, serde_json::Value>>,
+    /// Backtrace captured with the error, used if the error has no `failure` backtrace.
+    std_backtrace: Option<&'a std::backtrace::Backtrace>,
+    /// Where the notice comes from, which decides the filters applied to it.
+    origin: NoticeOrigin,
+}
+
+/// Where a notice comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum NoticeOrigin {
+    /// An explicit call, such as `notify`.
+    #[default]
+    Notify,
+    /// A panic, caught by the panic hook, `capture_panics`, or
+    /// `rayon_panic_handler`.
+    Panic,
+    /// A build script or a procedural macro, reporting with `notify_tool`.
+    /// The context of the thread is not attached.
+    Tool,
+}
+
+fn notify_either<'a>(error: FailOrError<'a>, options: &NoticeOptions<'_>) {
+    notify_either_with_id(error, &random_uuid(), options)
+}
+
+fn notify_either_with_id<'a>(
+    error: FailOrError<'a>,
+    id: &Option<Uuid>,
+    options: &NoticeOptions<'_>,
+) {
+    let iddisp = id
+        .as_ref()
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| "nil".to_string());
+    // A copy, so that the lock is not held while sending, which may take
+    // long with retries and would block `configure`.
+    let (config, derived) = config::read_config().snapshot();
+    // The worker collects the stats again just before sending.
+    let defer_stats = config.async_send.unwrap_or(false) && config.deferred_stats.unwrap_or(false);
+    let payload = match notify_internal(error, id, options, &config, &derived, defer_stats) {
+        Err(e) => {
+            log_report(&Err(e), &iddisp);
+            return;
+        }
+        Ok(payload) => payload,
+    };
+    dispatch(payload, options.connection, &config, &iddisp);
+}
+
+/// Sends the exceptions recorded by `context::record_exception`.
+///
+/// See [`context::Aggregation`][context::Aggregation] for the shape of the notices.
+///
+/// [context::Aggregation]: context/enum.Aggregation.html
+pub(crate) fn notify_recorded(exceptions: Vec<ErrorInfo>, aggregation: context::Aggregation) {
+    let (config, derived) = config::read_config().snapshot();
+    match aggregation {
+        context::Aggregation::Separate => {
+            for exception in exceptions {
+                notify_recorded_one(exception, &config, &derived);
+            }
+        }
+        context::Aggregation::Combined => {
+            let mut exceptions = exceptions.into_iter();
+            let mut error_info = exceptions.next().expect("no exceptions recorded");
+            for exception in exceptions {
+                for tag in exception.tags {
+                    if !error_info.tags.contains(&tag) {
+                        error_info.tags.push(tag);
+                    }
+                }
+                error_info.causes.push(ErrorCause {
+                    class: exception.class,
+                    message: exception.message,
+                    backtrace: exception.backtrace,
+                });
+                error_info.causes.extend(exception.causes);
+            }
+            notify_recorded_one(error_info, &config, &derived);
+        }
+    }
+}
+
+fn notify_recorded_one(
+    mut error_info: ErrorInfo,
+    config: &config::Config,
+    derived: &config::Derived,
+) {
     let id = random_uuid();
     let iddisp = id
         .as_ref()
         .map(|u| u.to_string())
         .unwrap_or_else(|| "nil".to_string());
-    let resp = match notify_internal(error, &id) {
+    let defer_stats = config.async_send.unwrap_or(false) && config.deferred_stats.unwrap_or(false);
+    let dev_display = match check_report_data(config) {
+        Ok(dev_display) => dev_display,
+        Err(e) => {
+            log_report(&Err(e), &iddisp);
+            return;
+        }
+    };
+    error_info.token = id;
+    let options = NoticeOptions::default();
+    match assemble_payload(
+        error_info,
+        &options,
+        config,
+        derived,
+        defer_stats,
+        dev_display,
+    ) {
+        Ok(payload) => dispatch(payload, None, config, &iddisp),
+        Err(e) => log_report(&Err(e), &iddisp),
+    }
+}
+
+/// Sends the payload, from the worker if `async_send` is enabled.
+fn dispatch(
+    payload: Payload,
+    connection: Option<&config::ConnectionConfig>,
+    config: &config::Config,
+    iddisp: &str,
+) {
+    let result = match deliver(payload, connection, config) {
+        Ok(Some(results)) => any_delivered(results),
+        Ok(None) => return,
+        Err(e) => Err(e),
+    };
+    log_report(&result, iddisp);
+}
+
+/// Sends the payload to each destination, or hands it to `testing::intercept`
+/// or the worker, in which case `None` is returned.
+fn deliver(
+    payload: Payload,
+    connection: Option<&config::ConnectionConfig>,
+    config: &config::Config,
+) -> Result<Option<Vec<Result<HoneybadgerResponse, HoneybadgerError>>>, HoneybadgerError> {
+    #[cfg(any(test, feature = "test-server"))]
+    {
+        if testing::try_intercept(&payload) {
+            return Ok(None);
+        }
+    }
+    let connection = connection.cloned();
+    // There are no threads to run the worker on WebAssembly.
+    if config.async_send.unwrap_or(false) && !cfg!(target_arch = "wasm32") {
+        worker::enqueue(payload, connection);
+        return Ok(None);
+    }
+    let _slot = match SendSlot::acquire(config) {
+        Some(slot) => slot,
+        None => {
+            DROPPED_NOTICES.fetch_add(1, Ordering::Relaxed);
+            return Err(TooManyConcurrentSends(Backtrace::new()));
+        }
+    };
+    let results = if let Some(ref connection) = connection {
+        send_each(&payload, &config.with_connection(connection))
+    } else {
+        send_each(&payload, config)
+    };
+    Ok(Some(results))
+}
+
+/// The number of notices being sent synchronously.
+static SENDS_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of notices dropped by `max_concurrent_sends`.
+static DROPPED_NOTICES: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of notices counted toward `max_notices_per_process`.
+static NOTICES_ASSEMBLED: AtomicUsize = AtomicUsize::new(0);
+
+/// A slot of `max_concurrent_sends`, released when dropped.
+struct SendSlot;
+
+impl SendSlot {
+    /// Takes a slot, or returns `None` if all slots are in use.
+    fn acquire(config: &config::Config) -> Option<SendSlot> {
+        let max = config.max_concurrent_sends.unwrap_or(usize::MAX);
+        SENDS_IN_FLIGHT
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                if n < max {
+                    Some(n + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| SendSlot)
+    }
+}
+
+impl Drop for SendSlot {
+    fn drop(&mut self) {
+        SENDS_IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns the number of notices dropped in this process because
+/// [`max_concurrent_sends`][max_concurrent_sends] notices were already being sent.
+///
+/// [max_concurrent_sends]: config/struct.Config.html#structfield.max_concurrent_sends
+pub fn dropped_notice_count() -> usize {
+    DROPPED_NOTICES.load(Ordering::Relaxed)
+}
+
+/// The minimum interval between the messages about dropped notices of one kind.
+const DROP_LOG_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Dropped notices of one kind, keyed by the message, since it was last logged.
+struct DropLog {
+    logged_at: std::time::Instant,
+    suppressed: usize,
+}
+
+lazy_static! {
+    static ref DROP_LOGS: Mutex<BTreeMap<String, DropLog>> = Mutex::new(BTreeMap::new());
+}
+
+/// Returns the number of drops not logged since the last message if the drop
+/// should be logged, or `None` if it is suppressed.
+fn throttle_drop_log(message: &str, now: std::time::Instant) -> Option<usize> {
+    let mut logs = DROP_LOGS.lock().unwrap_or_else(|e| e.into_inner());
+    match logs.get_mut(message) {
+        Some(log) if now.saturating_duration_since(log.logged_at) < DROP_LOG_INTERVAL => {
+            log.suppressed += 1;
+            None
+        }
+        Some(log) => {
+            log.logged_at = now;
+            Some(std::mem::replace(&mut log.suppressed, 0))
+        }
+        None => {
+            logs.insert(
+                message.to_string(),
+                DropLog {
+                    logged_at: now,
+                    suppressed: 0,
+                },
+            );
+            Some(0)
+        }
+    }
+}
+
+fn log_report(result: &Result<HoneybadgerResponse, HoneybadgerError>, iddisp: &str) {
+    match result {
         Err(NoReportData(_)) => {
             eprintln!(
                 "** [Honeybadger] Configured not to send reports, id={}",
                 iddisp
             );
-            return;
+        }
+        Err(e @ ClassRateLimited(..))
+        | Err(e @ NoticeLimitReached(..))
+        | Err(e @ TooManyConcurrentSends(..)) => {
+            // These come in floods, so they are logged at most once per
+            // DROP_LOG_INTERVAL, with the number of the ones not logged.
+            let message = e.to_string();
+            match throttle_drop_log(&message, std::time::Instant::now()) {
+                Some(0) => eprintln!("** [Honeybadger] {}, id={}", message, iddisp),
+                Some(suppressed) => eprintln!(
+                    "** [Honeybadger] {}, id={} ({} more dropped since the last message)",
+                    message, iddisp, suppressed
+                ),
+                None => {}
+            }
+        }
+        Err(e @ IgnoredRequestMethod(..))
+        | Err(e @ IgnoredRequestPath(..))
+        | Err(e @ IgnoredErrorClass(..))
+        | Err(e @ IgnoredPanicMessage(..))
+        | Err(e @ PanicSampledOut(..)) => {
+            eprintln!("** [Honeybadger] {}, id={}", e, iddisp);
         }
         Err(e) => {
             eprintln!("** [Honeybadger] Error report failed: {}, id={}", e, iddisp);
-            return;
         }
-        Ok(resp) => resp,
-    };
-    let id = resp.id;
+        Ok(resp) => {
+            let id = resp.id;
+            eprintln!(
+                "** [Honeybadger] Success ⚡ https://app.honeybadger.io/notice/{} id={}",
+                id, id
+            );
+        }
+    }
+}
+
+fn log_destination_failure(error: &HoneybadgerError, config: &config::Config) {
     eprintln!(
-        "** [Honeybadger] Success ⚡ https://app.honeybadger.io/notice/{} id={}",
-        id, id
+        "** [Honeybadger] Error report to {} failed: {}",
+        config
+            .connection
+            .host
+            .as_deref()
+            .unwrap_or("api.honeybadger.io"),
+        error
     );
 }
 
+fn send(
+    payload: &Payload,
+    config: &config::Config,
+) -> Result<HoneybadgerResponse, HoneybadgerError> {
+    any_delivered(send_each(payload, config))
+}
+
+/// Picks the result of the notice from those of [`send_each`][send_each].
+///
+/// [send_each]: fn.send_each.html
+fn any_delivered(
+    mut results: Vec<Result<HoneybadgerResponse, HoneybadgerError>>,
+) -> Result<HoneybadgerResponse, HoneybadgerError> {
+    // The notice counts as delivered if any destination accepts it.
+    let index = results.iter().position(Result::is_ok).unwrap_or(0);
+    results.swap_remove(index)
+}
+
+/// Sends the payload to the primary destination and to each of
+/// `additional_destinations`, and returns the results in this order.
+///
+/// Failures of the additional destinations are logged, and so is a failure of
+/// the primary one if another destination accepted the notice.
+fn send_each(
+    payload: &Payload,
+    config: &config::Config,
+) -> Vec<Result<HoneybadgerResponse, HoneybadgerError>> {
+    let mut results = vec![report(payload, config)];
+    if !config.additional_destinations.is_empty() {
+        let mut payload = payload.clone();
+        for (api_key, connection) in &config.additional_destinations {
+            payload.api_key.clone_from(api_key);
+            let destination = config.with_connection(connection);
+            let result = report(&payload, &destination);
+            if let Err(ref e) = result {
+                log_destination_failure(e, &destination);
+            }
+            results.push(result);
+        }
+        if let Err(ref e) = results[0] {
+            if results.iter().any(Result::is_ok) {
+                log_destination_failure(e, config);
+            }
+        }
+    }
+    let delivered = results.iter().find(|result| result.is_ok());
+    record_report_status(delivered.unwrap_or(&results[0]), &results);
+    results
+}
+
+/// Checks the configuration and assembles the payload for the error.
+///
+/// Stats are left empty if `defer_stats` is `true`.
 fn notify_internal<'a>(
     error: FailOrError<'a>,
     id: &Option<Uuid>,
-) -> Result<HoneybadgerResponse, HoneybadgerError> {
-    let config = config::read_config();
-    let report_data = config.report_data.unwrap_or_else(|| {
-        let env = config.env.as_ref().map(|s| s.as_str()).unwrap_or("");
-        ["test", "development", "cucumber"]
+    options: &NoticeOptions<'_>,
+    config: &config::Config,
+    derived: &config::Derived,
+    defer_stats: bool,
+) -> Result<Payload, HoneybadgerError> {
+    let dev_display = check_report_data(config)?;
+    if options.origin == NoticeOrigin::Panic {
+        check_panic(error, config)?;
+    }
+    let error_info = error_info(error, id, options, config);
+    let mut extra = io_error_context(error);
+    if extra.is_empty() {
+        return assemble_payload(
+            error_info,
+            options,
+            config,
+            derived,
+            defer_stats,
+            dev_display,
+        );
+    }
+    // Fields given explicitly take precedence.
+    if let Some(options_extra) = options.extra {
+        extra.extend(options_extra.clone());
+    }
+    let options = NoticeOptions {
+        extra: Some(&extra),
+        ..*options
+    };
+    assemble_payload(
+        error_info,
+        &options,
+        config,
+        derived,
+        defer_stats,
+        dev_display,
+    )
+}
+
+/// Applies `panic_ignore_messages` and `panic_sample_rate` to a panic.
+fn check_panic(error: FailOrError<'_>, config: &config::Config) -> Result<(), HoneybadgerError> {
+    if let Some(ref ignore_messages) = config.panic_ignore_messages {
+        let message = match error {
+            FailOrError::Fail(error) => error.to_string(),
+            FailOrError::StdError(error) => error.to_string(),
+        };
+        if ignore_messages
             .iter()
-            .all(|&s| env != s)
-    });
-    if !report_data {
+            .any(|ignored| message.contains(ignored.as_str()))
+        {
+            return Err(IgnoredPanicMessage(message, Backtrace::new()));
+        }
+    }
+    let rate = config
+        .panic_sample_rate
+        .map_or(1.0, config::SampleRate::get);
+    if rate < 1.0 && rand::random::<f64>() >= rate {
+        return Err(PanicSampledOut(Backtrace::new()));
+    }
+    Ok(())
+}
+
+/// Returns `errno` and `io_error_kind` for the context if the error is a `std::io::Error`.
+fn io_error_context(error: FailOrError<'_>) -> serde_json::Map<String, serde_json::Value> {
+    let io_error = match error.unwrap_boxed() {
+        FailOrError::Fail(error) => error.downcast_ref::<io::Error>(),
+        FailOrError::StdError(error) => error.downcast_ref::<io::Error>(),
+    };
+    let mut context = serde_json::Map::new();
+    if let Some(io_error) = io_error {
+        if let Some(errno) = io_error.raw_os_error() {
+            context.insert("errno".to_string(), errno.into());
+        }
+        context.insert(
+            "io_error_kind".to_string(),
+            format!("{:?}", io_error.kind()).into(),
+        );
+    }
+    context
+}
+
+/// Checks whether notices are to be assembled at all.
+///
+/// Returns `true` if the notice is only assembled for `dev_display`.
+fn check_report_data(config: &config::Config) -> Result<bool, HoneybadgerError> {
+    let report_data = config.reports();
+    // The notice is still assembled for `dev_display`, but not sent.
+    let dev_display = !report_data && config.dev_display.unwrap_or(false);
+    if !report_data && !dev_display {
         return Err(NoReportData(Backtrace::new()));
     }
-    let api_key = config
-        .api_key
-        .clone()
-        .ok_or_else(|| NoApiKey(Backtrace::new()))?;
-    let backtrace = if let Some(bt) = error.backtrace() {
-        btparse::parse_and_decorate(bt)
+    Ok(dev_display)
+}
+
+/// Extracts the class, message, backtrace, and causes of the error.
+fn error_info<'a>(
+    error: FailOrError<'a>,
+    id: &Option<Uuid>,
+    options: &NoticeOptions<'_>,
+    config: &config::Config,
+) -> ErrorInfo {
+    let error = error.unwrap_boxed();
+    let mut backtrace = if let Some(bt) = error.backtrace() {
+        btparse::parse_and_decorate(bt, config)
+    } else if let Some(bt) = options.std_backtrace.or_else(|| error.panic_backtrace()) {
+        btparse::parse_and_decorate_std(bt, config)
     } else {
-        btparse::parse_and_decorate(&Backtrace::new())
+        btparse::parse_and_decorate(&Backtrace::new(), config)
     };
-    let notifier_info = Some(NotifierInfo {
-        name: "mightybadger-rust",
-        url: "https://github.com/qnighy/mightybadger-rs",
-        version: env!("CARGO_PKG_VERSION"),
-        language: "rust",
-    });
+    if let Some(location) = options.location {
+        let call_site = btparse::BacktraceLine {
+            line: Some(location.line()),
+            file: Some(location.file().to_string()),
+            method: "<call site>".to_string(),
+        };
+        backtrace.splice(0..0, btparse::decorate(vec![call_site], config));
+    }
     let causes = {
         let mut causes = Vec::new();
         let mut opterror = error.cause();
         while let Some(error) = opterror {
-            let backtrace = error.backtrace().map(|bt| btparse::parse_and_decorate(bt));
+            let backtrace = error
+                .backtrace()
+                .map(|bt| btparse::parse_and_decorate(bt, config));
             causes.push(ErrorCause {
                 class: error_class(error),
                 message: error.to_string(),
-                backtrace: backtrace,
+                backtrace,
             });
             opterror = error.cause();
         }
         causes
     };
-    let error_info = ErrorInfo {
-        token: id.clone(),
+    let mut tags = notice_tags(options, config);
+    if let Some((_, metadata_tags)) = metadata::get(error) {
+        for tag in metadata_tags {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    ErrorInfo {
+        token: *id,
         class: error_class(error),
         message: error.to_string(),
-        tags: vec![],
-        fingerprint: "".to_string(),
+        tags,
+        fingerprint: options
+            .fingerprint
+            .map(|fingerprint| fingerprint.to_string())
+            .or_else(|| fingerprint::fingerprint(error))
+            .unwrap_or_default(),
         backtrace: Some(backtrace),
-        causes: causes,
+        causes,
+    }
+}
+
+/// Tags of the notice, including the severity tag.
+fn notice_tags(options: &NoticeOptions<'_>, config: &config::Config) -> Vec<String> {
+    let mut tags = options
+        .tags
+        .iter()
+        .map(|tag| tag.to_string())
+        .collect::<Vec<_>>();
+    let severity = options.severity.or(config.default_severity.as_deref());
+    if let Some(severity) = severity {
+        tags.push(format!("severity:{}", severity));
+    }
+    for span in context::span_names() {
+        if !tags.contains(&span) {
+            tags.push(span);
+        }
+    }
+    tags
+}
+
+/// Formats `config.log_url_template` for the notice, percent-encoding the
+/// substituted values.
+///
+/// Returns `None` if a variable used in the template is unknown.
+fn log_url(
+    template: &str,
+    error_info: &ErrorInfo,
+    request_info: Option<&RequestInfo>,
+    config: &config::Config,
+) -> Option<String> {
+    let context_str = |key: &str| {
+        request_info
+            .and_then(|request| request.context.get(key))
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+    let request_id = context_str("request_id").or_else(|| {
+        request_info.and_then(|request| request.cgi_data.get("HTTP_X_REQUEST_ID").cloned())
+    });
+    let variables = [
+        (
+            "{notice_id}",
+            error_info.token.map(|token| token.to_string()),
+        ),
+        ("{request_id}", request_id),
+        ("{transaction_id}", context_str("transaction_id")),
+        ("{env}", config.env.clone()),
+    ];
+    let mut url = template.to_string();
+    for (name, value) in &variables {
+        if url.contains(name) {
+            url = url.replace(name, &percent_encode(value.as_ref()?));
+        }
+    }
+    Some(url)
+}
+
+/// Percent-encodes all bytes but the unreserved characters of RFC 3986.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for &byte in value.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char);
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Assembles the payload around `error_info` from the current context.
+///
+/// `derived` must be derived from `config`.
+fn assemble_payload(
+    error_info: ErrorInfo,
+    options: &NoticeOptions<'_>,
+    config: &config::Config,
+    derived: &config::Derived,
+    defer_stats: bool,
+    dev_display: bool,
+) -> Result<Payload, HoneybadgerError> {
+    let notifier_info = NotifierInfo::current();
+    let mut request_info = if options.origin == NoticeOrigin::Tool {
+        None
+    } else {
+        context::get()
+    };
+    // A per-request key (e.g. of the tenant) takes precedence,
+    // unless the caller gave one explicitly.
+    let request_api_key = request_info
+        .as_mut()
+        .and_then(|request| request.api_key_override.take());
+    let api_key = options
+        .api_key
+        .map(|api_key| api_key.to_string())
+        .or(request_api_key)
+        .or_else(|| config.api_key.clone());
+    let api_key = if dev_display {
+        api_key.unwrap_or_default()
+    } else {
+        api_key.ok_or_else(|| NoApiKey(Backtrace::new()))?
+    };
+    if let Some(method) = request_info
+        .as_ref()
+        .and_then(|request| request.cgi_data.get("REQUEST_METHOD"))
+    {
+        if !config.request.report_method(method) {
+            return Err(IgnoredRequestMethod(method.clone(), Backtrace::new()));
+        }
+    }
+    if let Some(ref request) = request_info {
+        if !config.request.report_path(&request.url) {
+            return Err(IgnoredRequestPath(request.url.clone(), Backtrace::new()));
+        }
+    }
+    #[cfg(feature = "log-filter")]
+    {
+        if let Some(ref error_filter) = derived.error_filter {
+            if !error_filter.enabled(&error_info.class) {
+                return Err(IgnoredErrorClass(error_info.class, Backtrace::new()));
+            }
+        }
+    }
+    if let Some(limit) = config.per_class_rate_limit {
+        if !rate_limit::allow(&error_info.class, limit) {
+            return Err(ClassRateLimited(error_info.class, Backtrace::new()));
+        }
+    }
+    if let Some(max) = config.max_notices_per_process {
+        // Only notices passing the filters above count toward the limit.
+        let within_limit = NOTICES_ASSEMBLED
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                if n < max {
+                    Some(n + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok();
+        if !within_limit {
+            return Err(NoticeLimitReached(max, Backtrace::new()));
+        }
+    }
+    let server_info = if defer_stats {
+        ServerInfo::generate_without_stats()
+    } else {
+        ServerInfo::generate()
     };
-    let server_info = ServerInfo::generate();
-    let request_info = context::get();
+    // The detected revision is filled in by `ServerInfo::generate`.
+    let git_branch = derived
+        .git_head
+        .as_ref()
+        .and_then(|git_head| git_head.branch.clone());
+    if let Some(branch) = git_branch {
+        request_info
+            .get_or_insert_with(RequestInfo::default)
+            .context
+            .insert("git_branch".to_string(), branch.into());
+    }
+    let queries = context::queries();
+    if !queries.is_empty() {
+        let queries = serde_json::to_value(queries)
+            .map_err(|e| CouldNotAssemblePayload(e, Backtrace::new()))?;
+        request_info
+            .get_or_insert_with(RequestInfo::default)
+            .context
+            .insert("queries".to_string(), queries);
+    }
+    if config.capture_process_info.unwrap_or(false) {
+        let context = &mut request_info
+            .get_or_insert_with(RequestInfo::default)
+            .context;
+        let args = payload::filter_args(
+            std::env::args_os().map(|arg| arg.to_string_lossy().into_owned()),
+            &config.request,
+        );
+        context.insert("args".to_string(), args.into());
+        if let Ok(cwd) = std::env::current_dir() {
+            context.insert("cwd".to_string(), cwd.to_string_lossy().into());
+        }
+    }
+    if let Some(extra) = options.extra.filter(|extra| !extra.is_empty()) {
+        let context = &mut request_info
+            .get_or_insert_with(RequestInfo::default)
+            .context;
+        for (k, v) in extra {
+            context.insert(k.clone(), v.clone());
+        }
+    }
+    if let Some(ref template) = config.log_url_template {
+        if let Some(log_url) = log_url(template, &error_info, request_info.as_ref(), config) {
+            request_info
+                .get_or_insert_with(RequestInfo::default)
+                .context
+                .insert("log_url".to_string(), log_url.into());
+        }
+    }
+    #[allow(unused_mut)]
+    let mut details = BTreeMap::new();
+    #[cfg(feature = "tokio")]
+    {
+        if let Some(tokio) = tokio_metrics::details() {
+            details.insert("tokio".to_string(), tokio);
+        }
+    }
     let mut payload = Payload {
-        api_key: api_key,
+        api_key,
         notifier: notifier_info,
         error: error_info,
         request: request_info,
         server: server_info,
+        details,
     };
     payload.sanitize();
-    report(&payload, &config)
+    // Cloned out, as it may itself notify or replace the preprocessor.
+    let preprocessor = NOTICE_PREPROCESSOR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    if let Some(preprocessor) = preprocessor {
+        preprocessor(&mut payload);
+    }
+    #[cfg(feature = "validate")]
+    validate::validate(&payload);
+    if dev_display {
+        eprintln!("{}", payload.pretty());
+        return Err(NoReportData(Backtrace::new()));
+    }
+    subscribe::publish(&payload);
+    Ok(payload)
 }
 
 fn error_class<'a>(error: FailOrError<'a>) -> String {
+    if let Some((class_name, _)) = metadata::get(error) {
+        return class_name;
+    }
     if let FailOrError::Fail(error) = error {
         if let Some(name) = error.name() {
             return name.to_owned();
@@ -270,14 +2382,14 @@ fn error_class<'a>(error: FailOrError<'a>) -> String {
         ($($class:ty,)*) => {
             $(
                 if let FailOrError::Fail(error) = error {
-                    if Fail::downcast_ref::<$class>(error).is_some() {
+                    if <dyn Fail>::downcast_ref::<$class>(error).is_some() {
                         return stringify!($class).to_string();
                     }
-                    if Fail::downcast_ref::<failure::Context<$class>>(error).is_some() {
+                    if <dyn Fail>::downcast_ref::<failure::Context<$class>>(error).is_some() {
                         return stringify!(failure::Context<$class>).to_string();
                     }
                 } else if let FailOrError::StdError(error) = error {
-                    if std::error::Error::downcast_ref::<$class>(error).is_some() {
+                    if <dyn std::error::Error>::downcast_ref::<$class>(error).is_some() {
                         return stringify!($class).to_string();
                     }
                 }
@@ -288,10 +2400,10 @@ fn error_class<'a>(error: FailOrError<'a>) -> String {
         ($($class:ty,)*) => {
             $(
                 if let FailOrError::Fail(error) = error {
-                    if Fail::downcast_ref::<$class>(error).is_some() {
+                    if <dyn Fail>::downcast_ref::<$class>(error).is_some() {
                         return stringify!($class).to_string();
                     }
-                    if Fail::downcast_ref::<failure::Context<$class>>(error).is_some() {
+                    if <dyn Fail>::downcast_ref::<failure::Context<$class>>(error).is_some() {
                         return stringify!(failure::Context<$class>).to_string();
                     }
                 }
@@ -338,7 +2450,7 @@ fn error_class<'a>(error: FailOrError<'a>) -> String {
     mod mightybadger {
         pub use crate::Panic;
     }
-    return "Fail".to_string();
+    "Fail".to_string()
 }
 
 pub fn install_hook() {
@@ -355,16 +2467,186 @@ pub fn install_hook() {
     });
 }
 
+/// Enables backtrace capture by setting `RUST_FAILURE_BACKTRACE=1` and `RUST_BACKTRACE=1`.
+///
+/// It is called as a part of [`setup`][setup].
+///
+/// `failure` reads `RUST_FAILURE_BACKTRACE` first and falls back to `RUST_BACKTRACE`;
+/// a value of `0` disables capture. The decision is cached when the first
+/// backtrace is captured, so this must be called early in the process.
+///
+/// `RUST_BACKTRACE=1` enables `std::backtrace::Backtrace::capture` as well
+/// (used by e.g. `anyhow`), so that notices carry frames even for errors
+/// which have no backtrace of their own. It is left as is if it is already
+/// set to a value other than `0`, e.g. `full`. As a side effect, the default
+/// panic hook prints backtraces to stderr.
+///
+/// Note that capturing is not free: with backtraces enabled, every
+/// `failure::Error` and `anyhow::Error` walks the stack when it is created,
+/// even if it is handled and never reported. This is usually negligible,
+/// but may matter for code which creates many errors on hot paths.
+///
+/// [setup]: fn.setup.html
 pub fn enable_backtrace() {
     use std::env;
 
     env::set_var("RUST_FAILURE_BACKTRACE", "1");
+    if !matches!(env::var_os("RUST_BACKTRACE"), Some(value) if value != "0") {
+        env::set_var("RUST_BACKTRACE", "1");
+    }
+}
+
+/// Same as [`enable_backtrace`][enable_backtrace], but also sets `RUST_BACKTRACE=full`.
+///
+/// `RUST_BACKTRACE` is the variable recognized by the standard library:
+/// with `full`, the default panic hook prints unabbreviated backtraces to stderr.
+/// `RUST_FAILURE_BACKTRACE=full` enables capture for `failure` errors as well.
+///
+/// [enable_backtrace]: fn.enable_backtrace.html
+pub fn enable_backtrace_full() {
+    use std::env;
+
+    env::set_var("RUST_BACKTRACE", "full");
+    env::set_var("RUST_FAILURE_BACKTRACE", "full");
+}
+
+/// Disables backtrace capture by setting `RUST_FAILURE_BACKTRACE=0`.
+///
+/// This takes precedence over `RUST_BACKTRACE`, and applies to `failure`
+/// errors and panics alike. Notices are then sent without backtraces,
+/// e.g. when they are symbolized separately from debug files.
+/// Like [`enable_backtrace`][enable_backtrace], it must be called before
+/// the first backtrace is captured.
+///
+/// [enable_backtrace]: fn.enable_backtrace.html
+pub fn disable_backtrace() {
+    use std::env;
+
+    env::set_var("RUST_FAILURE_BACKTRACE", "0");
 }
 
 pub fn setup() {
-    configure_from_env();
-    install_hook();
-    enable_backtrace();
+    setup_with_options(SetupOptions::default());
+}
+
+/// Configures Honeybadger and sets it up in one step, if the configuration is valid.
+///
+/// `f` is applied to a copy of the configuration, which is then completed
+/// from the environment variables like [`configure_from_env`][configure_from_env]
+/// and [validated][validate]. Only if it is valid, the configuration is
+/// replaced at once and the rest of [`setup`][setup] is done: the panic hook
+/// is installed and backtraces are enabled. Otherwise nothing is changed.
+///
+/// [configure_from_env]: config/fn.configure_from_env.html
+/// [validate]: config/struct.Config.html#method.validate
+/// [setup]: fn.setup.html
+///
+/// ## Example
+///
+/// ```no_run
+/// mightybadger::configure_and_setup(|config| {
+///     config.env = Some("production".to_string());
+/// })
+/// .expect("invalid Honeybadger configuration");
+/// ```
+pub fn configure_and_setup<F>(f: F) -> Result<(), ConfigValidationError>
+where
+    F: FnOnce(&mut config::Config),
+{
+    config::configure_validated(f)?;
+    setup_with_options(SetupOptions {
+        configure_from_env: false,
+        ..Default::default()
+    });
+    Ok(())
+}
+
+/// How much backtrace to capture, set by [`setup_with_options`][setup_with_options].
+///
+/// [setup_with_options]: fn.setup_with_options.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacktraceLevel {
+    /// No backtraces, as in [`disable_backtrace`][disable_backtrace].
+    ///
+    /// [disable_backtrace]: fn.disable_backtrace.html
+    None,
+    /// Backtraces of `failure` errors, as in [`enable_backtrace`][enable_backtrace].
+    ///
+    /// [enable_backtrace]: fn.enable_backtrace.html
+    One,
+    /// Unabbreviated backtraces of both `failure` errors and panics,
+    /// as in [`enable_backtrace_full`][enable_backtrace_full].
+    ///
+    /// [enable_backtrace_full]: fn.enable_backtrace_full.html
+    Full,
+}
+
+/// Steps taken by [`setup_with_options`][setup_with_options].
+///
+/// The default is what [`setup`][setup] does.
+///
+/// [setup_with_options]: fn.setup_with_options.html
+/// [setup]: fn.setup.html
+#[derive(Debug, Clone)]
+pub struct SetupOptions {
+    /// Whether to call [`configure_from_env`][configure_from_env].
+    /// Defaults to `true`.
+    ///
+    /// [configure_from_env]: config/fn.configure_from_env.html
+    pub configure_from_env: bool,
+    /// Whether to call [`install_hook`][install_hook].
+    /// Defaults to `true`.
+    ///
+    /// [install_hook]: fn.install_hook.html
+    pub install_hook: bool,
+    /// Whether to set up backtrace capture according to `backtrace_level`.
+    /// Defaults to `true`.
+    pub enable_backtrace: bool,
+    /// Defaults to `BacktraceLevel::One`.
+    pub backtrace_level: BacktraceLevel,
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for SetupOptions {
+    fn default() -> Self {
+        SetupOptions {
+            configure_from_env: true,
+            install_hook: true,
+            enable_backtrace: true,
+            backtrace_level: BacktraceLevel::One,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+/// Same as [`setup`][setup], but each step can be skipped.
+///
+/// [setup]: fn.setup.html
+///
+/// ## Example
+///
+/// ```
+/// // The application installs its own panic hook.
+/// mightybadger::setup_with_options(mightybadger::SetupOptions {
+///     install_hook: false,
+///     ..Default::default()
+/// });
+/// ```
+pub fn setup_with_options(opts: SetupOptions) {
+    if opts.configure_from_env {
+        configure_from_env();
+    }
+    if opts.install_hook {
+        install_hook();
+    }
+    if opts.enable_backtrace {
+        match opts.backtrace_level {
+            BacktraceLevel::None => disable_backtrace(),
+            BacktraceLevel::One => enable_backtrace(),
+            BacktraceLevel::Full => enable_backtrace_full(),
+        }
+    }
 }
 
 fn random_uuid() -> Option<Uuid> {
@@ -379,3 +2661,103 @@ fn random_uuid() -> Option<Uuid> {
         .build();
     Some(uuid)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_url_encoding() {
+        let mut request_info = RequestInfo::default();
+        request_info
+            .context
+            .insert("request_id".to_string(), "a b&c#d/e{env}".into());
+        let config = config::Config {
+            env: Some("prod/eu ü".to_string()),
+            ..Default::default()
+        };
+        let url = log_url(
+            "https://logs.example.com/{env}?q={request_id}",
+            &ErrorInfo::default(),
+            Some(&request_info),
+            &config,
+        );
+        assert_eq!(
+            url.as_deref(),
+            Some("https://logs.example.com/prod%2Feu%20%C3%BC?q=a%20b%26c%23d%2Fe%7Benv%7D")
+        );
+    }
+
+    #[test]
+    fn test_throttle_drop_log() {
+        let message = "test_throttle_drop_log";
+        let start = std::time::Instant::now();
+        assert_eq!(throttle_drop_log(message, start), Some(0));
+        assert_eq!(throttle_drop_log(message, start), None);
+        let later = start + std::time::Duration::from_secs(30);
+        assert_eq!(throttle_drop_log(message, later), None);
+        assert_eq!(throttle_drop_log("another kind", later), Some(0));
+        let later = start + DROP_LOG_INTERVAL;
+        assert_eq!(throttle_drop_log(message, later), Some(2));
+        assert_eq!(throttle_drop_log(message, later), None);
+    }
+
+    #[test]
+    fn test_std_error() {
+        fn read_notices() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            let e = io::Error::new(io::ErrorKind::NotFound, "no such file");
+            Err(NoticeFileFailed(e, Backtrace::new()))?;
+            Ok(())
+        }
+        let e = read_notices().unwrap_err();
+        assert_eq!(e.to_string(), "could not read or write the notice file");
+        let source = e.source().unwrap();
+        assert_eq!(source.to_string(), "no such file");
+        assert!(source.downcast_ref::<io::Error>().is_some());
+
+        let e = HoneybadgerStdError::from(NoApiKey(Backtrace::new()));
+        assert!(std::error::Error::source(&e).is_none());
+
+        #[cfg(feature = "anyhow")]
+        {
+            let e = anyhow::Error::from(NoticeFileFailed(
+                io::Error::new(io::ErrorKind::Other, "disk full"),
+                Backtrace::new(),
+            ));
+            assert_eq!(
+                e.chain().map(|e| e.to_string()).collect::<Vec<_>>(),
+                ["could not read or write the notice file", "disk full"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        use std::time::Duration;
+
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::from_secs(0))
+        );
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let delay = parse_retry_after(&date).unwrap();
+        assert!(delay > Duration::from_secs(25) && delay <= Duration::from_secs(30));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_panic_extractor() {
+        struct ExitCode(i32);
+
+        let message = |payload: Box<dyn Any + Send>| Panic::from_payload(&*payload).message;
+        assert_eq!(message(Box::new("static")), "static");
+        assert_eq!(message(Box::new(ExitCode(3))), "Box<Any>");
+        register_panic_extractor(|payload| {
+            let code = payload.downcast_ref::<ExitCode>()?;
+            Some(format!("exited with code {}", code.0))
+        });
+        assert_eq!(message(Box::new(ExitCode(3))), "exited with code 3");
+        assert_eq!(message(Box::new(42)), "Box<Any>");
+    }
+}
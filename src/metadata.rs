@@ -0,0 +1,97 @@
+//! Classes and tags declared on error types.
+//!
+//! Implement [`HoneybadgerMetadata`][HoneybadgerMetadata] for an error type,
+//! usually with `#[derive(HoneybadgerMetadata)]` (the `macros` feature), and
+//! [register][register] the type. Notices of such errors, and causes of
+//! such types, then use its class name, and the notices get its tags.
+//!
+//! [HoneybadgerMetadata]: trait.HoneybadgerMetadata.html
+//! [register]: fn.register.html
+//!
+//! ## Example
+//!
+//! ```ignore
+//! use mightybadger::HoneybadgerMetadata;
+//!
+//! #[derive(Debug, HoneybadgerMetadata)]
+//! #[honeybadger(tag = "payment")]
+//! enum ChargeError {
+//!     Declined,
+//!     #[honeybadger(tag = "fraud")]
+//!     Fraud,
+//! }
+//! # impl std::fmt::Display for ChargeError {
+//! #     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//! #         f.write_str("charge failed")
+//! #     }
+//! # }
+//! # impl std::error::Error for ChargeError {}
+//!
+//! mightybadger::metadata::register_std_error::<ChargeError>();
+//! ```
+
+use failure::Fail;
+use lazy_static::lazy_static;
+
+use crate::registry::Registry;
+use crate::FailOrError;
+
+/// Errors which declare their class name and tags.
+///
+/// Implementations take effect once the type is registered with
+/// [`register`][register] or [`register_std_error`][register_std_error].
+///
+/// [register]: fn.register.html
+/// [register_std_error]: fn.register_std_error.html
+pub trait HoneybadgerMetadata {
+    /// Returns the class of the notice, e.g. `app::billing::ChargeError`.
+    fn class_name(&self) -> &str;
+
+    /// Returns the tags attached to the notice.
+    fn tags(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Class name and tags of an error.
+type Metadata = (String, Vec<String>);
+
+type Extractor = Box<dyn Fn(FailOrError<'_>) -> Option<Metadata> + Send + Sync>;
+
+lazy_static! {
+    static ref EXTRACTORS: Registry<Extractor> = Registry::new();
+}
+
+fn metadata<E: HoneybadgerMetadata + ?Sized>(error: &E) -> Metadata {
+    (error.class_name().to_string(), error.tags())
+}
+
+/// Uses [`HoneybadgerMetadata`][HoneybadgerMetadata] of `E` for errors
+/// passed to `notify`.
+///
+/// [HoneybadgerMetadata]: trait.HoneybadgerMetadata.html
+pub fn register<E: Fail + HoneybadgerMetadata>() {
+    EXTRACTORS.push(Box::new(|error| match error {
+        FailOrError::Fail(error) => error.downcast_ref::<E>().map(metadata),
+        FailOrError::StdError(_) => None,
+    }));
+}
+
+/// Uses [`HoneybadgerMetadata`][HoneybadgerMetadata] of `E` for errors
+/// passed to either `notify` or `notify_std_error`.
+///
+/// [HoneybadgerMetadata]: trait.HoneybadgerMetadata.html
+pub fn register_std_error<E>()
+where
+    E: std::error::Error + HoneybadgerMetadata + Send + Sync + 'static,
+{
+    EXTRACTORS.push(Box::new(|error| match error {
+        FailOrError::Fail(error) => error.downcast_ref::<E>().map(metadata),
+        FailOrError::StdError(error) => error.downcast_ref::<E>().map(metadata),
+    }));
+}
+
+/// Returns the metadata from the first registered type matching the error.
+pub(crate) fn get(error: FailOrError<'_>) -> Option<Metadata> {
+    EXTRACTORS.find_map(|extractor| extractor(error))
+}
@@ -1,31 +1,169 @@
 use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::mem;
 use std::process;
 
-use chrono::Utc;
-use serde_derive::Serialize;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::btparse;
 use crate::config;
+use crate::context;
 use crate::stats;
 
 /// Notification payload.
-#[derive(Debug, Serialize, Default)]
+///
+/// The `Debug` output shows the API key as `****`.
+/// When deserialized, e.g. by [`replay_notices`][::replay_notices],
+/// `notifier` is set to this crate.
+///
+/// [::replay_notices]: ../fn.replay_notices.html
+#[derive(Clone, Serialize, Deserialize, Default)]
 pub struct Payload {
     pub api_key: String,
+    #[serde(skip_deserializing, default = "NotifierInfo::current")]
     pub notifier: Option<NotifierInfo>,
     pub error: ErrorInfo,
     pub request: Option<RequestInfo>,
     pub server: ServerInfo,
+    /// Additional sections of information, by section name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, serde_json::Value>,
 }
 
 impl Payload {
     pub(crate) fn sanitize(&mut self) {
-        self.request.as_mut().map(|req| req.sanitize());
+        if let Some(req) = self.request.as_mut() {
+            req.sanitize();
+        }
+        self.server.stats.sanitize();
+    }
+
+    /// Returns a human-readable rendering of the notice, for printing to a terminal.
+    ///
+    /// It shows the error, its causes, the topmost backtrace frames, and the context.
+    pub fn pretty(&self) -> PrettyPayload<'_> {
+        PrettyPayload(self)
+    }
+
+    /// Updates the time and the stats of the server information.
+    ///
+    /// Useful when the payload is sent some time after it is assembled.
+    /// The other fields are kept, including edits by a notice preprocessor.
+    pub fn refresh_server_info(&mut self) {
+        let local_time = config::read_config().local_time.unwrap_or(false);
+        self.server.time = current_time(local_time);
+        self.server.stats = Stats::generate();
+    }
+
+    /// Replaces the request information with the current thread's
+    /// [context][context], and filters it.
+    ///
+    /// [context]: ../context/fn.get.html
+    pub fn refresh_context(&mut self) {
+        self.request = context::get();
+        if let Some(req) = self.request.as_mut() {
+            req.sanitize();
+        }
+    }
+
+    /// Returns a copy with the API key replaced by `****`,
+    /// e.g. for keeping notices in audit logs.
+    pub fn redact_api_key(&self) -> Payload {
+        Payload {
+            api_key: REDACTED_API_KEY.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+const REDACTED_API_KEY: &str = "****";
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Payload {
+            api_key: _,
+            notifier,
+            error,
+            request,
+            server,
+            details,
+        } = self;
+        f.debug_struct("Payload")
+            .field("api_key", &REDACTED_API_KEY)
+            .field("notifier", notifier)
+            .field("error", error)
+            .field("request", request)
+            .field("server", server)
+            .field("details", details)
+            .finish()
+    }
+}
+
+/// Human-readable rendering of a [`Payload`], returned by [`Payload::pretty`].
+#[derive(Debug, Clone, Copy)]
+pub struct PrettyPayload<'a>(&'a Payload);
+
+impl PrettyPayload<'_> {
+    /// Number of backtrace frames shown.
+    const MAX_FRAMES: usize = 10;
+
+    fn fmt_backtrace(backtrace: &[BacktraceEntry], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, entry) in backtrace.iter().take(Self::MAX_FRAMES).enumerate() {
+            writeln!(f, "  {:>2}: {}", i, entry.method)?;
+            if let Some(ref file) = entry.file {
+                match entry.number {
+                    Some(ref number) => writeln!(f, "          at {}:{}", file, number)?,
+                    None => writeln!(f, "          at {}", file)?,
+                }
+            }
+        }
+        if backtrace.len() > Self::MAX_FRAMES {
+            writeln!(
+                f,
+                "      ...({} more frames)",
+                backtrace.len() - Self::MAX_FRAMES
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PrettyPayload<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let error = &self.0.error;
+        writeln!(f, "** [Honeybadger] {}: {}", error.class, error.message)?;
+        if !error.tags.is_empty() {
+            writeln!(f, "tags: {}", error.tags.join(", "))?;
+        }
+        if let Some(ref backtrace) = error.backtrace {
+            Self::fmt_backtrace(backtrace, f)?;
+        }
+        for cause in &error.causes {
+            writeln!(f, "caused by {}: {}", cause.class, cause.message)?;
+            if let Some(ref backtrace) = cause.backtrace {
+                Self::fmt_backtrace(backtrace, f)?;
+            }
+        }
+        if let Some(ref request) = self.0.request {
+            if !request.url.is_empty() {
+                writeln!(f, "url: {}", request.url)?;
+            }
+            if !request.context.is_empty() {
+                writeln!(f, "context:")?;
+                let context = request.context.iter().collect::<BTreeMap<_, _>>();
+                for (k, v) in context {
+                    writeln!(f, "  {} = {}", k, v)?;
+                }
+            }
+        }
+        Ok(())
     }
 }
 
 /// Information of the app that caused the error.
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NotifierInfo {
     pub name: &'static str,
     pub url: &'static str,
@@ -33,7 +171,19 @@ pub struct NotifierInfo {
     pub language: &'static str,
 }
 
-#[derive(Debug, Serialize, Default)]
+impl NotifierInfo {
+    /// Information of this crate.
+    pub(crate) fn current() -> Option<NotifierInfo> {
+        Some(NotifierInfo {
+            name: "mightybadger-rust",
+            url: "https://github.com/qnighy/mightybadger-rs",
+            version: env!("CARGO_PKG_VERSION"),
+            language: "rust",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ErrorInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token: Option<Uuid>,
@@ -46,7 +196,7 @@ pub struct ErrorInfo {
     pub causes: Vec<ErrorCause>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BacktraceEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub number: Option<String>,
@@ -55,9 +205,40 @@ pub struct BacktraceEntry {
     pub method: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<BTreeMap<u32, String>>,
+    /// Link to the line in a code host.
+    /// Built from [`Config::source_url_template`][source_url_template].
+    ///
+    /// [source_url_template]: ../config/struct.Config.html#structfield.source_url_template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// The result of [`is_noise`](#method.is_noise) when the notice was assembled,
+    /// so that the UI can fold such frames.
+    #[serde(default)]
+    pub noise: bool,
+}
+
+impl BacktraceEntry {
+    /// Returns `true` if the frame is in the machinery of panics, backtraces,
+    /// or error reporting (including this crate) rather than in the code
+    /// which caused the error.
+    pub fn is_noise(&self) -> bool {
+        btparse::is_noise(&self.method)
+    }
+
+    /// Returns `true` if the file of the frame is in the project, i.e. under
+    /// [`Config::root`][root] or given as a relative path, which Rust uses for
+    /// the crate being built. Frames without a file are not in the project.
+    ///
+    /// [root]: ../config/struct.Config.html#structfield.root
+    pub fn is_in_app(&self) -> bool {
+        let config = config::read_config();
+        self.file
+            .as_deref()
+            .is_some_and(|file| btparse::is_in_app(file, config.root.as_deref()))
+    }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorCause {
     pub class: String,
     pub message: String,
@@ -65,7 +246,7 @@ pub struct ErrorCause {
     pub backtrace: Option<Vec<BacktraceEntry>>,
 }
 
-#[derive(Debug, Clone, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RequestInfo {
     pub url: String,
     pub cgi_data: HashMap<String, String>,
@@ -75,35 +256,256 @@ pub struct RequestInfo {
     pub session: HashMap<String, String>,
     pub context: HashMap<String, serde_json::Value>,
     pub local_variables: HashMap<String, serde_json::Value>,
+    /// The `User-Agent` of the request.
+    /// Copied from `cgi_data["HTTP_USER_AGENT"]` if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// The IP address of the client.
+    /// Copied from `cgi_data["REMOTE_ADDR"]` if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<String>,
+    /// The API key to send the notice with, instead of `Config::api_key`,
+    /// e.g. to route the errors of each tenant to their own project.
+    /// It is only used for authentication and not sent as a part of the request.
+    #[serde(skip)]
+    pub api_key_override: Option<String>,
 }
 
 impl RequestInfo {
     pub(crate) fn sanitize(&mut self) {
         let config = config::read_config();
+        let filter = Filter::new(&config.request, config.derived());
         for (k, v) in self.cgi_data.iter_mut() {
-            if config.request.filter_key(k) {
+            if filter.key(k) || filter.value(v) {
                 *v = "[FILTERED]".to_string();
             }
         }
         for (k, v) in self.params.iter_mut() {
-            if config.request.filter_key(k) {
+            if filter.key(k) || filter.value(v) {
                 *v = "[FILTERED]".to_string();
             }
         }
         for (k, v) in self.session.iter_mut() {
-            if config.request.filter_key(k) {
+            if filter.key(k) || filter.value(v) {
                 *v = "[FILTERED]".to_string();
             }
         }
         for (k, v) in self.context.iter_mut() {
-            if config.request.filter_key(k) {
+            if filter.key(k) {
                 *v = serde_json::Value::String("[FILTERED]".to_string());
+            } else {
+                sanitize_json(v, &filter);
+            }
+        }
+        if self.user_agent.is_none() {
+            self.user_agent = self.cgi_data.get("HTTP_USER_AGENT").cloned();
+        }
+        if self.ip_address.is_none() {
+            self.ip_address = self.cgi_data.get("REMOTE_ADDR").cloned();
+        }
+        if let Some(max) = config.request.max_request_entries {
+            truncate_entries(&mut self.cgi_data, max, |s| s);
+            truncate_entries(&mut self.params, max, |s| s);
+            truncate_entries(&mut self.session, max, |s| s);
+            truncate_entries(&mut self.context, max, serde_json::Value::String);
+        }
+    }
+
+    /// Attaches a JSON request body to the context under `request_body`.
+    ///
+    /// Bodies of other content types are ignored. Bodies larger than
+    /// [`max_body_bytes`][max_body_bytes] are replaced with a placeholder.
+    /// Keys in the body are filtered like the other request data.
+    ///
+    /// This only borrows the body: middlewares should buffer the body once,
+    /// pass it here, and hand the same bytes on to the handler.
+    ///
+    /// [max_body_bytes]: ../config/struct.RequestConfig.html#structfield.max_body_bytes
+    pub fn capture_body(&mut self, content_type: Option<&str>, body: &[u8]) {
+        let mime = content_type
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|mime| mime.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        if mime != "application/json" && !mime.ends_with("+json") {
+            return;
+        }
+        let max_body_bytes = config::read_config()
+            .request
+            .max_body_bytes
+            .unwrap_or(16384);
+        let value = if body.len() > max_body_bytes {
+            serde_json::Value::String(format!("[{} bytes omitted]", body.len()))
+        } else if let Ok(value) = serde_json::from_slice(body) {
+            value
+        } else {
+            return;
+        };
+        self.context.insert("request_body".to_string(), value);
+    }
+
+    /// Attaches the headers of an error response to the context under
+    /// `response_headers`, e.g. a custom `X-Error-Code` explaining a 5xx.
+    ///
+    /// Only the headers listed in [`response_headers`][response_headers]
+    /// are kept. They are filtered like the other request data.
+    ///
+    /// [response_headers]: ../config/struct.RequestConfig.html#structfield.response_headers
+    pub fn capture_response_headers<'a, I>(&mut self, headers: I)
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let config = config::read_config();
+        let default_names = ["Content-Type".to_string()];
+        let names = config
+            .request
+            .response_headers
+            .as_deref()
+            .unwrap_or(&default_names);
+        let captured = headers
+            .into_iter()
+            .filter(|(name, _)| names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+            .map(|(name, value)| (name.to_string(), serde_json::Value::from(value)))
+            .collect::<serde_json::Map<_, _>>();
+        if !captured.is_empty() {
+            self.context.insert(
+                "response_headers".to_string(),
+                serde_json::Value::Object(captured),
+            );
+        }
+    }
+}
+
+/// Command-line arguments of the process, with the values of secret-looking
+/// options replaced with `[FILTERED]`.
+///
+/// Both `--password=secret` and `--password secret` are filtered,
+/// according to [`filter_keys`][filter_keys].
+///
+/// [filter_keys]: ../config/struct.RequestConfig.html#structfield.filter_keys
+pub(crate) fn filter_args<I>(args: I, config: &config::RequestConfig) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut filter_next = false;
+    args.into_iter()
+        .map(|arg| {
+            if mem::take(&mut filter_next) {
+                return "[FILTERED]".to_string();
+            }
+            if !arg.starts_with('-') {
+                return arg;
+            }
+            let (name, value) = match arg.find('=') {
+                Some(pos) => (&arg[..pos], Some(&arg[pos + 1..])),
+                None => (&arg[..], None),
+            };
+            if !config.filter_key(name.trim_start_matches('-')) {
+                return arg;
+            }
+            match value {
+                Some(_) => format!("{}=[FILTERED]", name),
+                None => {
+                    filter_next = true;
+                    arg
+                }
+            }
+        })
+        .collect()
+}
+
+/// Keeps the first `max` entries in the order of keys, and replaces the rest
+/// with an `[OMITTED]` entry.
+fn truncate_entries<V, F>(map: &mut HashMap<String, V>, max: usize, note: F)
+where
+    F: FnOnce(String) -> V,
+{
+    if map.len() <= max {
+        return;
+    }
+    let mut keys = map.keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    let omitted = keys.len() - max;
+    for key in &keys[max..] {
+        map.remove(key);
+    }
+    map.insert(
+        "[OMITTED]".to_string(),
+        note(format!("…({} more omitted)", omitted)),
+    );
+}
+
+/// Decides which request data to filter.
+struct Filter<'a> {
+    config: &'a config::RequestConfig,
+    /// Compiled `filter_value_patterns`.
+    #[cfg(feature = "regex")]
+    value_patterns: Option<&'a regex::RegexSet>,
+}
+
+impl<'a> Filter<'a> {
+    #[allow(unused_variables)]
+    fn new(config: &'a config::RequestConfig, derived: &'a config::Derived) -> Self {
+        Filter {
+            config,
+            #[cfg(feature = "regex")]
+            value_patterns: derived.value_patterns.as_ref(),
+        }
+    }
+
+    fn key(&self, key: &str) -> bool {
+        self.config.filter_key(key)
+    }
+
+    #[cfg(feature = "regex")]
+    fn value(&self, value: &str) -> bool {
+        self.value_patterns
+            .as_ref()
+            .map(|patterns| patterns.is_match(value))
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn value(&self, _value: &str) -> bool {
+        false
+    }
+}
+
+/// Filters the values under secret-looking keys of nested objects,
+/// and the secret-looking strings.
+fn sanitize_json(value: &mut serde_json::Value, filter: &Filter<'_>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if filter.key(k) {
+                    *v = serde_json::Value::String("[FILTERED]".to_string());
+                } else {
+                    sanitize_json(v, filter);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for v in values {
+                sanitize_json(v, filter);
             }
         }
+        serde_json::Value::String(s) if filter.value(s) => {
+            *s = "[FILTERED]".to_string();
+        }
+        _ => {}
     }
 }
 
-#[derive(Debug, Serialize, Default)]
+/// A database query recorded by [`context::add_query`][add_query].
+///
+/// [add_query]: ../context/fn.add_query.html
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryInfo {
+    pub sql: String,
+    /// Duration in milliseconds.
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ServerInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub project_root: Option<String>,
@@ -113,6 +515,13 @@ pub struct ServerInfo {
     pub environment_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hostname: Option<String>,
+    /// Custom deployment metadata from `Config::server_attributes`.
+    #[serde(
+        rename = "attributes",
+        default,
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub custom_attributes: HashMap<String, String>,
     pub stats: Stats,
     pub time: String,
     pub pid: u32,
@@ -120,24 +529,58 @@ pub struct ServerInfo {
 
 impl ServerInfo {
     pub fn generate() -> Self {
+        let mut server_info = Self::generate_without_stats();
+        server_info.stats = Stats::generate();
+        server_info
+    }
+
+    /// Same as `generate`, but leaves `stats` empty.
+    pub(crate) fn generate_without_stats() -> Self {
         let config = config::read_config();
-        let time = Utc::now().format("%Y-%m-%d %H:%M:%S %Z").to_string();
-        let pid = process::id();
-        let stats = Stats::generate();
+        let time = current_time(config.local_time.unwrap_or(false));
+        // `process::id` panics on WebAssembly.
+        let pid = if cfg!(target_arch = "wasm32") {
+            0
+        } else {
+            process::id()
+        };
         ServerInfo {
             project_root: config.root.clone(),
-            revision: config.revision.clone(),
+            revision: config
+                .revision
+                .clone()
+                .or_else(|| config.derived().detected_revision().cloned()),
             environment_name: config.env.clone(),
             hostname: config.hostname.clone(),
-            time: time,
-            pid: pid,
-            stats: stats,
-            ..Default::default()
+            custom_attributes: config.server_attributes.clone(),
+            time,
+            pid,
+            stats: Stats::default(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Default)]
+/// Formats the current time, in the local time zone if `local_time` is `true`.
+fn current_time(local_time: bool) -> String {
+    if local_time {
+        format_time(&Local::now())
+    } else {
+        format_time(&Utc::now())
+    }
+}
+
+/// Formats the time like Ruby's `Time#to_s`, e.g. `2019-01-01 09:00:00 +0900`.
+fn format_time<Tz>(time: &DateTime<Tz>) -> String
+where
+    Tz: TimeZone,
+    Tz::Offset: fmt::Display,
+{
+    time.format(TIME_FORMAT).to_string()
+}
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Stats {
     pub mem: Option<MemoryInfo>,
     pub load: Option<LoadInfo>,
@@ -145,11 +588,32 @@ pub struct Stats {
 
 impl Stats {
     pub fn generate() -> Self {
-        stats::get_stats()
+        let mut stats = stats::get_stats();
+        stats.sanitize();
+        stats
+    }
+
+    /// Replaces non-finite values (NaN and infinities) with `None`.
+    pub(crate) fn sanitize(&mut self) {
+        fn finite(value: &mut Option<f64>) {
+            *value = value.filter(|value| value.is_finite());
+        }
+        if let Some(mem) = self.mem.as_mut() {
+            finite(&mut mem.total);
+            finite(&mut mem.free);
+            finite(&mut mem.buffers);
+            finite(&mut mem.cached);
+            finite(&mut mem.free_total);
+        }
+        if let Some(load) = self.load.as_mut() {
+            finite(&mut load.one);
+            finite(&mut load.five);
+            finite(&mut load.fifteen);
+        }
     }
 }
 
-#[derive(Debug, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MemoryInfo {
     pub total: Option<f64>,
     pub free: Option<f64>,
@@ -158,9 +622,240 @@ pub struct MemoryInfo {
     pub free_total: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct LoadInfo {
     pub one: Option<f64>,
     pub five: Option<f64>,
     pub fifteen: Option<f64>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_api_key() {
+        let payload = Payload {
+            api_key: "hbp_secret123".to_string(),
+            ..Payload::default()
+        };
+        let debug = format!("{:?}", payload);
+        assert!(!debug.contains("hbp_secret123"));
+        assert!(debug.contains("api_key: \"****\""));
+        assert!(!format!("{:#?}", payload).contains("hbp_secret123"));
+
+        let redacted = payload.redact_api_key();
+        assert_eq!(redacted.api_key, "****");
+        assert_eq!(payload.api_key, "hbp_secret123");
+        assert!(!serde_json::to_string(&redacted)
+            .unwrap()
+            .contains("hbp_secret123"));
+    }
+
+    #[test]
+    fn test_format_time() {
+        let now = Utc::now();
+        for time in [format_time(&now), format_time(&now.with_timezone(&Local))] {
+            let parsed = DateTime::parse_from_str(&time, TIME_FORMAT).unwrap();
+            assert_eq!(parsed.timestamp(), now.timestamp());
+        }
+        let utc = Utc.with_ymd_and_hms(2019, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(format_time(&utc), "2019-01-02 03:04:05 +0000");
+    }
+
+    #[test]
+    fn test_filter_args() {
+        let config = config::RequestConfig::default();
+        let args = [
+            "app",
+            "--password=abc",
+            "--password",
+            "def",
+            "-v",
+            "password",
+        ];
+        let args = filter_args(args.iter().map(|arg| arg.to_string()), &config);
+        assert_eq!(
+            args,
+            [
+                "app",
+                "--password=[FILTERED]",
+                "--password",
+                "[FILTERED]",
+                "-v",
+                "password"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_refresh_context() {
+        let mut payload = Payload::default();
+        let mut request = RequestInfo::default();
+        request
+            .params
+            .insert("password".to_string(), "secret".to_string());
+        context::with(&request, || payload.refresh_context());
+        assert_eq!(payload.request.unwrap().params["password"], "[FILTERED]");
+    }
+
+    #[test]
+    fn test_pretty() {
+        let mut payload = Payload::default();
+        payload.error.class = "std::io::Error".to_string();
+        payload.error.message = "file not found".to_string();
+        payload.error.tags = vec!["db".to_string()];
+        payload.error.backtrace = Some(
+            (0..12)
+                .map(|i| BacktraceEntry {
+                    number: Some(i.to_string()),
+                    file: Some("src/main.rs".to_string()),
+                    method: format!("app::f{}", i),
+                    source: None,
+                    source_url: None,
+                    noise: false,
+                })
+                .collect(),
+        );
+        let mut request = RequestInfo::default();
+        request
+            .context
+            .insert("user_id".to_string(), serde_json::json!(42));
+        payload.request = Some(request);
+        let pretty = payload.pretty().to_string();
+        assert!(pretty.starts_with("** [Honeybadger] std::io::Error: file not found\ntags: db\n"));
+        assert!(pretty.contains("   9: app::f9\n          at src/main.rs:9\n"));
+        assert!(!pretty.contains("app::f10"));
+        assert!(pretty.contains("...(2 more frames)"));
+        assert!(pretty.ends_with("context:\n  user_id = 42\n"));
+    }
+
+    #[test]
+    fn test_server_attributes() {
+        let mut server = ServerInfo::default();
+        let json = serde_json::to_value(&server).unwrap();
+        assert!(json.get("attributes").is_none());
+        server
+            .custom_attributes
+            .insert("pipeline_id".to_string(), "1234".to_string());
+        let json = serde_json::to_value(&server).unwrap();
+        assert_eq!(json["attributes"]["pipeline_id"], "1234");
+    }
+
+    #[test]
+    fn test_user_agent() {
+        let mut request = RequestInfo::default();
+        request
+            .cgi_data
+            .insert("HTTP_USER_AGENT".to_string(), "curl/8.0.0".to_string());
+        request.sanitize();
+        assert_eq!(request.user_agent.as_deref(), Some("curl/8.0.0"));
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["user_agent"], "curl/8.0.0");
+    }
+
+    #[test]
+    fn test_ip_address() {
+        let mut request = RequestInfo::default();
+        request.sanitize();
+        assert_eq!(request.ip_address, None);
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("ip_address").is_none());
+
+        request
+            .cgi_data
+            .insert("REMOTE_ADDR".to_string(), "192.0.2.1".to_string());
+        request.sanitize();
+        assert_eq!(request.ip_address.as_deref(), Some("192.0.2.1"));
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["ip_address"], "192.0.2.1");
+
+        // An address set by the middleware, e.g. from `X-Forwarded-For`, is kept.
+        request.ip_address = Some("198.51.100.7".to_string());
+        request.sanitize();
+        assert_eq!(request.ip_address.as_deref(), Some("198.51.100.7"));
+    }
+
+    #[test]
+    fn test_capture_body() {
+        let mut request = RequestInfo::default();
+        request.capture_body(Some("text/plain"), b"{}");
+        assert!(request.context.is_empty());
+        request.capture_body(
+            Some("application/json; charset=utf-8"),
+            br#"{"user": {"name": "foo", "password": "bar"}}"#,
+        );
+        request.sanitize();
+        assert_eq!(
+            request.context["request_body"],
+            serde_json::json!({"user": {"name": "foo", "password": "[FILTERED]"}})
+        );
+    }
+
+    #[test]
+    fn test_capture_response_headers() {
+        let mut request = RequestInfo::default();
+        request
+            .capture_response_headers(vec![("content-type", "text/html"), ("X-Error-Code", "E42")]);
+        assert_eq!(
+            request.context["response_headers"],
+            serde_json::json!({"content-type": "text/html"})
+        );
+        let mut request = RequestInfo::default();
+        request.capture_response_headers(vec![("Server", "nginx")]);
+        assert!(request.context.is_empty());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_filter_value_patterns() {
+        let config = config::RequestConfig::default();
+        let patterns = vec![r"^\d{16}$".to_string(), "(".to_string()];
+        let derived = config::Derived {
+            value_patterns: config::compile_value_patterns(&patterns),
+            ..Default::default()
+        };
+        let filter = Filter::new(&config, &derived);
+        assert!(filter.value("4111111111111111"));
+        assert!(!filter.value("foo"));
+        let mut value = serde_json::json!({"card": ["4111111111111111", "foo"], "n": 1});
+        sanitize_json(&mut value, &filter);
+        assert_eq!(
+            value,
+            serde_json::json!({"card": ["[FILTERED]", "foo"], "n": 1})
+        );
+    }
+
+    #[test]
+    fn test_truncate_entries() {
+        let mut params = (0..1000)
+            .map(|i| (format!("key{:04}", i), i.to_string()))
+            .collect::<HashMap<_, _>>();
+        truncate_entries(&mut params, 3, |s| s);
+        assert_eq!(params.len(), 4);
+        assert_eq!(params["key0000"], "0");
+        assert_eq!(params["key0002"], "2");
+        assert_eq!(params["[OMITTED]"], "…(997 more omitted)");
+
+        truncate_entries(&mut params, 10, |s| s);
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn test_stats_sanitize() {
+        let mut stats = Stats {
+            mem: None,
+            load: Some(LoadInfo {
+                one: Some(f64::NAN),
+                five: Some(f64::INFINITY),
+                fifteen: Some(0.5),
+            }),
+        };
+        stats.sanitize();
+        let load = stats.load.as_ref().unwrap();
+        assert_eq!(load.one, None);
+        assert_eq!(load.five, None);
+        assert_eq!(load.fifteen, Some(0.5));
+        serde_json::to_string(&stats).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+//! Local rate limiting of notices per error class.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref BUCKETS: Mutex<HashMap<String, TokenBucket>> = Mutex::new(HashMap::new());
+}
+
+/// Allows bursts of up to `capacity` notices, refilled at `capacity` per `period`.
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, now: Instant) -> Self {
+        TokenBucket {
+            tokens: f64::from(capacity),
+            last_refill: now,
+        }
+    }
+
+    fn try_take(&mut self, capacity: u32, period: Duration, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let rate = f64::from(capacity) / period.as_secs_f64();
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(f64::from(capacity));
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Returns whether a notice of `class` is allowed under `limit`,
+/// i.e. at most `limit.0` notices per `limit.1`.
+pub(crate) fn allow(class: &str, limit: (u32, Duration)) -> bool {
+    let (capacity, period) = limit;
+    if capacity == 0 {
+        return false;
+    }
+    if period == Duration::from_secs(0) {
+        return true;
+    }
+    let now = Instant::now();
+    let mut buckets = BUCKETS.lock().unwrap_or_else(|e| e.into_inner());
+    buckets
+        .entry(class.to_string())
+        .or_insert_with(|| TokenBucket::new(capacity, now))
+        .try_take(capacity, period, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let period = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+        assert!(bucket.try_take(2, period, start));
+        assert!(bucket.try_take(2, period, start));
+        assert!(!bucket.try_take(2, period, start));
+        // One token is refilled every 30 seconds.
+        assert!(!bucket.try_take(2, period, start + Duration::from_secs(20)));
+        assert!(bucket.try_take(2, period, start + Duration::from_secs(31)));
+        assert!(!bucket.try_take(2, period, start + Duration::from_secs(32)));
+        // Tokens don't accumulate beyond the capacity.
+        let later = start + Duration::from_secs(3600);
+        assert!(bucket.try_take(2, period, later));
+        assert!(bucket.try_take(2, period, later));
+        assert!(!bucket.try_take(2, period, later));
+    }
+}
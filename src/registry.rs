@@ -0,0 +1,35 @@
+//! Lists of functions registered by the application, tried in order.
+
+use std::sync::RwLock;
+
+/// Functions registered by e.g. [`fingerprint::register`][register],
+/// consulted in order of registration.
+///
+/// [register]: ../fingerprint/fn.register.html
+pub(crate) struct Registry<F> {
+    entries: RwLock<Vec<F>>,
+}
+
+impl<F> Registry<F> {
+    pub(crate) fn new() -> Self {
+        Registry {
+            entries: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn push(&self, entry: F) {
+        self.entries
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(entry);
+    }
+
+    /// Returns the first `Some` returned by `f` for the entries.
+    pub(crate) fn find_map<T>(&self, f: impl FnMut(&F) -> Option<T>) -> Option<T> {
+        self.entries
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find_map(f)
+    }
+}
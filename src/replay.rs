@@ -0,0 +1,110 @@
+//! Re-sending notices saved to a file.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Backtrace;
+
+use crate::config;
+use crate::payload::Payload;
+use crate::HoneybadgerError::{self, *};
+
+/// Sends the notices saved in the file at `path`, one JSON payload per line,
+/// e.g. ones captured while offline. Returns the number of notices sent.
+///
+/// Sending stops at the first failure, so that an unreachable server or
+/// rate limiting doesn't cost a timeout per notice. Notices not sent,
+/// including lines which cannot be parsed, are written back to the file,
+/// so that replaying can be retried later; the file is left empty once
+/// every notice is sent. Notices are sent as they are, without the current
+/// context or stats.
+///
+/// The file is replaced at once, through a temporary file next to it, so
+/// that it is left intact if the process stops in the middle. Lines appended
+/// to the file during the replay are lost, though: stop writing to it before
+/// calling this function.
+///
+/// ## Example
+///
+/// Notices can be saved with [`subscribe`][subscribe], and replayed at the
+/// next start before saving new ones:
+///
+/// ```no_run
+/// use std::fs::OpenOptions;
+/// use std::io::Write;
+/// use std::path::Path;
+/// use std::thread;
+///
+/// if Path::new("notices.jsonl").exists() {
+///     match mightybadger::replay_notices(Path::new("notices.jsonl")) {
+///         Ok(count) => println!("sent {} notices", count),
+///         Err(e) => println!("replay failed: {}", e),
+///     }
+/// }
+///
+/// let notices = mightybadger::subscribe();
+/// thread::spawn(move || {
+///     let mut file = OpenOptions::new()
+///         .create(true)
+///         .append(true)
+///         .open("notices.jsonl")
+///         .unwrap();
+///     for payload in notices {
+///         writeln!(file, "{}", serde_json::to_string(&*payload).unwrap()).unwrap();
+///     }
+/// });
+/// ```
+///
+/// [subscribe]: fn.subscribe.html
+pub fn replay_notices(path: &Path) -> Result<usize, HoneybadgerError> {
+    // A copy, so that the lock is not held while sending.
+    let config = config::read_config().clone();
+    if !config.reports() {
+        return Err(NoReportData(Backtrace::new()));
+    }
+    let content = fs::read_to_string(path).map_err(|e| NoticeFileFailed(e, Backtrace::new()))?;
+    let mut sent = 0;
+    let mut unsent = String::new();
+    let mut stopped = false;
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        if stopped {
+            unsent.push_str(line);
+            unsent.push('\n');
+            continue;
+        }
+        let mut payload = match serde_json::from_str::<Payload>(line) {
+            Ok(payload) => payload,
+            Err(e) => {
+                eprintln!("** [Honeybadger] Skipping a saved notice: {}", e);
+                unsent.push_str(line);
+                unsent.push('\n');
+                continue;
+            }
+        };
+        if payload.api_key.is_empty() {
+            payload.api_key = config.api_key.clone().unwrap_or_default();
+        }
+        match crate::send(&payload, &config) {
+            Ok(_) => sent += 1,
+            Err(e) => {
+                eprintln!("** [Honeybadger] Error replay failed: {}", e);
+                unsent.push_str(line);
+                unsent.push('\n');
+                stopped = true;
+            }
+        }
+    }
+    let tmp_path = tmp_path(path);
+    fs::write(&tmp_path, unsent)
+        .and_then(|()| fs::rename(&tmp_path, path))
+        .map_err(|e| NoticeFileFailed(e, Backtrace::new()))?;
+    Ok(sent)
+}
+
+/// The temporary file next to `path`, e.g. `notices.jsonl.tmp`.
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_path = OsString::from(path);
+    tmp_path.push(".tmp");
+    PathBuf::from(tmp_path)
+}
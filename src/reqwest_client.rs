@@ -0,0 +1,101 @@
+//! Transport based on `reqwest`, supporting HTTP/2.
+//!
+//! This module is available with the `reqwest-backend` feature.
+
+use std::io::Read;
+
+use attohttpc::StatusCode;
+use failure::Backtrace;
+use uuid::Uuid;
+
+use crate::client::HttpClient;
+use crate::config::Config;
+use crate::payload::Payload;
+use crate::HoneybadgerError::{self, *};
+
+/// [`HttpClient`][HttpClient] sending notices with `reqwest`.
+///
+/// Unlike the default transport, connections are pooled and HTTP/2 is used
+/// when the server supports it (negotiated over TLS), so concurrent notices,
+/// e.g. with [`connection.max_connections`][max_connections], share a single
+/// connection. The connection settings other than `socket_path` are
+/// respected.
+///
+/// It uses `reqwest::blocking`, so it must not be created or dropped
+/// from within an async runtime.
+///
+/// [HttpClient]: client/trait.HttpClient.html
+/// [max_connections]: config/struct.ConnectionConfig.html#structfield.max_connections
+///
+/// ## Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// mightybadger::configure(|config| {
+///     config.http_client = Some(Arc::new(mightybadger::ReqwestHttpClient::new()));
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReqwestHttpClient {
+    client: reqwest::blocking::Client,
+}
+
+impl ReqwestHttpClient {
+    /// Creates a client with the default settings of `reqwest`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the TLS backend cannot be initialized,
+    /// like `reqwest::blocking::Client::new`.
+    pub fn new() -> Self {
+        Self::with_client(reqwest::blocking::Client::new())
+    }
+
+    /// Creates a client sending notices with `client`, e.g. one built with
+    /// `http2_prior_knowledge` for a plain-text HTTP/2 endpoint.
+    pub fn with_client(client: reqwest::blocking::Client) -> Self {
+        ReqwestHttpClient { client }
+    }
+}
+
+impl Default for ReqwestHttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpClient for ReqwestHttpClient {
+    fn report(&self, payload: &Payload, config: &Config) -> Result<Uuid, HoneybadgerError> {
+        let body = serde_json::to_vec(payload)
+            .map_err(|e| CouldNotAssemblePayload(e, Backtrace::new()))?;
+        let mut request = self.client.post(crate::notice_url(config));
+        if let Some(timeout) = config.connection.timeout {
+            request = request.timeout(timeout);
+        }
+        let resp = request
+            .header("X-API-Key", payload.api_key.as_str())
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .header("User-Agent", crate::client_version())
+            .body(body)
+            .send()
+            .map_err(|e| ReqwestFailed(e, Backtrace::new()))?;
+        let status = StatusCode::from_u16(resp.status().as_u16())
+            .expect("reqwest only returns valid status codes");
+        let retry_after = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(crate::parse_retry_after);
+        let mut body = Vec::new();
+        // Read one more byte to tell whether the limit is exceeded.
+        let limit = (crate::max_response_body_bytes(config) as u64).saturating_add(1);
+        resp.take(limit)
+            .read_to_end(&mut body)
+            .map_err(|e| ResponseDecodeFailed(e.into(), Backtrace::new()))?;
+        crate::check_status(status, retry_after, &payload.api_key, &body)?;
+        crate::check_response_size(&body, config)?;
+        crate::decode_response(&body, payload, config).map(|resp| resp.id)
+    }
+}
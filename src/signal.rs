@@ -0,0 +1,43 @@
+//! Reloading the configuration on `SIGHUP`.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use signal_hook::consts::SIGHUP;
+use signal_hook::iterator::Signals;
+
+use crate::config;
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Reloads the configuration from the environment on `SIGHUP`.
+///
+/// On each signal, [`configure_from_env_forced`][configure_from_env_forced]
+/// is called from a background thread, which then calls
+/// [`Config::on_config_reload`][on_config_reload].
+/// Calling this more than once has no further effect.
+///
+/// This is available on Unix with the `signal` feature.
+///
+/// [configure_from_env_forced]: config/fn.configure_from_env_forced.html
+/// [on_config_reload]: config/struct.Config.html#structfield.on_config_reload
+pub fn install_sighup_reload() -> io::Result<()> {
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let result = Signals::new([SIGHUP]).and_then(|mut signals| {
+        thread::Builder::new()
+            .name("mightybadger-sighup".to_string())
+            .spawn(move || {
+                for _ in signals.forever() {
+                    config::configure_from_env_forced();
+                }
+            })
+    });
+    if let Err(e) = result {
+        INSTALLED.store(false, Ordering::SeqCst);
+        return Err(e);
+    }
+    Ok(())
+}
@@ -1,13 +1,273 @@
+//! System statistics attached to notices.
+//!
+//! The statistics are collected by a [`StatsBackend`][StatsBackend], which
+//! can be replaced with [`Config::stats_backend`][stats_backend].
+//! By default, the backend for the target platform is used:
+//! [`LinuxStatsBackend`][LinuxStatsBackend] reads `/proc`,
+//! [`MacosStatsBackend`][MacosStatsBackend] and
+//! [`WindowsStatsBackend`][WindowsStatsBackend] ask the system, and
+//! [`NullStatsBackend`][NullStatsBackend] is used on WebAssembly.
+//!
+//! [StatsBackend]: trait.StatsBackend.html
+//! [stats_backend]: ../config/struct.Config.html#structfield.stats_backend
+//! [LinuxStatsBackend]: struct.LinuxStatsBackend.html
+//! [MacosStatsBackend]: struct.MacosStatsBackend.html
+//! [WindowsStatsBackend]: struct.WindowsStatsBackend.html
+//! [NullStatsBackend]: struct.NullStatsBackend.html
+
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use crate::config;
 use crate::payload::{LoadInfo, MemoryInfo, Stats};
 
+/// Source of the memory and load statistics of the system.
+///
+/// Memory sizes are in megabytes.
+pub trait StatsBackend: fmt::Debug + Send + Sync {
+    /// Returns the memory usage, or `None` if it is unavailable.
+    fn memory_info(&self) -> Option<MemoryInfo>;
+    /// Returns the load averages, or `None` if they are unavailable.
+    fn load_info(&self) -> Option<LoadInfo>;
+}
+
+/// [`StatsBackend`][StatsBackend] reading `/proc/meminfo` and `/proc/loadavg`.
+///
+/// It works on Linux and returns `None` elsewhere.
+///
+/// [StatsBackend]: trait.StatsBackend.html
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinuxStatsBackend;
+
+impl StatsBackend for LinuxStatsBackend {
+    fn memory_info(&self) -> Option<MemoryInfo> {
+        get_mem()
+    }
+
+    fn load_info(&self) -> Option<LoadInfo> {
+        get_load()
+    }
+}
+
+/// [`StatsBackend`][StatsBackend] using `sysctl` and `getloadavg`.
+///
+/// Only `total` and `free` of the memory are known; pages kept as cache
+/// are not counted as free.
+///
+/// [StatsBackend]: trait.StatsBackend.html
+#[cfg(target_os = "macos")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MacosStatsBackend;
+
+#[cfg(target_os = "macos")]
+impl StatsBackend for MacosStatsBackend {
+    fn memory_info(&self) -> Option<MemoryInfo> {
+        let total = macos::sysctl_u64("hw.memsize\0")?;
+        let free = macos::sysctl_u64("vm.page_free_count\0")
+            .and_then(|pages| Some(pages * macos::sysctl_u64("hw.pagesize\0")?));
+        Some(MemoryInfo {
+            total: Some(total as f64 / 1048576.0),
+            free: free.map(|free| free as f64 / 1048576.0),
+            ..Default::default()
+        })
+    }
+
+    fn load_info(&self) -> Option<LoadInfo> {
+        let mut loadavg = [0.0; 3];
+        // SAFETY: `loadavg` has room for the 3 requested samples.
+        let n = unsafe { macos::getloadavg(loadavg.as_mut_ptr(), 3) };
+        if n != 3 {
+            return None;
+        }
+        Some(LoadInfo {
+            one: Some(loadavg[0]),
+            five: Some(loadavg[1]),
+            fifteen: Some(loadavg[2]),
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::mem;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::ptr;
+
+    extern "C" {
+        pub(super) fn getloadavg(loadavg: *mut f64, nelem: c_int) -> c_int;
+        fn sysctlbyname(
+            name: *const c_char,
+            oldp: *mut c_void,
+            oldlenp: *mut usize,
+            newp: *mut c_void,
+            newlen: usize,
+        ) -> c_int;
+    }
+
+    /// Reads an integer by a NUL-terminated `sysctl` name.
+    pub(super) fn sysctl_u64(name: &str) -> Option<u64> {
+        let mut value = 0u64;
+        let mut len = mem::size_of::<u64>();
+        // SAFETY: `name` is NUL-terminated and `value` has `len` bytes.
+        let ret = unsafe {
+            sysctlbyname(
+                name.as_ptr() as *const c_char,
+                &mut value as *mut u64 as *mut c_void,
+                &mut len,
+                ptr::null_mut(),
+                0,
+            )
+        };
+        // Some values are 32-bit; the lower bytes come first on both
+        // supported architectures.
+        if ret == 0 && (len == 4 || len == 8) {
+            Some(if len == 4 { value & 0xffff_ffff } else { value })
+        } else {
+            None
+        }
+    }
+}
+
+/// [`StatsBackend`][StatsBackend] using `GlobalMemoryStatusEx`.
+///
+/// Windows has no load average, so `load_info` returns `None`.
+/// The available memory, which includes the standby cache, is
+/// reported as `free` and `free_total`.
+///
+/// [StatsBackend]: trait.StatsBackend.html
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowsStatsBackend;
+
+#[cfg(windows)]
+impl StatsBackend for WindowsStatsBackend {
+    fn memory_info(&self) -> Option<MemoryInfo> {
+        let status = windows::global_memory_status()?;
+        let available = status.avail_phys as f64 / 1048576.0;
+        Some(MemoryInfo {
+            total: Some(status.total_phys as f64 / 1048576.0),
+            free: Some(available),
+            free_total: Some(available),
+            ..Default::default()
+        })
+    }
+
+    fn load_info(&self) -> Option<LoadInfo> {
+        None
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::mem;
+
+    /// `MEMORYSTATUSEX`.
+    #[repr(C)]
+    #[derive(Default)]
+    pub(super) struct MemoryStatusEx {
+        length: u32,
+        memory_load: u32,
+        pub(super) total_phys: u64,
+        pub(super) avail_phys: u64,
+        total_page_file: u64,
+        avail_page_file: u64,
+        total_virtual: u64,
+        avail_virtual: u64,
+        avail_extended_virtual: u64,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GlobalMemoryStatusEx(buffer: *mut MemoryStatusEx) -> i32;
+    }
+
+    pub(super) fn global_memory_status() -> Option<MemoryStatusEx> {
+        let mut status = MemoryStatusEx {
+            length: mem::size_of::<MemoryStatusEx>() as u32,
+            ..Default::default()
+        };
+        // SAFETY: `status` is a `MEMORYSTATUSEX` with its length set.
+        if unsafe { GlobalMemoryStatusEx(&mut status) } != 0 {
+            Some(status)
+        } else {
+            None
+        }
+    }
+}
+
+/// [`StatsBackend`][StatsBackend] which reports nothing.
+///
+/// [StatsBackend]: trait.StatsBackend.html
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullStatsBackend;
+
+impl StatsBackend for NullStatsBackend {
+    fn memory_info(&self) -> Option<MemoryInfo> {
+        None
+    }
+
+    fn load_info(&self) -> Option<LoadInfo> {
+        None
+    }
+}
+
+/// The backend used if `Config::stats_backend` is not set.
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_BACKEND: &dyn StatsBackend = &NullStatsBackend;
+#[cfg(target_os = "macos")]
+const DEFAULT_BACKEND: &dyn StatsBackend = &MacosStatsBackend;
+#[cfg(windows)]
+const DEFAULT_BACKEND: &dyn StatsBackend = &WindowsStatsBackend;
+#[cfg(not(any(target_arch = "wasm32", target_os = "macos", windows)))]
+const DEFAULT_BACKEND: &dyn StatsBackend = &LinuxStatsBackend;
+
+/// Calls `f` with the configured backend, or the default one.
+fn with_backend<R>(f: impl FnOnce(&dyn StatsBackend) -> R) -> R {
+    let backend = config::read_config().stats_backend.clone();
+    match backend {
+        Some(backend) => f(&*backend),
+        None => f(DEFAULT_BACKEND),
+    }
+}
+
 pub(crate) fn get_stats() -> Stats {
-    Stats {
-        mem: get_mem(),
-        load: get_load(),
+    with_backend(|backend| Stats {
+        mem: backend.memory_info(),
+        load: backend.load_info(),
+    })
+}
+
+/// Returns the fraction of memory in use, from `0.0` (all free) to `1.0` (fully utilized).
+///
+/// It is computed as `1.0 - free_total / total` from the
+/// [`StatsBackend`][StatsBackend], where `free_total` includes buffers and
+/// page cache. This is a best-effort estimate: it doesn't account for
+/// cgroup limits in containers, and `free_total` is unknown on macOS.
+///
+/// [StatsBackend]: trait.StatsBackend.html
+pub fn memory_pressure() -> Option<f64> {
+    pressure(&with_backend(|backend| backend.memory_info())?)
+}
+
+/// The part of `memory_pressure` that computes the ratio.
+fn pressure(meminfo: &MemoryInfo) -> Option<f64> {
+    let total = meminfo.total?;
+    let free_total = meminfo.free_total?;
+    if total <= 0.0 {
+        return None;
     }
+    Some((1.0 - free_total / total).clamp(0.0, 1.0))
+}
+
+/// Returns the system load average over the last minute.
+///
+/// It is read from the [`StatsBackend`][StatsBackend] and is not available
+/// on Windows.
+///
+/// [StatsBackend]: trait.StatsBackend.html
+pub fn load_average_1min() -> Option<f64> {
+    with_backend(|backend| backend.load_info())?.one
 }
 
 fn get_mem() -> Option<MemoryInfo> {
@@ -28,11 +288,9 @@ fn get_mem() -> Option<MemoryInfo> {
         };
         let key = line[..colon].trim();
         let value = line[colon + 1..].trim();
-        let kbvalue = if value.ends_with(" kB") {
-            value[..value.len() - 3].parse::<i64>().ok()
-        } else {
-            None
-        };
+        let kbvalue = value
+            .strip_suffix(" kB")
+            .and_then(|value| value.parse::<i64>().ok());
         let mbvalue = kbvalue.map(|kbvalue| kbvalue as f64 / 1024.0);
         match key {
             "MemTotal" => meminfo.total = mbvalue,
@@ -66,3 +324,25 @@ fn get_load() -> Option<LoadInfo> {
     loadinfo.fifteen = tokens.next().and_then(|token| token.parse::<f64>().ok());
     Some(loadinfo)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_pressure() {
+        let meminfo = |total, free_total| MemoryInfo {
+            total,
+            free_total,
+            ..Default::default()
+        };
+        assert_eq!(pressure(&meminfo(Some(1024.0), Some(256.0))), Some(0.75));
+        assert_eq!(pressure(&meminfo(Some(1024.0), Some(1024.0))), Some(0.0));
+        // Clamped, e.g. if the backend reports inconsistent numbers.
+        assert_eq!(pressure(&meminfo(Some(1024.0), Some(2048.0))), Some(0.0));
+        assert_eq!(pressure(&meminfo(Some(1024.0), Some(-1.0))), Some(1.0));
+        assert_eq!(pressure(&meminfo(Some(0.0), Some(0.0))), None);
+        assert_eq!(pressure(&meminfo(Some(1024.0), None)), None);
+        assert_eq!(pressure(&meminfo(None, Some(256.0))), None);
+    }
+}
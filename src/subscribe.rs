@@ -0,0 +1,54 @@
+//! Delivery of assembled notices to in-process subscribers.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+
+use crate::payload::Payload;
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<Sender<Arc<Payload>>>> = Mutex::new(Vec::new());
+}
+
+/// Returns a receiver of every notice assembled from now on.
+///
+/// Notices are delivered before they are sent, whether or not sending
+/// succeeds. This allows custom processing alongside Honeybadger, e.g.
+/// routing critical errors to a pager. Notices skipped by the configuration
+/// (e.g. because `report_data` is off) are not delivered.
+///
+/// Dropping the receiver unsubscribes it.
+///
+/// ## Example
+///
+/// ```
+/// use std::thread;
+///
+/// let notices = mightybadger::subscribe();
+/// thread::spawn(move || {
+///     for payload in notices {
+///         if payload.error.class == "OutOfMemory" {
+///             // page someone
+///         }
+///     }
+/// });
+/// ```
+pub fn subscribe() -> Receiver<Arc<Payload>> {
+    let (tx, rx) = mpsc::channel();
+    SUBSCRIBERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(tx);
+    rx
+}
+
+/// Sends the payload to all subscribers, dropping those that are gone.
+pub(crate) fn publish(payload: &Payload) {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap_or_else(|e| e.into_inner());
+    if subscribers.is_empty() {
+        return;
+    }
+    let payload = Arc::new(payload.clone());
+    subscribers.retain(|subscriber| subscriber.send(payload.clone()).is_ok());
+}
@@ -0,0 +1,154 @@
+//! Helpers for testing code that reports errors.
+//!
+//! This module is available with the `test-server` feature.
+
+use std::cell::RefCell;
+use std::sync::Mutex;
+
+use scoped_tls::scoped_thread_local;
+use uuid::Uuid;
+
+use crate::client::HttpClient;
+use crate::config::Config;
+use crate::payload::Payload;
+use crate::HoneybadgerError;
+
+/// [`HttpClient`][HttpClient] that records payloads instead of sending them.
+///
+/// [HttpClient]: ../client/trait.HttpClient.html
+///
+/// ## Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use mightybadger::testing::FakeReporter;
+///
+/// let reporter = Arc::new(FakeReporter::new());
+/// mightybadger::configure(|config| {
+///     config.api_key = Some("abcdef".to_string());
+///     config.http_client = Some(reporter.clone());
+/// });
+///
+/// let error = "x".parse::<i32>().unwrap_err();
+/// mightybadger::notify_std_error(&error);
+/// assert_eq!(reporter.payloads().len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct FakeReporter {
+    payloads: Mutex<Vec<Payload>>,
+}
+
+impl FakeReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the payloads recorded so far.
+    pub fn payloads(&self) -> Vec<Payload> {
+        self.lock().clone()
+    }
+
+    /// Forgets the payloads recorded so far.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Payload>> {
+        match self.payloads.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl HttpClient for FakeReporter {
+    fn report(&self, payload: &Payload, _config: &Config) -> Result<Uuid, HoneybadgerError> {
+        self.lock().push(payload.clone());
+        Ok(payload.error.token.unwrap_or_else(Uuid::nil))
+    }
+}
+
+scoped_thread_local!(
+    static INTERCEPTED: RefCell<Vec<Payload>>
+);
+
+/// Runs `f` and returns its result together with the payloads that would
+/// have been sent from the current thread in the meantime.
+///
+/// Intercepted notices are neither sent nor queued for `async_send`.
+/// Interception is per thread, so tests running in parallel don't see each
+/// other's notices; notices from threads spawned by `f` are sent as usual.
+/// The configuration still has to allow reporting (`api_key` set and
+/// `report_data` not disabled).
+///
+/// ## Example
+///
+/// ```
+/// use mightybadger::testing::intercept;
+///
+/// mightybadger::configure(|config| {
+///     config.api_key = Some("abcdef".to_string());
+/// });
+///
+/// let ((), payloads) = intercept(|| {
+///     let error = "x".parse::<i32>().unwrap_err();
+///     mightybadger::notify_std_error(&error);
+/// });
+/// assert_eq!(payloads.len(), 1);
+/// ```
+pub fn intercept<R, F>(f: F) -> (R, Vec<Payload>)
+where
+    F: FnOnce() -> R,
+{
+    let intercepted = RefCell::new(Vec::new());
+    let result = INTERCEPTED.set(&intercepted, f);
+    (result, intercepted.into_inner())
+}
+
+/// Records the payload if the current thread is in [`intercept`](fn.intercept.html).
+pub(crate) fn try_intercept(payload: &Payload) -> bool {
+    if INTERCEPTED.is_set() {
+        INTERCEPTED.with(|intercepted| intercepted.borrow_mut().push(payload.clone()));
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_reporter() {
+        let reporter = FakeReporter::new();
+        let mut payload = Payload::default();
+        payload.error.class = "TestError".to_string();
+        let id = reporter.report(&payload, &Config::default()).unwrap();
+        assert_eq!(id, Uuid::nil());
+        let payloads = reporter.payloads();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].error.class, "TestError");
+        reporter.clear();
+        assert!(reporter.payloads().is_empty());
+    }
+
+    #[test]
+    fn test_intercept() {
+        let mut payload = Payload::default();
+        assert!(!try_intercept(&payload));
+        let (outer, payloads) = intercept(|| {
+            payload.error.class = "Outer".to_string();
+            try_intercept(&payload);
+            let (_, inner) = intercept(|| {
+                payload.error.class = "Inner".to_string();
+                try_intercept(&payload)
+            });
+            assert_eq!(inner.len(), 1);
+            42
+        });
+        assert_eq!(outer, 42);
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].error.class, "Outer");
+    }
+}
@@ -0,0 +1,42 @@
+//! Tokio runtime metrics attached to notices.
+
+use serde_json::json;
+use tokio::runtime::Handle;
+
+/// Returns the metrics of the current Tokio runtime,
+/// or `None` outside of a runtime.
+///
+/// The blocking pool metrics are only included when built with
+/// `RUSTFLAGS="--cfg tokio_unstable"`.
+pub(crate) fn details() -> Option<serde_json::Value> {
+    let handle = Handle::try_current().ok()?;
+    let metrics = handle.metrics();
+    #[allow(unused_mut)]
+    let mut details = json!({
+        "workers": metrics.num_workers(),
+        "alive_tasks": metrics.num_alive_tasks(),
+        "global_queue_depth": metrics.global_queue_depth(),
+    });
+    #[cfg(tokio_unstable)]
+    {
+        details["blocking_threads"] = json!(metrics.num_blocking_threads());
+        details["idle_blocking_threads"] = json!(metrics.num_idle_blocking_threads());
+        details["blocking_queue_depth"] = json!(metrics.blocking_queue_depth());
+    }
+    Some(details)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_details() {
+        assert_eq!(details(), None);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let details = rt.block_on(async { details() }).unwrap();
+        assert_eq!(details["workers"], 1);
+    }
+}
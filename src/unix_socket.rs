@@ -0,0 +1,231 @@
+//! Minimal HTTP/1.1 client over Unix domain sockets.
+//!
+//! Used when `config.connection.socket_path` is set, e.g. to talk to a sidecar proxy.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+use attohttpc::StatusCode;
+
+/// Time limit of each read and write if `connection.timeout` is not set.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends a POST request and returns the status, the `Retry-After` header,
+/// and the body of the response.
+///
+/// `timeout` limits each read from and write to the socket.
+/// The body is read up to `max_body_bytes` plus one byte, so that the caller
+/// can tell whether the limit is exceeded.
+pub(crate) fn post(
+    socket_path: &Path,
+    host: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    timeout: Option<Duration>,
+    max_body_bytes: usize,
+) -> io::Result<(StatusCode, Option<String>, Vec<u8>)> {
+    for field in [host, path]
+        .iter()
+        .chain(headers.iter().flat_map(|(name, value)| [name, value]))
+    {
+        if field.contains(['\r', '\n']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line break in the request line or a header",
+            ));
+        }
+    }
+    let mut stream = UnixStream::connect(socket_path)?;
+    let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        path,
+        host,
+        body.len()
+    );
+    for &(name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    read_response(BufReader::new(stream), max_body_bytes)
+}
+
+fn read_response<R: BufRead>(
+    mut reader: R,
+    max_body_bytes: usize,
+) -> io::Result<(StatusCode, Option<String>, Vec<u8>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    // HTTP/1.1 201 Created
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .and_then(|status| StatusCode::from_u16(status).ok())
+        .ok_or_else(|| invalid_data("malformed status line"))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut retry_after = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("unexpected end of headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(colon) = header.find(':') {
+            let name = header[..colon].trim();
+            let value = header[colon + 1..].trim();
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.parse::<usize>().ok();
+            } else if name.eq_ignore_ascii_case("Transfer-Encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            } else if name.eq_ignore_ascii_case("Retry-After") {
+                retry_after = Some(value.to_string());
+            }
+        }
+    }
+
+    let limit = max_body_bytes.saturating_add(1);
+    let mut body = Vec::new();
+    if chunked {
+        while body.len() < limit {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = line.trim().split(';').next().unwrap_or("");
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| invalid_data("malformed chunk size"))?;
+            if size == 0 {
+                break;
+            }
+            let start = body.len();
+            body.resize(start + size.min(limit - start), 0);
+            reader.read_exact(&mut body[start..])?;
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+    } else if let Some(content_length) = content_length {
+        body.resize(content_length.min(limit), 0);
+        reader.read_exact(&mut body)?;
+    } else {
+        reader.take(limit as u64).read_to_end(&mut body)?;
+    }
+    Ok((status, retry_after, body))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    #[test]
+    fn test_post() {
+        let socket_path =
+            env::temp_dir().join(format!("mightybadger-test-{}.sock", std::process::id()));
+        fs::remove_file(&socket_path).ok();
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.strip_prefix("Content-Length: ") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let mut stream = reader.into_inner();
+            stream
+                .write_all(b"HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n{\"a\"\r\n3\r\n:1}\r\n0\r\n\r\n")
+                .unwrap();
+            (request, body)
+        });
+
+        let (status, retry_after, body) = post(
+            &socket_path,
+            "api.honeybadger.io",
+            "/v1/notices",
+            &[("X-API-Key", "abcdef")],
+            b"{}",
+            None,
+            4096,
+        )
+        .unwrap();
+        let (request, request_body) = server.join().unwrap();
+        fs::remove_file(&socket_path).ok();
+
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(retry_after, None);
+        assert_eq!(body, b"{\"a\":1}");
+        assert!(request.starts_with("POST /v1/notices HTTP/1.1\r\n"));
+        assert!(request.contains("Host: api.honeybadger.io\r\n"));
+        assert!(request.contains("X-API-Key: abcdef\r\n"));
+        assert_eq!(request_body, b"{}");
+    }
+
+    #[test]
+    fn test_post_rejects_line_breaks() {
+        let socket_path = env::temp_dir().join("mightybadger-test-unused.sock");
+        let error = post(
+            &socket_path,
+            "api.honeybadger.io",
+            "/v1/notices",
+            &[("X-API-Key", "abc\r\nX-Injected: 1")],
+            b"{}",
+            None,
+            4096,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_read_response_limit() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 1000000000\r\n\r\n0123456789";
+        let (_, _, body) = read_response(&response[..], 4).unwrap();
+        assert_eq!(body, b"01234");
+
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n012\r\n3\r\n345\r\n3\r\n678\r\n0\r\n\r\n";
+        let (_, _, body) = read_response(&response[..], 4).unwrap();
+        assert_eq!(body, b"01234");
+
+        let response = b"HTTP/1.1 200 OK\r\n\r\n0123456789";
+        let (_, _, body) = read_response(&response[..], 4).unwrap();
+        assert_eq!(body, b"01234");
+
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n012";
+        let (_, _, body) = read_response(&response[..], usize::MAX).unwrap();
+        assert_eq!(body, b"012");
+
+        let response = b"HTTP/1.1 503 Service Unavailable\r\nretry-after: 5\r\n\r\n";
+        let (status, retry_after, _) = read_response(&response[..], 4).unwrap();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(retry_after.as_deref(), Some("5"));
+    }
+}
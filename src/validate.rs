@@ -0,0 +1,73 @@
+//! Validation of payloads against the notice JSON schema, for tests.
+
+use std::thread;
+
+use jsonschema::Validator;
+use lazy_static::lazy_static;
+
+use crate::payload::Payload;
+
+lazy_static! {
+    static ref VALIDATOR: Validator = {
+        let schema = serde_json::from_str(include_str!("notice.schema.json"))
+            .expect("invalid notice schema");
+        jsonschema::validator_for(&schema).expect("invalid notice schema")
+    };
+}
+
+/// Returns the schema violations in the payload, if any.
+pub(crate) fn errors(payload: &Payload) -> Vec<String> {
+    let value = match serde_json::to_value(payload) {
+        Ok(value) => value,
+        Err(e) => return vec![e.to_string()],
+    };
+    VALIDATOR
+        .iter_errors(&value)
+        .map(|e| format!("{} at {}", e, e.instance_path))
+        .collect()
+}
+
+/// Checks that the payload matches the notice schema.
+///
+/// A mismatch panics in debug builds, unless the thread is already panicking
+/// (e.g. in the panic hook), and is printed to stderr otherwise.
+pub(crate) fn validate(payload: &Payload) {
+    let errors = errors(payload);
+    if errors.is_empty() {
+        return;
+    }
+    let message = format!(
+        "notice payload doesn't match the schema:\n{}",
+        errors.join("\n")
+    );
+    if cfg!(debug_assertions) && !thread::panicking() {
+        panic!("{}", message);
+    }
+    eprintln!("** [Honeybadger] {}", message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::BacktraceEntry;
+
+    #[test]
+    fn test_validate() {
+        let mut payload = Payload::default();
+        payload.error.class = "std::io::Error".to_string();
+        payload.error.backtrace = Some(vec![BacktraceEntry {
+            number: Some("12".to_string()),
+            file: Some("src/main.rs".to_string()),
+            method: "app::main".to_string(),
+            source: None,
+            source_url: None,
+            noise: false,
+        }]);
+        assert_eq!(errors(&payload), Vec::<String>::new());
+
+        payload.error.backtrace.as_mut().unwrap()[0].number = Some("twelve".to_string());
+        let errors = errors(&payload);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].ends_with("at /error/backtrace/0/number"));
+    }
+}
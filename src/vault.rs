@@ -0,0 +1,102 @@
+//! Configuration from HashiCorp Vault.
+//!
+//! This module is available with the `vault` feature.
+
+use attohttpc::StatusCode;
+use failure::{Backtrace, Fail};
+
+use crate::config::configure;
+
+/// Error occurred during [`configure_from_vault`][configure_from_vault].
+///
+/// [configure_from_vault]: fn.configure_from_vault.html
+#[derive(Debug, Fail)]
+pub enum VaultError {
+    #[fail(display = "could not fetch the secret")]
+    FetchFailed(#[cause] attohttpc::Error, Backtrace),
+    #[fail(display = "Vault responded with status {}", _0)]
+    UnexpectedStatus(StatusCode, Backtrace),
+    #[fail(display = "could not parse the secret")]
+    ParseFailed(#[cause] serde_json::Error, Backtrace),
+    #[fail(display = "the secret has no string field `api_key`")]
+    NoApiKey(Backtrace),
+}
+
+/// Sets the API key from a secret stored in HashiCorp Vault.
+///
+/// Reads the secret at `path` (e.g. `secret/data/honeybadger` for a KV v2
+/// engine mounted at `secret`, or `secret/honeybadger` for KV v1) from the
+/// Vault server at `addr`, and takes the `api_key` field of it.
+/// `token` is only used for this request and is not kept.
+///
+/// ## Example
+///
+/// ```no_run
+/// # fn example() -> Result<(), mightybadger::VaultError> {
+/// let token = std::env::var("VAULT_TOKEN").unwrap_or_default();
+/// mightybadger::configure_from_vault(
+///     "https://vault.example.com:8200",
+///     &token,
+///     "secret/data/honeybadger",
+/// )?;
+/// mightybadger::setup();
+/// # Ok(())
+/// # }
+/// ```
+pub fn configure_from_vault(addr: &str, token: &str, path: &str) -> Result<(), VaultError> {
+    let url = format!(
+        "{}/v1/{}",
+        addr.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+    let resp = attohttpc::get(&url)
+        .header("X-Vault-Token", token)
+        .send()
+        .map_err(|e| VaultError::FetchFailed(e, Backtrace::new()))?;
+    if !resp.status().is_success() {
+        return Err(VaultError::UnexpectedStatus(
+            resp.status(),
+            Backtrace::new(),
+        ));
+    }
+    let body = resp
+        .bytes()
+        .map_err(|e| VaultError::FetchFailed(e, Backtrace::new()))?;
+    let api_key = extract_api_key(&body)?;
+    configure(|config| {
+        config.api_key = Some(api_key);
+    });
+    Ok(())
+}
+
+/// Takes `api_key` from a KV v1 (`data.api_key`) or v2 (`data.data.api_key`) response.
+fn extract_api_key(body: &[u8]) -> Result<String, VaultError> {
+    let body = serde_json::from_slice::<serde_json::Value>(body)
+        .map_err(|e| VaultError::ParseFailed(e, Backtrace::new()))?;
+    let data = &body["data"];
+    let data = if data["data"].is_object() {
+        &data["data"]
+    } else {
+        data
+    };
+    data["api_key"]
+        .as_str()
+        .map(|api_key| api_key.to_string())
+        .ok_or_else(|| VaultError::NoApiKey(Backtrace::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_api_key() {
+        let v1 = br#"{"data": {"api_key": "abc"}, "lease_duration": 2764800}"#;
+        assert_eq!(extract_api_key(v1).unwrap(), "abc");
+        let v2 = br#"{"data": {"data": {"api_key": "def"}, "metadata": {"version": 1}}}"#;
+        assert_eq!(extract_api_key(v2).unwrap(), "def");
+        let missing = br#"{"data": {"data": {"key": "ghi"}}}"#;
+        assert!(extract_api_key(missing).is_err());
+        assert!(extract_api_key(b"not json").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Background sender used when `config.async_send` is enabled.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use failure::{Backtrace, Fail};
+use lazy_static::lazy_static;
+
+use crate::config::{self, ConnectionConfig};
+use crate::payload::Payload;
+
+lazy_static! {
+    /// Sending end of the queue. The worker thread is spawned on first use.
+    static ref QUEUE: Mutex<Queue> = Mutex::new(Queue::Idle);
+    /// The number of notices queued but not yet sent.
+    static ref PENDING: (Mutex<usize>, Condvar) = (Mutex::new(0), Condvar::new());
+}
+
+enum Queue {
+    /// No notice has been queued yet.
+    Idle,
+    Running(Sender<Job>),
+    /// Stopped by `graceful_shutdown`.
+    ShutDown,
+}
+
+/// A queued notice.
+struct Job {
+    payload: Payload,
+    /// Overrides set by `notify_to`.
+    connection: Option<ConnectionConfig>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+pub(crate) fn enqueue(payload: Payload, connection: Option<ConnectionConfig>) {
+    let pending = {
+        let mut pending = lock(&PENDING.0);
+        *pending += 1;
+        *pending
+    };
+    log_debug!(
+        "queued notice {} ({} pending)",
+        payload
+            .error
+            .token
+            .map(|u| u.to_string())
+            .unwrap_or_else(|| "nil".to_string()),
+        pending
+    );
+    let mut queue = lock(&QUEUE);
+    if let Queue::Idle = *queue {
+        *queue = Queue::Running(spawn());
+    }
+    let sender = match *queue {
+        Queue::Running(ref sender) => sender,
+        _ => {
+            eprintln!(
+                "** [Honeybadger] Shut down, dropping notice id={}",
+                payload
+                    .error
+                    .token
+                    .map(|u| u.to_string())
+                    .unwrap_or_else(|| "nil".to_string())
+            );
+            finish_one();
+            return;
+        }
+    };
+    if sender
+        .send(Job {
+            payload,
+            connection,
+        })
+        .is_err()
+    {
+        *queue = Queue::Idle;
+        finish_one();
+    }
+}
+
+/// Spawns `connection.max_connections` worker threads sharing a queue.
+///
+/// The pool is not resized if the setting changes afterwards.
+fn spawn() -> Sender<Job> {
+    let (tx, rx) = mpsc::channel::<Job>();
+    let rx = Arc::new(Mutex::new(rx));
+    let max_connections = config::read_config()
+        .connection
+        .max_connections
+        .unwrap_or(1)
+        .max(1);
+    for i in 0..max_connections {
+        let rx = rx.clone();
+        let name = if max_connections == 1 {
+            "mightybadger-worker".to_string()
+        } else {
+            format!("mightybadger-worker-{}", i)
+        };
+        thread::Builder::new()
+            .name(name)
+            .spawn(move || work(&rx))
+            .expect("Could not spawn the mightybadger worker thread.");
+    }
+    tx
+}
+
+/// Sends queued notices until the queue is closed.
+fn work(rx: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is released before sending, so that other workers can
+        // take the next job meanwhile.
+        let job = match lock(rx).recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let Job {
+            mut payload,
+            connection,
+        } = job;
+        catch_unwind(AssertUnwindSafe(|| {
+            // Unlike the server info, the context is not refreshed: the
+            // worker thread has none, and the one captured when the notice
+            // was assembled is the right one.
+            payload.refresh_server_info();
+            let iddisp = payload
+                .error
+                .token
+                .map(|u| u.to_string())
+                .unwrap_or_else(|| "nil".to_string());
+            // Not locked while sending, which may take long with retries.
+            let config = config::read_config().clone();
+            let result = if let Some(ref connection) = connection {
+                crate::send(&payload, &config.with_connection(connection))
+            } else {
+                crate::send(&payload, &config)
+            };
+            crate::log_report(&result, &iddisp);
+        }))
+        .ok();
+        finish_one();
+    }
+}
+
+fn finish_one() {
+    let (ref pending, ref condvar) = *PENDING;
+    let mut pending = lock(pending);
+    *pending -= 1;
+    if *pending == 0 {
+        condvar.notify_all();
+    }
+}
+
+/// Blocks until all notices queued by `async_send` have been sent.
+///
+/// Returns immediately if `async_send` is disabled.
+pub fn flush() {
+    let (ref pending, ref condvar) = *PENDING;
+    let mut pending = lock(pending);
+    while *pending > 0 {
+        pending = match condvar.wait(pending) {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+    }
+}
+
+/// Error returned by [`graceful_shutdown`][graceful_shutdown].
+///
+/// [graceful_shutdown]: fn.graceful_shutdown.html
+#[derive(Debug, Fail)]
+pub enum ShutdownError {
+    #[fail(display = "timed out with {} notices pending", _0)]
+    Timeout(usize, Backtrace),
+}
+
+/// Stops accepting notices for `async_send` and waits up to `timeout`
+/// for the queued ones to be sent.
+///
+/// Notices queued afterwards are dropped. Notices sent synchronously
+/// (with `async_send` disabled) are not affected.
+///
+/// This is meant to be called when the process is asked to terminate,
+/// e.g. in a `ctrlc` handler, where destructors such as
+/// [`FlushGuard`][FlushGuard] don't run.
+///
+/// [FlushGuard]: struct.FlushGuard.html
+///
+/// ## Example
+///
+/// ```ignore
+/// ctrlc::set_handler(|| {
+///     if let Err(e) = mightybadger::graceful_shutdown(Duration::from_secs(5)) {
+///         eprintln!("{}", e);
+///     }
+///     std::process::exit(130);
+/// })?;
+/// ```
+pub fn graceful_shutdown(timeout: Duration) -> Result<(), ShutdownError> {
+    // The worker exits after sending the notices already queued.
+    *lock(&QUEUE) = Queue::ShutDown;
+    let deadline = Instant::now() + timeout;
+    let (ref pending, ref condvar) = *PENDING;
+    let mut pending = lock(pending);
+    while *pending > 0 {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(ShutdownError::Timeout(*pending, Backtrace::new()));
+        }
+        pending = match condvar.wait_timeout(pending, deadline - now) {
+            Ok((guard, _)) => guard,
+            Err(poisoned) => poisoned.into_inner().0,
+        };
+    }
+    Ok(())
+}
+
+/// Flushes queued notices when dropped.
+///
+/// Returned by [`flush_on_exit`][flush_on_exit].
+///
+/// [flush_on_exit]: fn.flush_on_exit.html
+#[derive(Debug)]
+#[must_use = "notices are flushed when the guard is dropped"]
+pub struct FlushGuard {
+    _private: (),
+}
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        flush();
+    }
+}
+
+/// Returns a guard that [flushes][flush] queued notices when dropped.
+///
+/// Keep it alive for the whole of `main`, so that notices queued by
+/// `async_send` (including the one from a panic unwinding out of `main`)
+/// are sent before the process exits.
+///
+/// `std::process::exit` doesn't run destructors; call [`flush`][flush]
+/// explicitly before calling it.
+///
+/// [flush]: fn.flush.html
+///
+/// ## Example
+///
+/// ```
+/// mightybadger::setup();
+/// let _flush_guard = mightybadger::flush_on_exit();
+///
+/// // ...
+/// ```
+pub fn flush_on_exit() -> FlushGuard {
+    FlushGuard { _private: () }
+}
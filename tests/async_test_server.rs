@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use mightybadger_test_server::testing::{drain_and_assert_class, drain_and_assert_count};
+use mightybadger_test_server::TestServer;
+
+#[tokio::test]
+async fn test_drain_and_assert() {
+    let mut server = TestServer::new();
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+        // Sending synchronously would block the runtime serving the notices.
+        config.async_send = Some(true);
+    });
+
+    let error = "x".parse::<i32>().unwrap_err();
+    mightybadger::notify_std_error(&error);
+    mightybadger::notify_std_error(&error);
+    drain_and_assert_count(&server, 2, Duration::from_secs(10)).await;
+    drain_and_assert_class(&server, "std::num::ParseIntError", Duration::from_secs(10)).await;
+
+    let result = tokio::spawn(async move {
+        drain_and_assert_count(&server, 3, Duration::from_millis(50)).await;
+        server.shutdown().await;
+    })
+    .await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,29 @@
+use std::env;
+use std::sync::Arc;
+
+use mightybadger::testing::FakeReporter;
+
+#[test]
+fn test_backtrace_without_env() {
+    env::remove_var("RUST_BACKTRACE");
+    env::remove_var("RUST_FAILURE_BACKTRACE");
+    mightybadger::enable_backtrace();
+    assert_eq!(env::var("RUST_BACKTRACE").unwrap(), "1");
+
+    let client = Arc::new(FakeReporter::new());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(http_client);
+    });
+    // `ParseIntError` carries no backtrace.
+    let error = "x".parse::<i32>().unwrap_err();
+    mightybadger::notify_std_error(&error);
+
+    let payloads = client.payloads();
+    assert_eq!(payloads.len(), 1);
+    let backtrace = payloads[0].error.backtrace.as_ref().unwrap();
+    assert!(backtrace
+        .iter()
+        .any(|frame| frame.method.contains("test_backtrace_without_env")));
+}
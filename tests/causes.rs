@@ -0,0 +1,52 @@
+// `failure_derive` expands to impls nested in anonymous consts.
+#![allow(non_local_definitions)]
+
+use failure::{Backtrace, Fail};
+use mightybadger_test_server::sync::TestServer;
+
+#[derive(Debug, Fail)]
+#[fail(display = "file not found")]
+struct NotFound {
+    backtrace: Backtrace,
+}
+
+#[inline(never)]
+fn read_settings() -> Result<(), NotFound> {
+    Err(NotFound {
+        backtrace: Backtrace::new(),
+    })
+}
+
+#[test]
+fn test_cause_chain() {
+    mightybadger::enable_backtrace();
+    let server = TestServer::new();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(server.addr().port());
+    });
+
+    let error = read_settings()
+        .unwrap_err()
+        .context("could not load settings")
+        .context("startup failed");
+    mightybadger::notify(&error);
+
+    let data = server.data().read().unwrap();
+    assert_eq!(data.errors.len(), 1);
+    let error = &data.errors[0].error;
+    error.assert_cause_chain(&[
+        "startup failed",
+        "could not load settings",
+        "file not found",
+    ]);
+    assert_eq!(
+        error.cause_messages(),
+        ["could not load settings", "file not found"]
+    );
+    assert!(error.has_frame("test_cause_chain"));
+    let innermost = error.causes.last().unwrap();
+    assert!(innermost.has_frame("read_settings"), "{:#?}", innermost);
+}
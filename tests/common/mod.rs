@@ -0,0 +1,28 @@
+//! Fixtures shared by the integration tests.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use mightybadger::config::Config;
+use mightybadger::{HoneybadgerError, HttpClient, Payload};
+use uuid::Uuid;
+
+/// Takes 200ms per notice, counting the concurrent and completed sends.
+#[derive(Debug, Default)]
+pub struct SlowClient {
+    pub active: AtomicUsize,
+    pub max_active: AtomicUsize,
+    pub sent: AtomicUsize,
+}
+
+impl HttpClient for SlowClient {
+    fn report(&self, payload: &Payload, _config: &Config) -> Result<Uuid, HoneybadgerError> {
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_active.fetch_max(active, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(200));
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        self.sent.fetch_add(1, Ordering::SeqCst);
+        Ok(payload.error.token.unwrap_or_else(Uuid::nil))
+    }
+}
@@ -0,0 +1,39 @@
+use std::env;
+
+use mightybadger::ConfigValidationError;
+
+#[test]
+fn test_configure_from_env_with_validation() {
+    env::set_var("HONEYBADGER_ENV", "production");
+    env::set_var("HONEYBADGER_CONNECTION_PORT", "abc");
+    env::set_var("HONEYBADGER_ASYNC_SEND", "yes");
+    env::set_var("HONEYBADGER_CONNECTION_MAX_CONNECTIONS", "4");
+
+    let result = mightybadger::configure_from_env_with_validation();
+    assert!(matches!(
+        result,
+        Err(ConfigValidationError::MissingApiKey(_))
+    ));
+    assert_eq!(mightybadger::config::read_config().env, None);
+
+    env::set_var("HONEYBADGER_API_KEY", "abcd1234");
+    let warnings = mightybadger::configure_from_env_with_validation().unwrap();
+    let names = warnings
+        .iter()
+        .map(|warning| warning.env_name)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["HONEYBADGER_ASYNC_SEND", "HONEYBADGER_CONNECTION_PORT"]
+    );
+    assert_eq!(warnings[1].value, "abc");
+    assert_eq!(
+        warnings[1].to_string(),
+        "ignoring HONEYBADGER_CONNECTION_PORT=\"abc\": invalid digit found in string"
+    );
+
+    let config = mightybadger::config::read_config();
+    assert_eq!(config.env.as_deref(), Some("production"));
+    assert_eq!(config.connection.port, None);
+    assert_eq!(config.connection.max_connections, Some(4));
+}
@@ -0,0 +1,60 @@
+use mightybadger::config::ConnectionConfig;
+use mightybadger::ReportOutcome;
+use mightybadger_test_server::sync::{TestServer, TestServerConfig};
+use std::net::TcpListener;
+
+#[test]
+fn test_additional_destinations() {
+    let server1 = TestServer::new();
+    let server2 = TestServer::with_config(TestServerConfig {
+        require_api_key: Some("central".to_owned()),
+        ..Default::default()
+    });
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(server1.addr().port());
+        config.additional_destinations = vec![(
+            "central".to_owned(),
+            ConnectionConfig {
+                port: Some(server2.addr().port()),
+                ..Default::default()
+            },
+        )];
+    });
+    let error = "x".parse::<i32>().unwrap_err();
+    mightybadger::notify_std_error(&error);
+    {
+        let data1 = server1.data().read().unwrap();
+        let data2 = server2.data().read().unwrap();
+        assert_eq!(data1.errors.len(), 1);
+        assert_eq!(data2.errors.len(), 1);
+        assert_eq!(data1.errors[0].error.token, data2.errors[0].error.token);
+    }
+    assert_eq!(
+        mightybadger::last_destination_statuses(),
+        vec![ReportOutcome::Success, ReportOutcome::Success]
+    );
+
+    // A failure to the primary destination doesn't prevent the others.
+    let closed_port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    mightybadger::configure(|config| {
+        config.connection.port = Some(closed_port);
+    });
+    mightybadger::notify_std_error(&error);
+    assert_eq!(server1.data().read().unwrap().errors.len(), 1);
+    assert_eq!(server2.data().read().unwrap().errors.len(), 2);
+    assert_eq!(
+        mightybadger::last_report_status(),
+        Some(ReportOutcome::Success)
+    );
+    assert_eq!(
+        mightybadger::last_destination_statuses(),
+        vec![ReportOutcome::Failed, ReportOutcome::Success]
+    );
+}
@@ -0,0 +1,40 @@
+#![cfg(all(feature = "anyhow", feature = "eyre"))]
+
+use anyhow::Context;
+use mightybadger_test_server::sync::TestServer;
+use std::env;
+
+#[test]
+fn test_notify_anyhow_and_eyre() {
+    let server = TestServer::new();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(server.addr().port());
+    });
+    let notices = mightybadger::subscribe();
+
+    env::set_var("RUST_LIB_BACKTRACE", "1");
+    fn load_config() -> anyhow::Result<i32> {
+        let port = "x".parse::<i32>().context("invalid port")?;
+        Ok(port)
+    }
+    let error = load_config().unwrap_err();
+    mightybadger::notify_anyhow(&error);
+    let payload = notices.try_recv().unwrap();
+    assert_eq!(payload.error.message, "invalid port");
+    assert_eq!(payload.error.causes.len(), 1);
+    assert_eq!(payload.error.causes[0].class, "std::num::ParseIntError");
+    let backtrace = payload.error.backtrace.as_ref().unwrap();
+    assert!(backtrace[0].method.ends_with("::load_config"));
+
+    let error = eyre::Report::new("x".parse::<i32>().unwrap_err()).wrap_err("invalid port");
+    mightybadger::notify_eyre(&error);
+    let payload = notices.try_recv().unwrap();
+    assert_eq!(payload.error.message, "invalid port");
+    assert_eq!(payload.error.causes.len(), 1);
+    assert_eq!(payload.error.causes[0].class, "std::num::ParseIntError");
+
+    assert_eq!(server.data().read().unwrap().errors.len(), 2);
+}
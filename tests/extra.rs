@@ -0,0 +1,27 @@
+use mightybadger_test_server::sync::TestServer;
+
+#[test]
+fn test_notify_with_extra() {
+    let server = TestServer::new();
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+    });
+    let error = failure::err_msg("job failed");
+    mightybadger::notify_with_extra(
+        error.as_fail(),
+        serde_json::json!({ "job_id": 42, "password": "secret" }),
+    );
+    mightybadger::notify_with_extra(error.as_fail(), serde_json::json!([1, 2, 3]));
+    {
+        let data = server.data().read().unwrap();
+        assert_eq!(data.errors.len(), 2);
+        let context = &data.errors[0].request.as_ref().unwrap().context;
+        assert_eq!(context["job_id"], 42);
+        assert_eq!(context["password"], "[FILTERED]");
+        assert!(data.errors[1].request.is_none());
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+mod common;
+
+use common::SlowClient;
+
+#[test]
+fn test_max_concurrent_sends() {
+    let client = Arc::new(SlowClient::default());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.max_concurrent_sends = Some(2);
+        config.http_client = Some(http_client);
+    });
+    let threads = 32;
+    let barrier = Arc::new(Barrier::new(threads));
+    let handles = (0..threads)
+        .map(|_| {
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let error = "x".parse::<i32>().unwrap_err();
+                barrier.wait();
+                mightybadger::notify_std_error(&error);
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let sent = client.sent.load(Ordering::SeqCst);
+    let dropped = mightybadger::dropped_notice_count();
+    assert_eq!(sent + dropped, threads);
+    assert!(dropped > 0, "{}", dropped);
+    let max_active = client.max_active.load(Ordering::SeqCst);
+    assert!((1..=2).contains(&max_active), "{}", max_active);
+
+    // Slots are released after sending.
+    mightybadger::notify_std_error(&"y".parse::<i32>().unwrap_err());
+    assert_eq!(client.sent.load(Ordering::SeqCst), sent + 1);
+    assert_eq!(mightybadger::dropped_notice_count(), dropped);
+}
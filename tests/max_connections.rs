@@ -0,0 +1,26 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+mod common;
+
+use common::SlowClient;
+
+#[test]
+fn test_max_connections() {
+    let client = Arc::new(SlowClient::default());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.async_send = Some(true);
+        config.connection.max_connections = Some(3);
+        config.http_client = Some(http_client);
+    });
+    let error = "x".parse::<i32>().unwrap_err();
+    for _ in 0..6 {
+        mightybadger::notify_std_error(&error);
+    }
+    mightybadger::flush();
+    assert_eq!(client.sent.load(Ordering::SeqCst), 6);
+    let max_active = client.max_active.load(Ordering::SeqCst);
+    assert!(max_active > 1 && max_active <= 3, "{}", max_active);
+}
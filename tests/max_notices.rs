@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use mightybadger::testing::FakeReporter;
+use mightybadger::HoneybadgerError;
+
+#[derive(Debug)]
+struct LoopError(u32);
+
+impl std::fmt::Display for LoopError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "iteration {} failed", self.0)
+    }
+}
+
+impl std::error::Error for LoopError {}
+
+#[test]
+fn test_max_notices_per_process() {
+    let client = Arc::new(FakeReporter::new());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(http_client);
+        config.max_notices_per_process = Some(2);
+    });
+
+    for i in 0..5 {
+        mightybadger::notify_std_error(&LoopError(i));
+    }
+    let payloads = client.payloads();
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].error.message, "iteration 0 failed");
+    assert_eq!(payloads[1].error.message, "iteration 1 failed");
+    assert!(matches!(
+        mightybadger::trigger_test_notice(),
+        Err(HoneybadgerError::NoticeLimitReached(2, _))
+    ));
+}
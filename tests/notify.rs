@@ -0,0 +1,681 @@
+use std::sync::{Arc, Mutex};
+
+use lazy_static::lazy_static;
+use mightybadger::testing::FakeReporter;
+use mightybadger::Payload;
+
+lazy_static! {
+    static ref TEST_GUARD: Mutex<()> = Mutex::new(());
+}
+
+/// Runs `f` with a recording client and returns the payloads it would have sent.
+fn capture<F: FnOnce()>(f: F) -> Vec<Payload> {
+    let _guard = TEST_GUARD.lock().unwrap_or_else(|e| e.into_inner());
+    let client = Arc::new(FakeReporter::new());
+    mightybadger::configure(|config| {
+        *config = Default::default();
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(client.clone());
+    });
+    f();
+    client.payloads()
+}
+
+#[test]
+fn test_notify_here() {
+    let line = line!() + 3;
+    let payloads = capture(|| {
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error_here(&error);
+    });
+    assert_eq!(payloads.len(), 1);
+    let frame = &payloads[0].error.backtrace.as_ref().unwrap()[0];
+    assert_eq!(frame.method, "<call site>");
+    assert_eq!(frame.file.as_deref(), Some(file!()));
+    assert_eq!(frame.number, Some(line.to_string()));
+}
+
+#[test]
+fn test_async_send() {
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.async_send = Some(true);
+        });
+        let _flush_guard = mightybadger::flush_on_exit();
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+    });
+    assert_eq!(payloads.len(), 1);
+}
+
+#[test]
+fn test_async_send_detected_revision() {
+    let root = std::env::temp_dir().join(format!("mightybadger-revision-{}", std::process::id()));
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    std::fs::write(root.join(".git/HEAD"), "0123abcd\n").unwrap();
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.root = Some(root.to_str().unwrap().to_owned());
+            config.async_send = Some(true);
+        });
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+        mightybadger::flush();
+    });
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].server.revision.as_deref(), Some("0123abcd"));
+}
+
+#[test]
+fn test_async_send_keeps_preprocessed_server() {
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.async_send = Some(true);
+        });
+        mightybadger::set_notice_preprocessor(Box::new(|payload| {
+            payload.server.hostname = Some("preprocessed".to_owned());
+        }));
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+        mightybadger::flush();
+        mightybadger::set_notice_preprocessor(Box::new(|_| {}));
+    });
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].server.hostname.as_deref(), Some("preprocessed"));
+}
+
+#[test]
+fn test_preprocessor_replaces_itself() {
+    let payloads = capture(|| {
+        mightybadger::set_notice_preprocessor(Box::new(|payload| {
+            payload.error.tags.push("first".to_owned());
+            mightybadger::set_notice_preprocessor(Box::new(|_| {}));
+        }));
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+        mightybadger::notify_std_error(&error);
+    });
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].error.tags, vec!["first".to_owned()]);
+    assert!(payloads[1].error.tags.is_empty());
+}
+
+#[test]
+fn test_skip_preflight() {
+    let mut request = mightybadger::payload::RequestInfo::default();
+    request
+        .cgi_data
+        .insert("REQUEST_METHOD".to_owned(), "OPTIONS".to_owned());
+    let error = "x".parse::<i32>().unwrap_err();
+    let payloads = capture(|| {
+        mightybadger::context::with(&request, || mightybadger::notify_std_error(&error));
+    });
+    assert_eq!(payloads.len(), 0);
+
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.request.report_preflight = Some(true);
+        });
+        mightybadger::context::with(&request, || mightybadger::notify_std_error(&error));
+    });
+    assert_eq!(payloads.len(), 1);
+}
+
+#[test]
+fn test_ignore_paths() {
+    let mut request = mightybadger::payload::RequestInfo {
+        url: "http://localhost:8080/healthz".to_owned(),
+        ..Default::default()
+    };
+    let error = "x".parse::<i32>().unwrap_err();
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.request.ignore_paths = Some(vec!["/healthz".to_owned()]);
+        });
+        mightybadger::context::with(&request, || mightybadger::notify_std_error(&error));
+        request.url = "http://localhost:8080/users".to_owned();
+        mightybadger::context::with(&request, || mightybadger::notify_std_error(&error));
+    });
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(
+        payloads[0].request.as_ref().unwrap().url,
+        "http://localhost:8080/users"
+    );
+}
+
+#[test]
+fn test_deferred_stats() {
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.async_send = Some(true);
+            config.deferred_stats = Some(true);
+        });
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+        mightybadger::flush();
+    });
+    assert_eq!(payloads.len(), 1);
+    if cfg!(target_os = "linux") {
+        assert!(payloads[0].server.stats.mem.is_some());
+    }
+}
+
+#[derive(Debug)]
+struct TimeoutError {
+    endpoint: &'static str,
+}
+
+impl std::fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request to {} timed out", self.endpoint)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+impl mightybadger::Fingerprint for TimeoutError {
+    fn fingerprint(&self) -> Option<String> {
+        Some(format!("timeout:{}", self.endpoint))
+    }
+}
+
+#[test]
+fn test_fingerprint() {
+    mightybadger::fingerprint::register_std_error::<TimeoutError>();
+    let error = TimeoutError { endpoint: "/users" };
+    let payloads = capture(|| {
+        mightybadger::notify_std_error(&error);
+        mightybadger::notify(&error);
+        mightybadger::notify_std_error_with_fingerprint(&error, "explicit");
+        mightybadger::notify_std_error(&"x".parse::<i32>().unwrap_err());
+    });
+    let fingerprints = payloads
+        .iter()
+        .map(|payload| payload.error.fingerprint.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fingerprints,
+        ["timeout:/users", "timeout:/users", "explicit", ""]
+    );
+}
+
+#[test]
+fn test_notify_if_error() {
+    let payloads = capture(|| {
+        assert_eq!(
+            mightybadger::notify_std_if_error(|| "42".parse::<i32>()),
+            Ok(42)
+        );
+        assert!(mightybadger::notify_std_if_error(|| "x".parse::<i32>()).is_err());
+        let result: Result<(), _> =
+            mightybadger::notify_if_error(|| Err(failure::err_msg("failed").compat()));
+        assert!(result.is_err());
+    });
+    let messages = payloads
+        .iter()
+        .map(|payload| payload.error.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["invalid digit found in string", "failed"]);
+}
+
+struct NoopWaker;
+
+impl std::task::Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Polls a future which completes without waiting.
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    let mut fut = Box::pin(fut);
+    let waker = std::task::Waker::from(Arc::new(NoopWaker));
+    let mut cx = std::task::Context::from_waker(&waker);
+    loop {
+        if let std::task::Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn test_notify_future() {
+    let payloads = capture(|| {
+        let ok = mightybadger::notify_future_std_error(async { "42".parse::<i32>() });
+        assert_eq!(block_on(ok), Ok(42));
+        let err = mightybadger::notify_future_std_error(async { "x".parse::<i32>() });
+        assert!(block_on(err).is_err());
+        let err = mightybadger::notify_future(async {
+            Err::<(), _>(failure::err_msg("failed").compat())
+        });
+        assert!(block_on(err).is_err());
+    });
+    let messages = payloads
+        .iter()
+        .map(|payload| payload.error.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["invalid digit found in string", "failed"]);
+}
+
+/// Returns `Pending` once before completing.
+#[derive(Default)]
+struct YieldOnce(bool);
+
+impl std::future::Future for YieldOnce {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<()> {
+        if self.0 {
+            std::task::Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    }
+}
+
+#[test]
+fn test_instrument() {
+    let payloads = capture(|| {
+        let request = mightybadger::payload::RequestInfo {
+            component: "worker".to_owned(),
+            ..Default::default()
+        };
+        let task = mightybadger::context::instrument(request, async {
+            YieldOnce::default().await;
+            mightybadger::notify_std_error(&"x".parse::<i32>().unwrap_err());
+        });
+        block_on(task);
+        mightybadger::notify_std_error(&"x".parse::<i32>().unwrap_err());
+    });
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].request.as_ref().unwrap().component, "worker");
+    assert!(payloads[1].request.is_none());
+}
+
+#[cfg(feature = "log-filter")]
+#[test]
+fn test_error_filter() {
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.error_filter = Some("std::num=off".to_owned());
+        });
+        mightybadger::notify_std_error(&"x".parse::<i32>().unwrap_err());
+        mightybadger::notify_std_error(&"x".parse::<f64>().unwrap_err());
+        mightybadger::notify(&failure::err_msg("failed").compat());
+    });
+    assert_eq!(payloads.len(), 1);
+}
+
+#[test]
+fn test_capture_process_info() {
+    let payloads = capture(|| {
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+        mightybadger::configure(|config| {
+            config.capture_process_info = Some(true);
+        });
+        mightybadger::notify_std_error(&error);
+    });
+    assert!(payloads[0].request.is_none());
+    let context = &payloads[1].request.as_ref().unwrap().context;
+    let args = std::env::args().collect::<Vec<_>>();
+    assert_eq!(context["args"], serde_json::json!(args));
+    let cwd = std::env::current_dir().unwrap();
+    assert_eq!(context["cwd"], cwd.to_str().unwrap());
+}
+
+#[test]
+fn test_notify_boxed() {
+    let payloads = capture(|| {
+        let error: Box<dyn std::error::Error + Send + Sync> =
+            Box::new("x".parse::<i32>().unwrap_err());
+        mightybadger::notify_boxed(error);
+        let error: Box<dyn std::error::Error + Send + Sync> =
+            Box::new(std::io::Error::new(std::io::ErrorKind::Other, "disk full"));
+        mightybadger::notify(&mightybadger::Error(error));
+    });
+    let errors = payloads
+        .iter()
+        .map(|payload| (payload.error.class.as_str(), payload.error.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            ("std::num::ParseIntError", "invalid digit found in string"),
+            ("std::io::Error", "disk full"),
+        ]
+    );
+}
+
+#[test]
+fn test_io_error_context() {
+    let payloads = capture(|| {
+        let error = std::fs::File::open("/nonexistent/mightybadger").unwrap_err();
+        mightybadger::notify_std_error(&error);
+        mightybadger::notify_std_error(&std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "disk full",
+        ));
+    });
+    assert_eq!(payloads.len(), 2);
+    let context = &payloads[0].request.as_ref().unwrap().context;
+    #[cfg(unix)]
+    assert_eq!(context["errno"], 2); // ENOENT
+    assert!(context["errno"].is_i64());
+    assert_eq!(context["io_error_kind"], "NotFound");
+    let context = &payloads[1].request.as_ref().unwrap().context;
+    assert!(context.get("errno").is_none());
+    assert_eq!(context["io_error_kind"], "Other");
+}
+
+#[test]
+fn test_with_span() {
+    let payloads = capture(|| {
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::with_span!(name: "import", {
+            mightybadger::with_span!(name: "csv", action: "parse", {
+                mightybadger::notify_std_error(&error);
+            });
+        });
+        mightybadger::notify_std_error(&error);
+    });
+    assert_eq!(payloads[0].error.tags, ["import", "csv"]);
+    let request = payloads[0].request.as_ref().unwrap();
+    assert_eq!(request.component, "csv");
+    assert_eq!(request.action, "parse");
+    assert!(payloads[1].error.tags.is_empty());
+    assert!(payloads[1].request.is_none());
+}
+
+#[test]
+fn test_severity() {
+    let payloads = capture(|| {
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+        mightybadger::notify_std_error_with_severity(&error, "critical");
+        mightybadger::configure(|config| {
+            config.default_severity = Some("warning".to_owned());
+        });
+        mightybadger::notify_std_error(&error);
+        mightybadger::notify_std_error_with_severity(&error, "critical");
+    });
+    let tags = payloads
+        .iter()
+        .map(|payload| payload.error.tags.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tags,
+        [
+            vec![],
+            vec!["severity:critical"],
+            vec!["severity:warning"],
+            vec!["severity:critical"],
+        ]
+    );
+}
+
+#[test]
+fn test_warn() {
+    let payloads = capture(|| {
+        let error = "x".parse::<i32>().unwrap_err();
+        mightybadger::warn_std_error(&error);
+        mightybadger::warn(failure::err_msg("falling back to defaults").as_fail());
+    });
+    let tags = payloads
+        .iter()
+        .map(|payload| payload.error.tags.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(tags, [vec!["severity:warning"], vec!["severity:warning"]]);
+}
+
+#[test]
+fn test_sample_success() {
+    let payloads = capture(|| {
+        mightybadger::sample_success(200);
+        mightybadger::configure(|config| {
+            config.success_sample_rate = mightybadger::config::SampleRate::new(1.0);
+        });
+        mightybadger::sample_success(500);
+        for _ in 0..20 {
+            mightybadger::sample_success(204);
+        }
+    });
+    // Limited to 10 per minute.
+    assert_eq!(payloads.len(), 10);
+    let error = &payloads[0].error;
+    assert_eq!(error.class, "SuccessSample");
+    assert_eq!(error.message, "Sampled successful request (status 204)");
+    assert_eq!(error.tags, ["sample:success", "severity:info"]);
+}
+
+#[test]
+fn test_with_error_boundary() {
+    let payloads = capture(|| {
+        let result = mightybadger::context::with_error_boundary("plugin", || {
+            panic!("plugin failed");
+        });
+        assert!(result.is_err());
+        let result = mightybadger::context::with_error_boundary("plugin", || 42);
+        assert_eq!(result.unwrap(), 42);
+    });
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].error.class, "mightybadger::Panic");
+    assert_eq!(payloads[0].error.message, "plugin failed");
+    assert_eq!(payloads[0].error.tags, ["plugin"]);
+}
+
+#[test]
+fn test_capture_panics() {
+    let payloads = capture(|| {
+        let result = mightybadger::capture_panics(|| panic!("captured"));
+        assert!(result.is_err());
+        assert_eq!(mightybadger::capture_panics(|| 42).unwrap(), 42);
+        mightybadger::rayon_panic_handler(Box::new("from pool"));
+    });
+    let messages = payloads
+        .iter()
+        .map(|payload| payload.error.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["captured", "from pool"]);
+    assert!(payloads[0].error.tags.is_empty());
+}
+
+#[test]
+fn test_per_class_rate_limit() {
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.per_class_rate_limit = Some((2, std::time::Duration::from_secs(3600)));
+        });
+        let error = "x".parse::<i32>().unwrap_err();
+        for _ in 0..5 {
+            mightybadger::notify_std_error(&error);
+        }
+        let error = std::io::Error::new(std::io::ErrorKind::Other, "disk full");
+        mightybadger::notify_std_error(&error);
+    });
+    let classes = payloads
+        .iter()
+        .map(|payload| payload.error.class.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        classes,
+        [
+            "std::num::ParseIntError",
+            "std::num::ParseIntError",
+            "std::io::Error"
+        ]
+    );
+}
+
+#[test]
+fn test_record_exception() {
+    let payloads = capture(|| {
+        let error = failure::err_msg("cache miss");
+        mightybadger::context::record_exception(error.as_fail(), &["cache"]);
+        let error = failure::err_msg("retrying");
+        mightybadger::context::record_exception(error.as_fail(), &["http", "cache"]);
+        let error = failure::err_msg("invalid email");
+        mightybadger::context::record_error(error.as_fail());
+        mightybadger::context::flush_exceptions();
+        mightybadger::context::flush_exceptions();
+    });
+    assert_eq!(payloads.len(), 1);
+    let error = &payloads[0].error;
+    assert_eq!(error.message, "cache miss");
+    assert_eq!(error.tags, ["cache", "http"]);
+    assert!(error.token.is_some());
+    let causes = error
+        .causes
+        .iter()
+        .map(|cause| cause.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(causes, ["retrying", "invalid email"]);
+}
+
+#[test]
+fn test_record_exception_separately() {
+    use mightybadger::context::Aggregation;
+
+    let payloads = capture(|| {
+        let error = failure::err_msg("cache miss");
+        mightybadger::context::record_exception(error.as_fail(), &["cache"]);
+        let error = failure::err_msg("invalid email");
+        mightybadger::context::record_error(error.as_fail());
+        mightybadger::context::flush_exceptions_with(Aggregation::Separate);
+        mightybadger::context::flush_exceptions_with(Aggregation::Separate);
+    });
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].error.message, "cache miss");
+    assert_eq!(payloads[0].error.tags, ["cache"]);
+    assert!(payloads[0].error.causes.is_empty());
+    assert_eq!(payloads[1].error.message, "invalid email");
+    assert!(payloads[1].error.tags.is_empty());
+    assert_ne!(payloads[0].error.token, payloads[1].error.token);
+}
+
+#[test]
+fn test_notify_cause_chain_separately() {
+    use failure::ResultExt;
+
+    let payloads = capture(|| {
+        let error = "x"
+            .parse::<i32>()
+            .context("invalid port")
+            .context("could not load config")
+            .unwrap_err();
+        mightybadger::notify_cause_chain_separately(&error);
+    });
+    let messages = payloads
+        .iter()
+        .map(|payload| payload.error.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "could not load config",
+            "invalid port",
+            "invalid digit found in string"
+        ]
+    );
+    let parent_id = payloads[0].error.token.unwrap().to_string();
+    assert!(payloads[0].request.is_none());
+    for payload in &payloads[1..] {
+        let context = &payload.request.as_ref().unwrap().context;
+        assert_eq!(context["parent_honeybadger_id"], parent_id.as_str());
+    }
+}
+
+#[test]
+fn test_simulate_panic() {
+    let payloads = capture(|| {
+        mightybadger::simulate_panic("simulated");
+    });
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].error.class, "mightybadger::Panic");
+    assert_eq!(payloads[0].error.message, "simulated");
+    let backtrace = payloads[0].error.backtrace.as_ref().unwrap();
+    assert!(backtrace[0].method.contains("test_simulate_panic"));
+}
+
+#[test]
+fn test_api_key_override() {
+    let request = mightybadger::payload::RequestInfo {
+        api_key_override: Some("tenant-key".to_owned()),
+        ..Default::default()
+    };
+    let error = "x".parse::<i32>().unwrap_err();
+    let payloads = capture(|| {
+        mightybadger::context::with(&request, || {
+            mightybadger::notify_std_error(&error);
+        });
+        mightybadger::notify_std_error(&error);
+    });
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].api_key, "tenant-key");
+    let request = payloads[0].request.as_ref().unwrap();
+    assert_eq!(request.api_key_override, None);
+    let json = serde_json::to_string(request).unwrap();
+    assert!(!json.contains("tenant-key"));
+    assert_eq!(payloads[1].api_key, "abcdef");
+}
+
+#[test]
+fn test_response_headers() {
+    let error = "x".parse::<i32>().unwrap_err();
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.request.response_headers =
+                Some(vec!["x-error-code".to_owned(), "x-password".to_owned()]);
+        });
+        let mut request = mightybadger::payload::RequestInfo::default();
+        request.capture_response_headers(vec![
+            ("Content-Type", "text/html"),
+            ("X-Error-Code", "db_timeout"),
+            ("x-password", "hunter2"),
+        ]);
+        mightybadger::context::with(&request, || mightybadger::notify_std_error(&error));
+    });
+    assert_eq!(payloads.len(), 1);
+    let request = payloads[0].request.as_ref().unwrap();
+    assert_eq!(
+        request.context["response_headers"],
+        serde_json::json!({"X-Error-Code": "db_timeout", "x-password": "[FILTERED]"})
+    );
+}
+
+#[test]
+fn test_log_url_template() {
+    let error = "x".parse::<i32>().unwrap_err();
+    let payloads = capture(|| {
+        mightybadger::configure(|config| {
+            config.env = Some("production".to_owned());
+            config.log_url_template = Some(
+                "https://logs.example.com/{env}/search?request_id={request_id}&notice={notice_id}"
+                    .to_owned(),
+            );
+        });
+        let mut request = mightybadger::payload::RequestInfo::default();
+        request
+            .cgi_data
+            .insert("HTTP_X_REQUEST_ID".to_owned(), "req-42".to_owned());
+        mightybadger::context::with(&request, || mightybadger::notify_std_error(&error));
+        // No link without a request ID.
+        mightybadger::notify_std_error(&error);
+    });
+    assert_eq!(payloads.len(), 2);
+    let token = payloads[0].error.token.unwrap();
+    let request = payloads[0].request.as_ref().unwrap();
+    assert_eq!(
+        request.context["log_url"],
+        format!(
+            "https://logs.example.com/production/search?request_id=req-42&notice={}",
+            token
+        )
+    );
+    let request = payloads[1].request.as_ref();
+    assert!(!matches!(request, Some(request) if request.context.contains_key("log_url")));
+}
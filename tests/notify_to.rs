@@ -0,0 +1,33 @@
+use mightybadger::config::ConnectionConfig;
+use mightybadger_test_server::sync::{TestServer, TestServerConfig};
+
+#[test]
+fn test_notify_to() {
+    let server1 = TestServer::new();
+    let server2 = TestServer::with_config(TestServerConfig {
+        require_api_key: Some("subsystem".to_owned()),
+        ..Default::default()
+    });
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(server1.addr().port());
+    });
+    let connection = ConnectionConfig {
+        port: Some(server2.addr().port()),
+        ..Default::default()
+    };
+    let error = "x".parse::<i32>().unwrap_err();
+    mightybadger::notify_std_error_to(Some("subsystem"), &connection, &error);
+    assert_eq!(server1.data().read().unwrap().errors.len(), 0);
+    assert_eq!(server2.data().read().unwrap().errors.len(), 1);
+
+    // Without a key, the global one is sent and rejected by the second server.
+    mightybadger::notify_std_error_to(None, &connection, &error);
+    assert_eq!(server2.data().read().unwrap().errors.len(), 1);
+
+    mightybadger::notify_std_error(&error);
+    assert_eq!(server1.data().read().unwrap().errors.len(), 1);
+    assert_eq!(server2.data().read().unwrap().errors.len(), 1);
+}
@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+use mightybadger::payload::RequestInfo;
+use mightybadger_test_server::sync::{TestServer, TestServerConfig};
+
+fn configure(server: &TestServer) {
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+    });
+}
+
+#[test]
+fn test_notify_tool() {
+    let server = TestServer::new();
+    configure(&server);
+    let mut request = RequestInfo::default();
+    request
+        .context
+        .insert("request_id".to_owned(), "req-1".into());
+    mightybadger::context::set(request);
+    let id = mightybadger::notify_tool(
+        "build::ProtocMissing",
+        "protoc could not be run",
+        &[("crate", "app")],
+    );
+    assert!(id.is_some());
+    {
+        let data = server.data().read().unwrap();
+        assert_eq!(data.errors.len(), 1);
+        let notice = &data.errors[0];
+        assert_eq!(notice.error.token, id);
+        assert_eq!(notice.error.class, "build::ProtocMissing");
+        assert_eq!(notice.error.message, "protoc could not be run");
+        let request = notice.request.as_ref().unwrap();
+        assert!(!request.context.contains_key("request_id"));
+        assert_eq!(request.context["crate"], "app");
+    }
+
+    // A slow server makes the report fail instead of blocking the build.
+    let slow_server = TestServer::with_config(TestServerConfig {
+        response_delay_ms: 2000,
+        ..Default::default()
+    });
+    configure(&slow_server);
+    mightybadger::configure(|config| {
+        config.connection.timeout = Some(Duration::from_millis(200));
+    });
+    let start = Instant::now();
+    assert_eq!(mightybadger::notify_tool("build::Slow", "slow", &[]), None);
+    assert!(start.elapsed() < Duration::from_millis(1500));
+}
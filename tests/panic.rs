@@ -1,6 +1,5 @@
 use mightybadger_test_server::sync::TestServer;
 use std::thread;
-use std::time::Duration;
 
 #[test]
 fn test_panic() {
@@ -13,13 +12,24 @@ fn test_panic() {
         config.connection.host = Some("127.0.0.1".to_owned());
         config.connection.port = Some(port);
     });
-    thread::sleep(Duration::from_millis(100));
     let th = thread::spawn(|| {
         panic!("panic test");
     });
     th.join().ok();
+    server.drain_pending();
+    assert_eq!(server.completed(), 1);
     {
         let data = server.data().read().unwrap();
         assert_eq!(data.errors.len(), 1);
     }
+
+    // Reported once, from the hook.
+    let result = mightybadger::context::with_error_boundary("handler", || {
+        panic!("boundary test");
+    });
+    assert!(result.is_err());
+    {
+        let data = server.data().read().unwrap();
+        assert_eq!(data.errors.len(), 2);
+    }
 }
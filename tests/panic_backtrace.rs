@@ -0,0 +1,40 @@
+use std::env;
+use std::sync::Arc;
+use std::thread;
+
+use mightybadger::testing::FakeReporter;
+
+#[inline(never)]
+fn panicking_function() {
+    panic!("panic without backtrace variables");
+}
+
+#[test]
+fn test_panic_backtrace_without_env() {
+    env::remove_var("RUST_BACKTRACE");
+    env::remove_var("RUST_LIB_BACKTRACE");
+    env::remove_var("RUST_FAILURE_BACKTRACE");
+
+    let client = Arc::new(FakeReporter::new());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(http_client);
+    });
+    mightybadger::install_hook();
+    thread::spawn(panicking_function).join().ok();
+
+    // Cloned so that a failed assertion, reported by the hook, does not deadlock.
+    let payloads = client.payloads();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].error.class, "mightybadger::Panic");
+    let backtrace = payloads[0].error.backtrace.as_ref().unwrap();
+    assert!(
+        backtrace
+            .iter()
+            .any(|frame| frame.method.contains("panicking_function")),
+        "{:#?}",
+        backtrace
+    );
+    assert!(!backtrace[0].is_noise(), "{:#?}", backtrace[0]);
+}
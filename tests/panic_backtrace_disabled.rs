@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use std::thread;
+
+use mightybadger::testing::FakeReporter;
+
+#[test]
+fn test_panic_backtrace_disabled() {
+    mightybadger::disable_backtrace();
+
+    let client = Arc::new(FakeReporter::new());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(http_client);
+    });
+    mightybadger::install_hook();
+    thread::spawn(|| panic!("panic with backtraces disabled"))
+        .join()
+        .ok();
+
+    let payloads = client.payloads();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].error.class, "mightybadger::Panic");
+    let backtrace = payloads[0].error.backtrace.as_ref().unwrap();
+    assert!(backtrace.is_empty(), "{:#?}", backtrace);
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use std::thread;
+
+use mightybadger::config::SampleRate;
+use mightybadger::testing::FakeReporter;
+
+#[derive(Debug)]
+struct BrokenPipe;
+
+impl std::fmt::Display for BrokenPipe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("broken pipe")
+    }
+}
+
+impl std::error::Error for BrokenPipe {}
+
+#[test]
+fn test_panic_filter() {
+    let client = Arc::new(FakeReporter::new());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.http_client = Some(http_client);
+        config.panic_ignore_messages = Some(vec!["broken pipe".to_owned()]);
+    });
+    mightybadger::install_hook();
+
+    thread::spawn(|| panic!("write failed: broken pipe"))
+        .join()
+        .ok();
+    assert!(mightybadger::capture_panics(|| panic!("broken pipe")).is_err());
+    thread::spawn(|| panic!("out of range")).join().ok();
+    mightybadger::notify_std_error(&BrokenPipe);
+
+    mightybadger::configure(|config| {
+        config.panic_ignore_messages = None;
+        config.panic_sample_rate = SampleRate::new(0.0);
+    });
+    thread::spawn(|| panic!("sampled out")).join().ok();
+    mightybadger::notify_std_error(&BrokenPipe);
+
+    let payloads = client.payloads();
+    let messages = payloads
+        .iter()
+        .map(|payload| payload.error.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["out of range", "broken pipe", "broken pipe"]);
+}
@@ -0,0 +1,31 @@
+use mightybadger_test_server::sync::TestServer;
+
+#[test]
+fn test_notice_preprocessor() {
+    let server = TestServer::new();
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+    });
+    mightybadger::set_notice_preprocessor(Box::new(|payload| {
+        let context = &mut payload.request.as_mut().unwrap().context;
+        // Runs after filtering.
+        assert_eq!(context["password"], "[FILTERED]");
+        context.remove("job_id");
+        context.insert("preprocessed".to_string(), true.into());
+    }));
+    let error = failure::err_msg("job failed");
+    mightybadger::notify_with_extra(
+        error.as_fail(),
+        serde_json::json!({ "job_id": 42, "password": "secret" }),
+    );
+    let data = server.data().read().unwrap();
+    assert_eq!(data.errors.len(), 1);
+    let context = &data.errors[0].request.as_ref().unwrap().context;
+    assert!(context.get("job_id").is_none());
+    assert_eq!(context["preprocessed"], true);
+    assert_eq!(context["password"], "[FILTERED]");
+}
@@ -0,0 +1,67 @@
+use mightybadger_test_server::sync::{TestServer, TestServerConfig};
+use mightybadger_test_server::StatusCode;
+use std::env;
+use std::fs;
+
+#[test]
+fn test_replay_notices() {
+    let notices = mightybadger::subscribe();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        // Nothing listens here, as if offline.
+        config.connection.port = Some(1);
+    });
+    for i in 0..3 {
+        let error = format!("x{}", i).parse::<i32>().unwrap_err();
+        mightybadger::notify_std_error(&error);
+    }
+    let payloads = notices.try_iter().collect::<Vec<_>>();
+    assert_eq!(payloads.len(), 3);
+    let mut content = payloads
+        .iter()
+        .map(|payload| serde_json::to_string(&**payload).unwrap() + "\n")
+        .collect::<String>();
+    content.push_str("not a notice\n");
+    let path = env::temp_dir().join(format!("mightybadger-replay-{}.jsonl", std::process::id()));
+    fs::write(&path, content).unwrap();
+
+    let server = TestServer::with_config(TestServerConfig {
+        initial_status_queue: vec![StatusCode::CREATED, StatusCode::SERVICE_UNAVAILABLE],
+        ..Default::default()
+    });
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.connection.port = Some(port);
+    });
+
+    assert_eq!(mightybadger::replay_notices(&path).unwrap(), 1);
+    let rest = fs::read_to_string(&path).unwrap();
+    assert_eq!(rest.lines().count(), 3);
+    assert!(rest.ends_with("not a notice\n"));
+    // Written through a temporary file.
+    assert!(!path.with_extension("jsonl.tmp").exists());
+
+    assert_eq!(mightybadger::replay_notices(&path).unwrap(), 2);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "not a notice\n");
+
+    let data = server.data().read().unwrap();
+    let tokens = data
+        .errors
+        .iter()
+        .map(|error| error.error.token)
+        .collect::<Vec<_>>();
+    let expected = payloads
+        .iter()
+        .map(|payload| payload.error.token)
+        .collect::<Vec<_>>();
+    assert_eq!(tokens, expected);
+    drop(data);
+
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        mightybadger::replay_notices(&path),
+        Err(mightybadger::HoneybadgerError::NoticeFileFailed(..))
+    ));
+}
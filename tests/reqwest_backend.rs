@@ -0,0 +1,36 @@
+#![cfg(feature = "reqwest-backend")]
+
+use mightybadger::ReqwestHttpClient;
+use mightybadger_test_server::sync::TestServer;
+use std::sync::Arc;
+
+#[test]
+fn test_reqwest_backend() {
+    let server = TestServer::new();
+    let port = server.addr().port();
+    let error = "x".parse::<i32>().unwrap_err();
+    let clients = vec![
+        ReqwestHttpClient::new(),
+        ReqwestHttpClient::with_client(
+            reqwest::blocking::Client::builder()
+                .http2_prior_knowledge()
+                .build()
+                .unwrap(),
+        ),
+    ];
+    for client in clients {
+        mightybadger::configure(|config| {
+            config.api_key = Some("abcdef".to_owned());
+            config.connection.secure = Some(false);
+            config.connection.host = Some("127.0.0.1".to_owned());
+            config.connection.port = Some(port);
+            config.http_client = Some(Arc::new(client));
+        });
+        mightybadger::notify_std_error(&error);
+    }
+    assert_eq!(
+        mightybadger::last_report_status(),
+        Some(mightybadger::ReportOutcome::Success)
+    );
+    assert_eq!(server.data().read().unwrap().errors.len(), 2);
+}
@@ -0,0 +1,37 @@
+use mightybadger_test_server::sync::TestServer;
+
+#[test]
+fn test_empty_created_response() {
+    let server = TestServer::new();
+    let port = server.addr().port();
+    server.data().write().unwrap().empty_response = true;
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+    });
+    let id = mightybadger::trigger_test_notice().unwrap();
+    {
+        let data = server.data().read().unwrap();
+        assert_eq!(data.errors.len(), 1);
+        assert_eq!(data.errors[0].error.token, Some(id));
+    }
+
+    mightybadger::configure(|config| {
+        config.connection.strict_response = Some(true);
+    });
+    assert!(mightybadger::trigger_test_notice().is_err());
+
+    server.data().write().unwrap().empty_response = false;
+    assert!(mightybadger::trigger_test_notice().is_ok());
+    mightybadger::configure(|config| {
+        config.connection.max_response_body_bytes = Some(10);
+    });
+    match mightybadger::trigger_test_notice() {
+        Err(mightybadger::HoneybadgerError::ResponseDecodeFailed(e, _)) => {
+            assert!(e.to_string().contains("response body exceeds 10 bytes"));
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
@@ -0,0 +1,91 @@
+use lazy_static::lazy_static;
+use mightybadger::HoneybadgerError;
+use mightybadger_test_server::sync::{TestServer, TestServerConfig};
+use mightybadger_test_server::StatusCode;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    static ref TEST_GUARD: Mutex<()> = Mutex::new(());
+}
+
+#[test]
+fn test_retry_status_codes() {
+    let _guard = TEST_GUARD.lock().unwrap_or_else(|e| e.into_inner());
+    let server = TestServer::with_config(TestServerConfig {
+        initial_status_queue: vec![
+            StatusCode::BAD_GATEWAY,
+            StatusCode::GATEWAY_TIMEOUT,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ],
+        ..Default::default()
+    });
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+        config.connection.retries = Some(2);
+        config.connection.retry_status_codes = Some(vec![502, 504]);
+    });
+
+    // 502 and 504 are retried; 200ms and 400ms are waited.
+    let start = Instant::now();
+    assert!(matches!(
+        mightybadger::trigger_test_notice(),
+        Err(HoneybadgerError::UnknownResponse(..))
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(600));
+    assert_eq!(server.completed(), 3);
+
+    // 503 is not in the list.
+    assert!(matches!(
+        mightybadger::trigger_test_notice(),
+        Err(HoneybadgerError::TooManyRequests(..))
+    ));
+    assert_eq!(server.completed(), 4);
+
+    server.reset();
+    mightybadger::configure(|config| {
+        config.connection.retries = Some(4);
+        config.connection.retry_status_codes = Some(vec![502, 503, 504]);
+    });
+    let id = mightybadger::trigger_test_notice().unwrap();
+    assert_eq!(server.completed(), 9);
+    let data = server.data().read().unwrap();
+    assert_eq!(data.errors.len(), 1);
+    assert_eq!(data.errors[0].error.token, Some(id));
+}
+
+#[test]
+fn test_retry_after() {
+    let _guard = TEST_GUARD.lock().unwrap_or_else(|e| e.into_inner());
+    let server = TestServer::with_config(TestServerConfig {
+        initial_status_queue: vec![StatusCode::SERVICE_UNAVAILABLE],
+        retry_after: Some("1".to_owned()),
+        ..Default::default()
+    });
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+        config.connection.retries = Some(1);
+    });
+
+    let start = Instant::now();
+    let sender = thread::spawn(mightybadger::trigger_test_notice);
+    thread::sleep(Duration::from_millis(300));
+    // The configuration is not locked while waiting to retry.
+    mightybadger::configure(|config| {
+        config.env = Some("production".to_owned());
+    });
+    assert!(start.elapsed() < Duration::from_millis(900));
+    sender.join().unwrap().unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.completed(), 2);
+}
@@ -0,0 +1,32 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+use common::SlowClient;
+
+#[test]
+fn test_graceful_shutdown() {
+    let client = Arc::new(SlowClient::default());
+    let http_client = client.clone();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.async_send = Some(true);
+        config.http_client = Some(http_client);
+    });
+    let error = "x".parse::<i32>().unwrap_err();
+    mightybadger::notify_std_error(&error);
+    mightybadger::notify_std_error(&error);
+    match mightybadger::graceful_shutdown(Duration::from_millis(10)) {
+        Err(mightybadger::ShutdownError::Timeout(pending, _)) => assert!(pending > 0),
+        Ok(()) => panic!("expected a timeout"),
+    }
+    assert!(mightybadger::graceful_shutdown(Duration::from_secs(10)).is_ok());
+    assert_eq!(client.sent.load(Ordering::SeqCst), 2);
+
+    // New notices are dropped.
+    mightybadger::notify_std_error(&error);
+    assert!(mightybadger::graceful_shutdown(Duration::from_secs(10)).is_ok());
+    assert_eq!(client.sent.load(Ordering::SeqCst), 2);
+}
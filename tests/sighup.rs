@@ -0,0 +1,29 @@
+#![cfg(all(unix, feature = "signal"))]
+
+use std::env;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn test_sighup_reload() {
+    let (tx, rx) = mpsc::channel();
+    let tx = Mutex::new(tx);
+    mightybadger::configure(|config| {
+        config.hostname = Some("old.example.com".to_owned());
+        config.revision = Some("0123abc".to_owned());
+        config.on_config_reload = Some(Arc::new(move || {
+            tx.lock().unwrap().send(()).ok();
+        }));
+    });
+    mightybadger::install_sighup_reload().unwrap();
+
+    env::set_var("HONEYBADGER_HOSTNAME", "new.example.com");
+    env::remove_var("HONEYBADGER_REVISION");
+    signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    let config = mightybadger::config::read_config();
+    assert_eq!(config.hostname.as_deref(), Some("new.example.com"));
+    assert_eq!(config.revision.as_deref(), Some("0123abc"));
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use mightybadger::payload::{LoadInfo, MemoryInfo, ServerInfo};
+use mightybadger::StatsBackend;
+
+#[derive(Debug)]
+struct FixedStats;
+
+impl StatsBackend for FixedStats {
+    fn memory_info(&self) -> Option<MemoryInfo> {
+        Some(MemoryInfo {
+            total: Some(1024.0),
+            free_total: Some(256.0),
+            ..Default::default()
+        })
+    }
+
+    fn load_info(&self) -> Option<LoadInfo> {
+        Some(LoadInfo {
+            one: Some(f64::NAN),
+            five: Some(0.5),
+            fifteen: None,
+        })
+    }
+}
+
+#[test]
+fn test_stats_backend() {
+    mightybadger::configure(|config| {
+        config.stats_backend = Some(Arc::new(FixedStats));
+    });
+    let stats = ServerInfo::generate().stats;
+    assert_eq!(stats.mem.unwrap().total, Some(1024.0));
+    let load = stats.load.unwrap();
+    assert_eq!(load.one, None);
+    assert_eq!(load.five, Some(0.5));
+    assert_eq!(mightybadger::stats::memory_pressure(), Some(0.75));
+
+    mightybadger::configure(|config| {
+        config.stats_backend = Some(Arc::new(mightybadger::stats::NullStatsBackend));
+    });
+    let stats = ServerInfo::generate().stats;
+    assert!(stats.mem.is_none());
+    assert!(stats.load.is_none());
+    assert_eq!(mightybadger::stats::load_average_1min(), None);
+}
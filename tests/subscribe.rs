@@ -0,0 +1,28 @@
+use mightybadger_test_server::sync::TestServer;
+
+#[test]
+fn test_subscribe() {
+    let server = TestServer::new();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(server.addr().port());
+    });
+    let notices1 = mightybadger::subscribe();
+    let notices2 = mightybadger::subscribe();
+    let error = "x".parse::<i32>().unwrap_err();
+    mightybadger::notify_std_error(&error);
+    let payload = notices1.try_recv().unwrap();
+    assert_eq!(payload.error.message, "invalid digit found in string");
+    assert_eq!(
+        notices2.try_recv().unwrap().error.token,
+        payload.error.token
+    );
+    assert_eq!(server.data().read().unwrap().errors.len(), 1);
+
+    drop(notices2);
+    mightybadger::notify_std_error(&error);
+    assert!(notices1.try_recv().is_ok());
+    assert!(notices1.try_recv().is_err());
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use mightybadger::testing::FakeReporter;
+use mightybadger_test_server::sync::TestServer;
+
+#[test]
+fn test_trigger_test_notice() {
+    assert_eq!(mightybadger::last_report_status(), None);
+    let server = TestServer::new();
+    let port = server.addr().port();
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+    });
+    let id = mightybadger::trigger_test_notice().unwrap();
+    assert_eq!(
+        mightybadger::last_report_status(),
+        Some(mightybadger::ReportOutcome::Success)
+    );
+    {
+        let data = server.data().read().unwrap();
+        assert_eq!(data.errors.len(), 1);
+        assert_eq!(data.errors[0].error.token, Some(id));
+    }
+
+    mightybadger::configure(|config| {
+        config.report_data = Some(false);
+    });
+    assert!(mightybadger::trigger_test_notice().is_err());
+    {
+        let data = server.data().read().unwrap();
+        assert_eq!(data.errors.len(), 1);
+    }
+
+    // Queued like other notices.
+    let client = Arc::new(FakeReporter::new());
+    mightybadger::configure(|config| {
+        config.report_data = None;
+        config.async_send = Some(true);
+        config.http_client = Some(client.clone());
+    });
+    let id = mightybadger::trigger_test_notice().unwrap();
+    mightybadger::flush();
+    let payloads = client.payloads();
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(payloads[0].error.class, "TestNotice");
+    assert_eq!(payloads[0].error.token, Some(id));
+    drop(payloads);
+
+    // Limited like other notices.
+    mightybadger::configure(|config| {
+        config.async_send = None;
+        config.max_concurrent_sends = Some(0);
+    });
+    match mightybadger::trigger_test_notice() {
+        Err(mightybadger::HoneybadgerError::TooManyConcurrentSends(_)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(client.payloads().len(), 1);
+}
@@ -0,0 +1,72 @@
+use mightybadger::HoneybadgerError;
+use mightybadger_test_server::sync::{TestServer, TestServerConfig};
+use mightybadger_test_server::StatusCode;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+fn configure(server: &TestServer, api_key: &str) {
+    let port = server.addr().port();
+    let api_key = api_key.to_owned();
+    mightybadger::configure(|config| {
+        config.api_key = Some(api_key);
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+    });
+}
+
+#[test]
+fn test_server_config() {
+    let server = TestServer::with_config(TestServerConfig {
+        require_api_key: Some("abcdef".to_owned()),
+        initial_status_queue: vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::PAYMENT_REQUIRED,
+        ],
+        response_delay_ms: 100,
+        retry_after: Some("7".to_owned()),
+    });
+    configure(&server, "abcdef");
+
+    let start = Instant::now();
+    let error = mightybadger::trigger_test_notice().unwrap_err();
+    assert!(matches!(error, HoneybadgerError::TooManyRequests(..)));
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert_eq!(
+        error.context_json(),
+        Some(json!({ "kind": "too_many_requests", "status": 503, "retry_after": 7 }))
+    );
+    assert!(matches!(
+        mightybadger::trigger_test_notice(),
+        Err(HoneybadgerError::PaymentRequired(_))
+    ));
+    assert!(mightybadger::trigger_test_notice().is_ok());
+    assert_eq!(server.data().read().unwrap().errors.len(), 1);
+
+    configure(&server, "wrong-key-1234");
+    let error = mightybadger::trigger_test_notice().unwrap_err();
+    assert!(matches!(error, HoneybadgerError::Forbidden(..)));
+    assert_eq!(
+        error.context_json(),
+        Some(json!({ "kind": "forbidden", "status": 403, "api_key_hint": "...1234" }))
+    );
+    // The hint is of the key actually used.
+    let request = mightybadger::payload::RequestInfo {
+        api_key_override: Some("tenant-key-5678".to_owned()),
+        ..Default::default()
+    };
+    let error = mightybadger::context::with(&request, mightybadger::trigger_test_notice);
+    assert_eq!(
+        error.unwrap_err().context_json().unwrap()["api_key_hint"],
+        "...5678"
+    );
+
+    server.reset();
+    assert!(server.data().read().unwrap().errors.is_empty());
+    configure(&server, "abcdef");
+    assert!(matches!(
+        mightybadger::trigger_test_notice(),
+        Err(HoneybadgerError::TooManyRequests(..))
+    ));
+    assert_eq!(server.completed(), 6);
+}
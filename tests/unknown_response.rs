@@ -0,0 +1,49 @@
+use mightybadger::HoneybadgerError;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+#[test]
+fn test_unknown_response() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        // Read until the end of the JSON body.
+        while !request.ends_with(b"}") {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        let body = format!("upstream unavailable: {}", "x".repeat(1000));
+        write!(
+            stream,
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+    mightybadger::configure(|config| {
+        config.api_key = Some("abcdef".to_owned());
+        config.connection.secure = Some(false);
+        config.connection.host = Some("127.0.0.1".to_owned());
+        config.connection.port = Some(port);
+        config.connection.max_response_body_bytes = Some(10);
+    });
+    let e = mightybadger::trigger_test_notice().unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "unknown response from server: 502 Bad Gateway \"upstream un\""
+    );
+    match e {
+        HoneybadgerError::UnknownResponse(status, body, _) => {
+            assert_eq!(status.as_u16(), 502);
+            // Bounded by `max_response_body_bytes`.
+            assert_eq!(body, "upstream un");
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+    server.join().unwrap();
+}